@@ -1,21 +1,36 @@
+use bdk::TransactionDetails;
+
+use crate::backend::BackendConfig;
+use crate::bip85::Bip85Application;
 use crate::bitcoin_wallet::{bitcoin_test, generate_mnemonic_string, is_valid_bitcoin_address};
+use crate::cost_basis::{CostBasisMethod, CostBasisReport};
+use crate::fiat::{format_fiat, sats_to_fiat, FiatValuation, HttpPriceProvider, PriceSource};
+use crate::history_export::ExportFormat;
+use crate::labels::{get_label, LabelType};
+use crate::proxy::ProxyConfig;
+use crate::sweep::SweepTransaction;
+use crate::sync_scheduler::{ConnectionStatus, SyncScheduler};
+use crate::transaction_detail::{confirmations, TransactionDetail};
+use crate::units::{format_amount, format_input_amount, parse_amount, BitcoinUnit, NumberFormat};
 
 mod app_centrepanel;
 mod app_sidepanel;
 mod app_toppanel;
 
-use crate::wallet_file_manager::{encryption_test, EntryType, SyncData, WalletModel};
+use crate::wallet_file_manager::{
+    encryption_test, EntryType, JsonWallet, SyncData, WalletModel, DEFAULT_FINAL_CONFIRMATIONS,
+};
 
 use std::collections::HashMap;
 use std::sync::{mpsc, Arc, Mutex};
 use std::thread::JoinHandle;
+use std::time::Instant;
 
 const FILENAME: &str = "./wallet.txt";
 const PASSWORD_NEEDED_TIMEOUT_S: i64 = 300;
-use chrono::{DateTime, Duration};
+use chrono::{DateTime, Duration, Local, TimeZone};
 
 use egui::InnerResponse;
-use std::num::ParseIntError;
 #[derive(PartialEq, Clone)]
 pub enum CentralPanelState {
     WalletFileNotAvailable,
@@ -25,13 +40,51 @@ pub enum CentralPanelState {
     WalletMain,
     SendingMain,
     ReceivingMain,
+    ReceivingSweep {
+        sweep_transaction: Option<SweepTransaction>,
+    },
+    ReceivingPaymentRequests,
     ContactsMain,
     SettingsMain,
     WalletDelete,
     WalletRename,
     WalletSecret,
     WalletNewWallet { mnemonic_string: String },
+    WalletBackupQuiz {
+        mnemonic_string: String,
+        word_indices: Vec<usize>,
+        source: Box<CentralPanelState>,
+        destination: Box<CentralPanelState>,
+    },
     WalletExistingWallet,
+    WalletDescriptors,
+    WalletCostBasis {
+        method: CostBasisMethod,
+        report: Option<CostBasisReport>,
+    },
+    WalletExportHistory {
+        format: ExportFormat,
+        all_wallets: bool,
+    },
+    WalletImportDescriptors,
+    WalletUtxos,
+    WalletTransactionDetail {
+        detail: TransactionDetail,
+    },
+    WalletNewMultisig {
+        cosigner_keys: Vec<String>,
+    },
+    WalletMultisigPsbt {
+        psbt: String,
+    },
+    WalletBip85 {
+        application: Bip85Application,
+        derived: Option<String>,
+    },
+    WalletNewPolicy {
+        policy_keys: Vec<(String, String)>,
+        preview: Option<String>,
+    },
     SettingsChangePassword,
     ContactsNewContact,
     ContactsRename { pub_key: String },
@@ -61,10 +114,16 @@ pub struct DialogLineEdit {
 #[derive(Clone)]
 pub enum DialogBoxEnum {
     IncorrectMnemonic,
+    BackupVerified,
     WalletCreated,
     ConfirmSend,
     InvalidTransaction,
+    ConfirmationsLost,
     ChangeContactName { pub_key: String },
+    EditLabel {
+        label_type: LabelType,
+        reference: String,
+    },
 }
 
 pub struct MyApp {
@@ -74,11 +133,22 @@ pub struct MyApp {
     sync_data_receiver: mpsc::Receiver<SyncData>,
     sync_data_sender: mpsc::Sender<SyncData>,
     active_threads: Arc<Mutex<HashMap<String, JoinHandle<()>>>>,
-    string_scratchpad: [String; 3],
+    sweep_worker: Option<JoinHandle<()>>,
+    string_scratchpad: [String; 4],
     dialog_box: Option<DialogBox>,
+    // Shown one at a time once no other dialog is open
+    pending_notifications: Vec<DialogBox>,
     last_interaction_time: DateTime<chrono::Local>,
     folder_path: String,
     password_needed_timeout_s: i64,
+    spending_path: usize,
+    display_unit: BitcoinUnit,
+    number_format: NumberFormat,
+    fiat_valuation: FiatValuation,
+    fiat_input: bool,
+    sync_scheduler: SyncScheduler,
+    backend_config: BackendConfig,
+    final_confirmations: u32,
 }
 
 impl MyApp {
@@ -93,11 +163,61 @@ impl MyApp {
                     .wallet_model
                     .rename_wallet(EntryType::Contact, pub_key, &wallet_name);
             }
+            DialogBoxEnum::EditLabel {
+                label_type,
+                reference,
+            } => {
+                let _ = self
+                    .wallet_model
+                    .set_label(*label_type, reference, &edited_lines[0]);
+            }
 
             DialogBoxEnum::ConfirmSend { .. } => {
                 let recipient_addr = self.string_scratchpad[0].clone();
-                let amount = (&self.string_scratchpad[1]).parse().unwrap();
-                self.wallet_model.send_transaction(&recipient_addr, amount);
+                let amount = self.parse_amount(&self.string_scratchpad[1]).unwrap();
+                let wallet = self.wallet_model.get_active_wallet_data();
+                if wallet.requires_psbt() {
+                    let psbt = match wallet.policy {
+                        Some(_) => self.wallet_model.create_policy_psbt(
+                            &recipient_addr,
+                            amount,
+                            self.spending_path,
+                        ),
+                        None => self
+                            .wallet_model
+                            .create_multisig_psbt(&recipient_addr, amount),
+                    };
+                    match psbt {
+                        Ok(psbt) => {
+                            self.change_state(CentralPanelState::WalletMultisigPsbt { psbt })
+                        }
+                        Err(error) => {
+                            self.dialog_box = Some(DialogBox {
+                                dialog_box_enum: DialogBoxEnum::InvalidTransaction,
+                                title: "Invalid Transaction",
+                                dialog_line_edit: Vec::from([DialogLineEdit {
+                                    message: Some(error.to_string()),
+                                    line_edit: None,
+                                }]),
+                                optional: false,
+                            });
+                            return;
+                        }
+                    }
+                } else if let Err(error) =
+                    self.wallet_model.send_transaction(&recipient_addr, amount)
+                {
+                    self.dialog_box = Some(DialogBox {
+                        dialog_box_enum: DialogBoxEnum::InvalidTransaction,
+                        title: "Transaction Not Sent",
+                        dialog_line_edit: Vec::from([DialogLineEdit {
+                            message: Some(error.to_string()),
+                            line_edit: None,
+                        }]),
+                        optional: false,
+                    });
+                    return;
+                }
                 self.clear_string_scratchpad();
             }
             _ => {}
@@ -105,6 +225,23 @@ impl MyApp {
         self.dialog_box = None;
     }
 
+    fn open_label_dialog(&mut self, label_type: LabelType, reference: String) {
+        let wallet = self.wallet_model.get_active_wallet_data();
+        let label = get_label(&wallet.labels, label_type, &reference).unwrap_or_default();
+        self.dialog_box = Some(DialogBox {
+            dialog_box_enum: DialogBoxEnum::EditLabel {
+                label_type,
+                reference: reference.clone(),
+            },
+            title: "Edit Label",
+            dialog_line_edit: Vec::from([DialogLineEdit {
+                message: Some(reference),
+                line_edit: Some(label.to_string()),
+            }]),
+            optional: true,
+        });
+    }
+
     fn render_dialog_box(&mut self, ctx: &egui::Context) -> InnerResponse<Option<()>> {
         let response = egui::Window::new(self.dialog_box.as_ref().unwrap().title)
             .collapsible(false)
@@ -153,7 +290,7 @@ impl MyApp {
         let dialog_box = None;
         let active_threads = Arc::new(Mutex::new(HashMap::new()));
         let last_interaction_time = chrono::offset::Local::now();
-        let string_scratchpad = [String::new(), String::new(), String::new()];
+        let string_scratchpad = [String::new(), String::new(), String::new(), String::new()];
         let folder_path = "/Users/shaun/Dropbox/Mac/Documents".to_string();
         let password_needed_timeout_s = 300;
         let slf = Self {
@@ -163,11 +300,23 @@ impl MyApp {
             sync_data_sender: sync_data_sender,
             sync_data_receiver: sync_data_receiver,
             active_threads: active_threads,
+            sweep_worker: None,
             dialog_box: dialog_box,
+            pending_notifications: Vec::new(),
             last_interaction_time: last_interaction_time,
             string_scratchpad: string_scratchpad,
             folder_path: folder_path,
             password_needed_timeout_s: password_needed_timeout_s,
+            spending_path: 0,
+            display_unit: BitcoinUnit::Sats,
+            number_format: NumberFormat::from_environment(),
+            fiat_valuation: FiatValuation::new(Arc::new(HttpPriceProvider::new(
+                &ProxyConfig::default(),
+            ))),
+            fiat_input: false,
+            sync_scheduler: SyncScheduler::new(),
+            backend_config: BackendConfig::default(),
+            final_confirmations: DEFAULT_FINAL_CONFIRMATIONS,
         };
 
         slf
@@ -199,13 +348,18 @@ impl MyApp {
             invalid_transaction_vec.push("Invalid Bitcoin Address".to_string());
         }
 
+        let wallet = self.wallet_model.get_active_wallet_data();
+        if wallet.is_watch_only() && !wallet.requires_psbt() {
+            valid = false;
+            invalid_transaction_vec.push("Watch-only wallet can't sign transactions".to_string());
+        }
+
         if self.is_own_address(recipient_address_string) {
             valid = false;
             invalid_transaction_vec.push("Can't send to own address".to_string());
         }
 
-        let result: Result<u64, ParseIntError> = amount_to_send_string.parse();
-        match result {
+        match self.parse_amount(amount_to_send_string) {
             Ok(amount) => {
                 let total = self.wallet_model.get_active_wallet_data().get_total();
                 if amount > total {
@@ -214,63 +368,287 @@ impl MyApp {
                         .push("Insufficient funds in wallet for requested transaction".to_string())
                 }
             }
-            Err(_) => {
+            Err(error) => {
                 valid = false;
-                invalid_transaction_vec.push("Amount needs to be a number".to_string());
+                invalid_transaction_vec.push(error.to_string());
             }
         }
 
         return (valid, invalid_transaction_vec);
     }
 
-    fn is_own_address(&self, recipient_address_string: &str) -> bool {
-        let address = self.wallet_model.get_active_wallet_pub_key();
+    fn format_amount(&self, sats: u64) -> String {
+        return format_amount(sats, self.display_unit, self.number_format);
+    }
 
-        return recipient_address_string == address;
+    fn format_input_amount(&self, sats: u64) -> String {
+        return format_input_amount(sats, self.display_unit, self.number_format);
     }
 
-    fn wallet_poll(&mut self) {
-        let active_wallet_pub_key = self.wallet_model.get_active_wallet_pub_key();
+    fn parse_amount(&self, amount: &str) -> Result<u64, anyhow::Error> {
+        return parse_amount(amount, self.display_unit, self.number_format);
+    }
 
-        let sync_data_channel_clone = self.sync_data_sender.clone();
+    fn format_fiat_amount(&self, sats: u64, price: Option<f64>) -> String {
+        match price {
+            Some(price) => format_fiat(sats_to_fiat(sats, price), self.fiat_valuation.currency),
+            None => "-".to_string(),
+        }
+    }
 
-        while let Ok(mut sync_data) = self.sync_data_receiver.try_recv() {
-            sync_data.transactions.sort_by(|a, b| {
-                match (&a.confirmation_time, &b.confirmation_time) {
-                    (Some(a), Some(b)) => b.cmp(&a),
+    fn confirmation_status(
+        &self,
+        wallet: &JsonWallet,
+        transaction_details: &TransactionDetails,
+    ) -> String {
+        let format_time = |timestamp: i64| {
+            Local
+                .timestamp_opt(timestamp, 0)
+                .unwrap()
+                .format("%d/%m/%y %H:%M:%S")
+                .to_string()
+        };
+        let txid = transaction_details.txid.to_string();
+        let first_seen = wallet.first_seen.get(&txid).copied();
+        // Removed transactions keep their last known confirmation, which no longer counts
+        let removed = wallet
+            .removed_transactions
+            .iter()
+            .any(|removed| removed.txid == transaction_details.txid);
+        match (&transaction_details.confirmation_time, removed) {
+            (Some(confirmation_time), true) => {
+                return format_time(confirmation_time.timestamp as i64)
+            }
+            (Some(confirmation_time), false) => {
+                let date = format_time(confirmation_time.timestamp as i64);
+                match confirmations(Some(confirmation_time.height), wallet.tip_height) {
+                    Some(count) if count >= self.final_confirmations => {
+                        return format!("{} ({} conf, final)", date, count)
+                    }
+                    Some(count) => {
+                        return format!("{} ({}/{} conf)", date, count, self.final_confirmations)
+                    }
+                    None => return date,
+                }
+            }
+            (None, true) => match first_seen {
+                Some(first_seen) => return format!("First seen {}", format_time(first_seen)),
+                None => return "Pending".to_string(),
+            },
+            (None, false) => match first_seen {
+                Some(first_seen) => return format!("In mempool since {}", format_time(first_seen)),
+                None => return "In mempool".to_string(),
+            },
+        }
+    }
+
+    fn show_settings_error(&mut self, result: Result<(), Box<dyn std::error::Error>>) {
+        let Err(error) = result else {
+            return;
+        };
+        self.dialog_box = Some(DialogBox {
+            dialog_box_enum: DialogBoxEnum::WalletCreated,
+            title: "Unable To Save Settings",
+            dialog_line_edit: Vec::from([DialogLineEdit {
+                message: Some(error.to_string()),
+                line_edit: None,
+            }]),
+            optional: false,
+        });
+    }
 
-                    (Some(_), None) => std::cmp::Ordering::Greater,
-                    (None, Some(_)) => std::cmp::Ordering::Less,
+    // Keeps the current prices if the source can't be loaded
+    fn load_price_source(&mut self, price_source: PriceSource) -> bool {
+        match price_source.provider(&self.backend_config.proxy) {
+            Ok(provider) => {
+                self.fiat_valuation.set_provider(provider);
+                return true;
+            }
+            Err(error) => {
+                self.dialog_box = Some(DialogBox {
+                    dialog_box_enum: DialogBoxEnum::InvalidTransaction,
+                    title: "Unable To Load Prices",
+                    dialog_line_edit: Vec::from([DialogLineEdit {
+                        message: Some(error.to_string()),
+                        line_edit: None,
+                    }]),
+                    optional: false,
+                });
+                return false;
+            }
+        }
+    }
 
-                    (None, None) => std::cmp::Ordering::Equal,
+    fn set_price_source(&mut self, price_source: PriceSource) {
+        if self.load_price_source(price_source.clone()) {
+            let result = self.wallet_model.set_price_source(price_source);
+            self.show_settings_error(result);
+        }
+    }
+
+    fn show_sweep_error(&mut self, error: String) {
+        self.dialog_box = Some(DialogBox {
+            dialog_box_enum: DialogBoxEnum::InvalidTransaction,
+            title: "Sweep Failed",
+            dialog_line_edit: Vec::from([DialogLineEdit {
+                message: Some(error),
+                line_edit: None,
+            }]),
+            optional: false,
+        });
+    }
+
+    fn connection_status(&self) -> ConnectionStatus {
+        let pub_keys: Vec<String> = self.wallet_model.wallet_objs.keys().cloned().collect();
+        return self.sync_scheduler.connection_status(&pub_keys);
+    }
+
+    fn is_own_address(&self, recipient_address_string: &str) -> bool {
+        let address = self.wallet_model.get_active_wallet_pub_key();
+
+        return recipient_address_string == address;
+    }
+
+    fn wallet_poll(&mut self) {
+        while let Ok(sync_data) = self.sync_data_receiver.try_recv() {
+            match sync_data {
+                SyncData::Synced {
+                    pub_key,
+                    balance,
+                    mut transactions,
+                    utxos,
+                    tip_height,
+                } => {
+                    transactions.sort_by(|a, b| {
+                        match (&a.confirmation_time, &b.confirmation_time) {
+                            (Some(a), Some(b)) => b.cmp(&a),
+
+                            (Some(_), None) => std::cmp::Ordering::Greater,
+                            (None, Some(_)) => std::cmp::Ordering::Less,
+
+                            (None, None) => std::cmp::Ordering::Equal,
+                        }
+                    });
+                    let lost_confirmations = self
+                        .wallet_model
+                        .sync_wallet(
+                            &pub_key,
+                            Some(balance),
+                            Some(transactions),
+                            Some(utxos),
+                            tip_height,
+                        )
+                        .unwrap_or_default();
+                    if !lost_confirmations.is_empty() {
+                        let wallet_name = self
+                            .wallet_model
+                            .get_wallet_name(&pub_key)
+                            .unwrap_or_else(|| pub_key.clone());
+                        let mut dialog_line_edit = Vec::from([DialogLineEdit {
+                            message: Some(format!(
+                                "Transactions in wallet '{}' are no longer confirmed:",
+                                wallet_name
+                            )),
+                            line_edit: None,
+                        }]);
+                        for txid in lost_confirmations.iter() {
+                            dialog_line_edit.push(DialogLineEdit {
+                                message: Some(txid.to_string()),
+                                line_edit: None,
+                            });
+                        }
+                        self.pending_notifications.push(DialogBox {
+                            dialog_box_enum: DialogBoxEnum::ConfirmationsLost,
+                            title: "Confirmations Lost",
+                            dialog_line_edit,
+                            optional: false,
+                        });
+                    }
+                    self.sync_scheduler.succeeded(&pub_key);
                 }
-            });
-            let _ = self.wallet_model.sync_wallet(
-                &sync_data.pub_key,
-                Some(sync_data.balance),
-                Some(sync_data.transactions),
-            );
+                // The wallet keeps showing its cached balance and history until a sync succeeds
+                SyncData::Failed {
+                    pub_key,
+                    error,
+                    offline,
+                } => {
+                    self.sync_scheduler.failed(&pub_key, &error, offline);
+                }
+                SyncData::SweepFound { result } => match result {
+                    Ok(new_sweep_transaction) => {
+                        if let CentralPanelState::ReceivingSweep { sweep_transaction } =
+                            &mut self.central_panel_state
+                        {
+                            *sweep_transaction = Some(new_sweep_transaction);
+                        }
+                    }
+                    Err(error) => self.show_sweep_error(error),
+                },
+                SyncData::FeeEstimated { result } => match result {
+                    Ok(fee_rate) => {
+                        if let CentralPanelState::ReceivingSweep { .. } = self.central_panel_state {
+                            self.string_scratchpad[1] = format!("{:.1}", fee_rate);
+                        }
+                    }
+                    Err(error) => {
+                        self.dialog_box = Some(DialogBox {
+                            dialog_box_enum: DialogBoxEnum::InvalidTransaction,
+                            title: "Fee Estimation Failed",
+                            dialog_line_edit: Vec::from([DialogLineEdit {
+                                message: Some(error),
+                                line_edit: None,
+                            }]),
+                            optional: false,
+                        })
+                    }
+                },
+            }
         }
-        self.active_threads
-            .lock()
-            .unwrap()
-            .retain(|_, handle| !handle.is_finished());
 
-        if self
+        // Don't clobber a dialog the user may be typing into
+        if self.dialog_box.is_none() && !self.pending_notifications.is_empty() {
+            self.dialog_box = Some(self.pending_notifications.remove(0));
+        }
+
+        let finished_threads: Vec<String> = self
             .active_threads
             .lock()
             .unwrap()
-            .contains_key(&active_wallet_pub_key)
+            .iter()
+            .filter(|(_, handle)| handle.is_finished())
+            .map(|(pub_key, _)| pub_key.clone())
+            .collect();
+        for pub_key in finished_threads {
+            let handle = self.active_threads.lock().unwrap().remove(&pub_key);
+            // A worker that panicked never sent its data
+            if let Some(Err(_)) = handle.map(|handle| handle.join()) {
+                self.sync_scheduler
+                    .failed(&pub_key, "Sync worker stopped unexpectedly", false);
+            }
+        }
+
+        if self
+            .sweep_worker
+            .as_ref()
+            .is_some_and(|handle| handle.is_finished())
         {
-            return;
+            if let Some(Err(_)) = self.sweep_worker.take().map(|handle| handle.join()) {
+                self.show_sweep_error("Sweep worker stopped unexpectedly".to_string());
+            }
+        }
+
+        let pub_keys: Vec<String> = self.wallet_model.wallet_objs.keys().cloned().collect();
+        let now = Instant::now();
+        for pub_key in self.sync_scheduler.due_wallets(&pub_keys, now) {
+            let Some(handle) = self
+                .wallet_model
+                .sync_wallet_in_background(&pub_key, self.sync_data_sender.clone())
+            else {
+                continue;
+            };
+            self.sync_scheduler.started(&pub_key, now);
+            self.active_threads.lock().unwrap().insert(pub_key, handle);
         }
-        let handle = self
-            .wallet_model
-            .sync_current_wallet(sync_data_channel_clone);
-        self.active_threads
-            .lock()
-            .unwrap()
-            .insert(active_wallet_pub_key, handle);
     }
 }
 
@@ -1,13 +1,34 @@
 use std::str::FromStr;
 
 use crate::{
+    backend::{BackendKind, RpcAuth, BACKEND_KINDS},
+    bip21::{is_payment_uri, parse_payment_uri, PaymentUri},
+    bip85::{derive_bip85, Bip85Application, HEX_MAX_BYTES, HEX_MIN_BYTES},
     bitcoin_wallet::{
-        generate_mnemonic_string, generate_qrcode_from_address, generate_wallet, generate_xpriv,
-        get_transaction_details, is_valid_bitcoin_address, TransactionDirection,
+        generate_backup_quiz_indices, generate_mnemonic_string, generate_qrcode_from_address,
+        generate_wallet, generate_xpriv, get_transaction_details, is_backup_quiz_correct,
+        is_valid_bitcoin_address, private_descriptor_string, public_descriptor_string,
+        TransactionDirection, TransactionKind, BACKUP_QUIZ_WORD_COUNT,
     },
+    cost_basis::{compute_cost_basis, CostBasisMethod, CostBasisReport, COST_BASIS_METHODS},
+    fiat::{fiat_to_sats, format_fiat, sats_to_fiat, PriceSource, FIAT_CURRENCIES},
+    history_diff::TransactionState,
+    history_export::{export_history, ExportFormat, EXPORT_FORMATS},
+    labels::{get_label, LabelType},
+    multisig::{
+        export_bsms, export_coldcard, local_cosigner_key, multisig_status, public_cosigner_key,
+        MultisigStatus,
+    },
+    policy::compile_policy,
+    sweep::{decode_qr_image, SweepTransaction},
+    transaction_detail::TransactionDetail,
+    units::{strip_group_separators, BITCOIN_UNITS},
     wallet_file_manager::EntryType,
 };
 use bdk::bitcoin::bip32::ExtendedPrivKey;
+use bdk::bitcoin::psbt::PartiallySignedTransaction;
+use bdk::bitcoin::{Address, Network};
+use bdk::TransactionDetails;
 use egui::Ui;
 use egui_extras::{Column, TableBuilder};
 
@@ -17,6 +38,9 @@ use chrono::prelude::*;
 
 use zxcvbn::zxcvbn;
 
+const MNEMONIC_WORDS_PER_ROW: usize = 4;
+const SWEEP_FEE_TARGETS: [(&str, usize); 3] = [("Fast", 1), ("Normal", 6), ("Slow", 24)];
+
 impl MyApp {
     pub fn render_wallet_main_panel(
         &mut self,
@@ -33,6 +57,24 @@ impl MyApp {
             ui.heading("Active Wallet");
             ui.add_space(10.0);
             ui.heading(&wallet.wallet_name.to_owned()).rect;
+            // Imported keys and descriptors have no mnemonic to quiz on
+            if let (false, Some(mnemonic_string)) =
+                (wallet.backup_verified, wallet.mnemonic.clone())
+            {
+                ui.add_space(10.0);
+                ui.colored_label(
+                    egui::Color32::RED,
+                    "⚠ The mnemonic backup for this wallet has not been verified",
+                );
+                if ui.button("Verify Backup").clicked() {
+                    self.change_state(CentralPanelState::WalletBackupQuiz {
+                        word_indices: generate_backup_quiz_indices(&mnemonic_string),
+                        mnemonic_string: mnemonic_string,
+                        source: Box::new(CentralPanelState::WalletMain),
+                        destination: Box::new(CentralPanelState::WalletMain),
+                    });
+                }
+            }
         });
         ui.add_space(20.0);
         egui::ComboBox::from_label("Choose Active Wallet")
@@ -83,19 +125,149 @@ impl MyApp {
                 self.change_state(CentralPanelState::WalletExistingWallet)
             }
         });
+        ui.horizontal(|ui| {
+            if ui.button("Show Descriptors").clicked() {
+                self.change_state(CentralPanelState::WalletDescriptors);
+            }
+            ui.add_space(width / 12.0);
+            if ui.button("Export History").clicked() {
+                self.change_state(CentralPanelState::WalletExportHistory {
+                    format: ExportFormat::Csv,
+                    all_wallets: false,
+                });
+            }
+            ui.add_space(width / 12.0);
+            if ui.button("Cost Basis Report").clicked() {
+                self.change_state(CentralPanelState::WalletCostBasis {
+                    method: CostBasisMethod::Fifo,
+                    report: None,
+                });
+            }
+            ui.add_space(width / 12.0);
+            if ui.button("UTXOs & Labels").clicked() {
+                self.change_state(CentralPanelState::WalletUtxos);
+            }
+            ui.add_space(width / 12.0);
+            if ui.button("Import Descriptors").clicked() {
+                self.change_state(CentralPanelState::WalletImportDescriptors);
+            }
+            ui.add_space(width / 12.0);
+            if ui.button("Add Multisig Wallet").clicked() {
+                self.change_state(CentralPanelState::WalletNewMultisig {
+                    cosigner_keys: Vec::new(),
+                });
+            }
+            if wallet.priv_key.is_some() {
+                ui.add_space(width / 12.0);
+                if ui.button("BIP85 Child Seeds").clicked() {
+                    self.change_state(CentralPanelState::WalletBip85 {
+                        application: Bip85Application::Mnemonic { word_count: 12 },
+                        derived: None,
+                    });
+                }
+            }
+            ui.add_space(width / 12.0);
+            if ui.button("Add Policy Wallet").clicked() {
+                self.change_state(CentralPanelState::WalletNewPolicy {
+                    policy_keys: Vec::new(),
+                    preview: None,
+                });
+            }
+            if wallet.requires_psbt() {
+                ui.add_space(width / 12.0);
+                if ui.button("Sign PSBT").clicked() {
+                    self.change_state(CentralPanelState::WalletMultisigPsbt {
+                        psbt: String::new(),
+                    });
+                }
+            }
+        });
         ui.add_space(20.0);
         ui.separator();
         ui.vertical_centered(|ui| {
             let wallet = self.wallet_model.get_active_wallet_data();
             ui.add_space(20.0);
-            ui.heading(format!("Wallet Balance: {:?}", wallet.get_total()));
+            ui.heading(format!(
+                "Wallet Balance: {}",
+                self.format_amount(wallet.get_total())
+            ));
+            let current_price = self.fiat_valuation.current_price();
+            ui.label(self.format_fiat_amount(wallet.get_total(), current_price));
+            if let Some(balance) = &wallet.balance {
+                egui::Grid::new("balance_breakdown_grid").show(ui, |ui| {
+                    for (name, amount) in [
+                        ("Confirmed", balance.confirmed),
+                        ("Trusted Pending", balance.trusted_pending),
+                        ("Untrusted Pending", balance.untrusted_pending),
+                        ("Immature", balance.immature),
+                    ] {
+                        ui.label(name);
+                        ui.label(self.format_amount(amount));
+                        ui.end_row();
+                    }
+                });
+            }
+            ui.add_space(10.0);
+            let sync_progress = self
+                .wallet_model
+                .sync_progress
+                .lock()
+                .unwrap()
+                .get(&wallet.pub_key)
+                .cloned();
+            if let Some((progress, message)) = sync_progress {
+                ui.add(egui::ProgressBar::new(progress / 100.0).text(format!(
+                    "{} {:.0}%",
+                    message.unwrap_or_else(|| "Syncing".to_string()),
+                    progress
+                )));
+                ui.ctx().request_repaint();
+            }
+            egui::CollapsingHeader::new("Sync Status").show(ui, |ui| {
+                egui::Grid::new("sync_status_grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong("Wallet");
+                        ui.strong("Balance");
+                        ui.strong("Last Synced");
+                        ui.strong("Status");
+                        ui.end_row();
+                        for wallet in self.wallet_model.json_wallet_data.wallets.iter() {
+                            let sync_state = self.sync_scheduler.state(&wallet.pub_key);
+                            ui.label(&wallet.wallet_name);
+                            ui.label(self.format_amount(wallet.get_total()));
+                            ui.label(
+                                sync_state
+                                    .last_synced
+                                    .map(|last_synced| {
+                                        last_synced.format("%d/%m/%y %H:%M:%S").to_string()
+                                    })
+                                    .unwrap_or_else(|| "Never".to_string()),
+                            );
+                            ui.label(sync_state.status.name());
+                            ui.end_row();
+                        }
+                    });
+            });
+            let confirmation_timestamps: Vec<u64> = wallet
+                .sorted_transactions
+                .iter()
+                .flatten()
+                .filter_map(|transaction_details| transaction_details.confirmation_time.as_ref())
+                .map(|confirmation_time| confirmation_time.timestamp)
+                .collect();
+            self.fiat_valuation.refresh(&confirmation_timestamps);
             ui.add_space(50.0);
 
+            let mut edited_txid = None;
+            let mut opened_txid = None;
             TableBuilder::new(ui)
-                .column(Column::exact(width / 4.0).resizable(true))
-                .column(Column::exact(width / 4.0).resizable(true))
-                .column(Column::exact(width / 4.0).resizable(true))
-                .column(Column::exact(width / 4.0).resizable(true))
+                .column(Column::exact(width / 6.0).resizable(true))
+                .column(Column::exact(width / 6.0).resizable(true))
+                .column(Column::exact(width / 6.0).resizable(true))
+                .column(Column::exact(width / 6.0).resizable(true))
+                .column(Column::exact(width / 6.0).resizable(true))
+                .column(Column::exact(width / 6.0).resizable(true))
                 .header(20.0, |mut header| {
                     header.col(|ui| {
                         ui.heading("Txid");
@@ -103,92 +275,176 @@ impl MyApp {
                     header.col(|ui| {
                         ui.heading("Amount");
                     });
+                    header.col(|ui| {
+                        ui.heading(format!("{} Value", self.fiat_valuation.currency.name()));
+                    });
                     header.col(|ui| {
                         ui.heading("Date");
                     });
                     header.col(|ui| {
                         ui.heading("Recipient");
                     });
+                    header.col(|ui| {
+                        ui.heading("Label");
+                    });
                 })
                 .body(|mut body| {
                     let wallet = self.wallet_model.get_active_wallet_data();
-                    if let Some(transactions) = wallet.sorted_transactions.clone() {
-                        for transaction_details in transactions.iter() {
-                            let (
-                                transaction_direction,
-                                address,
-                                txid,
-                                transaction_total,
-                                fee,
-                                confirmation_time,
-                            ) = get_transaction_details(transaction_details.clone());
-                            body.row(30.0, |mut row| {
-                                row.col(|ui| {
-                                    ui.horizontal(|ui| {
-                                        if ui.button("📋").on_hover_text("Click to copy").clicked()
-                                        {
-                                            ui.output_mut(|o| o.copied_text = txid.clone());
-                                        }
-                                        let shortened_txid = txid.clone()[0..10].to_string()
-                                            + "..."
-                                            + &txid[txid.len() - 10..txid.len()];
+                    // Transactions that left the history stay listed with their state
+                    let transactions: Vec<TransactionDetails> = wallet
+                        .sorted_transactions
+                        .iter()
+                        .flatten()
+                        .chain(wallet.removed_transactions.iter())
+                        .cloned()
+                        .collect();
+                    for transaction_details in transactions.iter() {
+                        let (
+                            transaction_direction,
+                            address,
+                            txid,
+                            transaction_total,
+                            fee,
+                            confirmation_time,
+                        ) = get_transaction_details(
+                            transaction_details.clone(),
+                            wallet.classification(transaction_details),
+                        );
+                        let kind = wallet
+                            .classification(transaction_details)
+                            .map(|classification| classification.kind);
+                        body.row(30.0, |mut row| {
+                            row.col(|ui| {
+                                ui.horizontal(|ui| {
+                                    if ui.button("📋").on_hover_text("Click to copy").clicked() {
+                                        ui.output_mut(|o| o.copied_text = txid.clone());
+                                    }
+                                    let shortened_txid = txid.clone()[0..10].to_string()
+                                        + "..."
+                                        + &txid[txid.len() - 10..txid.len()];
 
-                                        ui.label(format!("{}", shortened_txid)).on_hover_text(txid)
-                                    });
+                                    if ui
+                                        .add(
+                                            egui::Label::new(shortened_txid)
+                                                .sense(egui::Sense::click()),
+                                        )
+                                        .on_hover_text(txid.as_str())
+                                        .clicked()
+                                    {
+                                        opened_txid = Some(txid.clone());
+                                    }
                                 });
+                            });
 
-                                row.col(|ui| {
-                                    let transaction_string = match transaction_direction {
-                                        TransactionDirection::To => {
-                                            format!("{} (fee: {})", transaction_total, fee)
-                                        }
-                                        TransactionDirection::From => {
-                                            format!("+{}", transaction_total)
-                                        }
-                                    };
-                                    ui.label(transaction_string);
-                                });
+                            row.col(|ui| {
+                                let transaction_string = match transaction_direction {
+                                    TransactionDirection::To => format!(
+                                        "-{} (fee: {})",
+                                        self.format_amount(transaction_total.unsigned_abs()),
+                                        self.format_amount(fee)
+                                    ),
+                                    TransactionDirection::From => format!(
+                                        "+{}",
+                                        self.format_amount(transaction_total.unsigned_abs())
+                                    ),
+                                };
+                                ui.label(transaction_string);
+                            });
 
-                                row.col(|ui| {
-                                    let confirmation_time_str = match confirmation_time {
-                                        Some(confirmation_time) => {
-                                            let confirmation_time_local = Local
-                                                .timestamp_opt(
-                                                    confirmation_time.timestamp as i64,
-                                                    0,
-                                                )
-                                                .unwrap();
-                                            confirmation_time_local
-                                                .format("%d/%m/%y %H:%M:%S")
-                                                .to_string()
-                                        }
-                                        None => "Pending".to_string(),
-                                    };
+                            row.col(|ui| {
+                                let transaction_amount = transaction_total.unsigned_abs();
+                                let confirmed_price =
+                                    confirmation_time.as_ref().and_then(|confirmation_time| {
+                                        self.fiat_valuation
+                                            .historical_price(confirmation_time.timestamp)
+                                    });
+                                ui.label(format!(
+                                    "{} (now {})",
+                                    self.format_fiat_amount(transaction_amount, confirmed_price),
+                                    self.format_fiat_amount(transaction_amount, current_price)
+                                ));
+                            });
 
-                                    ui.label(confirmation_time_str);
-                                });
-                                row.col(|ui| {
-                                    let destination_string = match transaction_direction {
-                                        TransactionDirection::To => self
-                                            .wallet_model
-                                            .get_wallet_name(&address)
-                                            .unwrap_or_else(|| address.clone()),
-                                        TransactionDirection::From => self
-                                            .wallet_model
-                                            .get_wallet_name(
-                                                &self.wallet_model.get_active_wallet_pub_key(),
-                                            )
-                                            .unwrap_or_else(|| {
-                                                self.wallet_model.get_active_wallet_pub_key()
-                                            }),
-                                    };
-
-                                    ui.label(destination_string);
+                            row.col(|ui| {
+                                let confirmation_time_str =
+                                    self.confirmation_status(&wallet, transaction_details);
+
+                                match wallet.transaction_states.get(&txid) {
+                                    Some(state) => {
+                                        let response = ui.colored_label(
+                                            egui::Color32::from_rgb(230, 140, 0),
+                                            format!("{} ({})", state.name(), confirmation_time_str),
+                                        );
+                                        if let TransactionState::Replaced { by } = state {
+                                            response.on_hover_text(format!("Replaced by {}", by));
+                                        }
+                                    }
+                                    None => {
+                                        ui.label(confirmation_time_str);
+                                    }
+                                }
+                            });
+                            row.col(|ui| {
+                                let destination_string = match (transaction_direction, kind) {
+                                    (_, Some(kind))
+                                        if !matches!(
+                                            kind,
+                                            TransactionKind::Incoming | TransactionKind::Outgoing
+                                        ) =>
+                                    {
+                                        kind.name().to_string()
+                                    }
+                                    (TransactionDirection::To, _) => self
+                                        .wallet_model
+                                        .get_wallet_name(&address)
+                                        .unwrap_or_else(|| address.clone()),
+                                    (TransactionDirection::From, _) => self
+                                        .wallet_model
+                                        .get_wallet_name(
+                                            &self.wallet_model.get_active_wallet_pub_key(),
+                                        )
+                                        .unwrap_or_else(|| {
+                                            self.wallet_model.get_active_wallet_pub_key()
+                                        }),
+                                };
+
+                                ui.label(destination_string);
+                            });
+                            row.col(|ui| {
+                                ui.horizontal(|ui| {
+                                    if ui.button("✏").on_hover_text("Edit Label").clicked() {
+                                        edited_txid = Some(txid.clone());
+                                    }
+                                    ui.label(
+                                        get_label(&wallet.labels, LabelType::Tx, &txid)
+                                            .unwrap_or_default(),
+                                    );
                                 });
                             });
-                        }
+                        });
                     }
                 });
+            if let Some(txid) = edited_txid {
+                self.open_label_dialog(LabelType::Tx, txid);
+            }
+            if let Some(txid) = opened_txid {
+                match self.wallet_model.transaction_detail(&txid) {
+                    Ok(detail) => {
+                        self.change_state(CentralPanelState::WalletTransactionDetail { detail })
+                    }
+                    Err(error) => {
+                        self.dialog_box = Some(DialogBox {
+                            dialog_box_enum: DialogBoxEnum::InvalidTransaction,
+                            title: "Transaction Unavailable",
+                            dialog_line_edit: Vec::from([DialogLineEdit {
+                                message: Some(error.to_string()),
+                                line_edit: None,
+                            }]),
+                            optional: false,
+                        })
+                    }
+                }
+            }
         });
     }
     pub fn render_sending_panel(
@@ -202,19 +458,105 @@ impl MyApp {
         ui.add_space(20.0);
         ui.vertical_centered(|ui| {
             ui.heading(format!(
-                "Wallet Balance: {:?}",
-                self.wallet_model.get_active_wallet_data().get_total()
+                "Wallet Balance: {}",
+                self.format_amount(self.wallet_model.get_active_wallet_data().get_total())
             ));
             ui.add_space(50.0);
 
             ui.heading("Recipient Address");
-            ui.text_edit_singleline(&mut self.string_scratchpad[0]);
+            let recipient_response = ui.text_edit_singleline(&mut self.string_scratchpad[0]);
+            if recipient_response.changed() && is_payment_uri(&self.string_scratchpad[0]) {
+                match parse_payment_uri(&self.string_scratchpad[0]) {
+                    Ok(payment_uri) => {
+                        self.string_scratchpad[0] = payment_uri.address;
+                        if let Some(amount) = payment_uri.amount {
+                            self.string_scratchpad[1] = self.format_input_amount(amount);
+                        }
+                        let description: Vec<String> = [payment_uri.label, payment_uri.message]
+                            .into_iter()
+                            .flatten()
+                            .collect();
+                        self.string_scratchpad[2] = description.join(" - ");
+                    }
+                    Err(error) => {
+                        self.dialog_box = Some(DialogBox {
+                            dialog_box_enum: DialogBoxEnum::InvalidTransaction,
+                            title: "Invalid Payment URI",
+                            dialog_line_edit: Vec::from([DialogLineEdit {
+                                message: Some(error.to_string()),
+                                line_edit: None,
+                            }]),
+                            optional: false,
+                        })
+                    }
+                }
+            }
+            if !self.string_scratchpad[2].is_empty() {
+                ui.label(&self.string_scratchpad[2]);
+            }
 
             ui.add_space(50.0);
 
             ui.heading("Amount to send");
-            ui.text_edit_singleline(&mut self.string_scratchpad[1]);
-            ui.label("Sats");
+            let current_price = self.fiat_valuation.current_price();
+            if current_price.is_some() {
+                ui.checkbox(
+                    &mut self.fiat_input,
+                    format!("Enter in {}", self.fiat_valuation.currency.name()),
+                );
+            }
+            match (self.fiat_input, current_price) {
+                (true, Some(price)) => {
+                    if ui
+                        .text_edit_singleline(&mut self.string_scratchpad[3])
+                        .changed()
+                    {
+                        self.string_scratchpad[1] = strip_group_separators(
+                            self.string_scratchpad[3].trim(),
+                            self.number_format,
+                        )
+                        .ok()
+                        .and_then(|fiat| {
+                            fiat.replace(self.number_format.decimal_separator, ".")
+                                .parse::<f64>()
+                                .ok()
+                        })
+                        .and_then(|fiat| fiat_to_sats(fiat, price))
+                        .map(|sats| self.format_input_amount(sats))
+                        .unwrap_or_default();
+                    }
+                    ui.label(format!(
+                        "{} {} = {} {}",
+                        self.string_scratchpad[3].trim(),
+                        self.fiat_valuation.currency.name(),
+                        self.string_scratchpad[1],
+                        self.display_unit.name()
+                    ));
+                }
+                _ => {
+                    ui.text_edit_singleline(&mut self.string_scratchpad[1]);
+                    ui.label(self.display_unit.name());
+                    if let Ok(sats) = self.parse_amount(&self.string_scratchpad[1]) {
+                        ui.label(self.format_fiat_amount(sats, current_price));
+                    }
+                }
+            }
+
+            let spending_paths = self.wallet_model.get_active_spending_paths();
+            if !spending_paths.is_empty() {
+                ui.add_space(30.0);
+                if self.spending_path >= spending_paths.len() {
+                    self.spending_path = 0;
+                }
+                egui::ComboBox::from_label("Spending Path")
+                    .selected_text(&spending_paths[self.spending_path])
+                    .show_ui(ui, |ui| {
+                        for (index, spending_path) in spending_paths.iter().enumerate() {
+                            ui.selectable_value(&mut self.spending_path, index, spending_path);
+                        }
+                    });
+                ui.add_space(20.0);
+            }
 
             if ui.button("Send").clicked() {
                 let (valid, invalid_vec) = self.is_valid_transaction_request(
@@ -229,8 +571,11 @@ impl MyApp {
                         dialog_line_edit: Vec::from([DialogLineEdit {
                             message: Some(
                                 format!(
-                                    "Are you sure you want to send {} Sats to {}?",
-                                    &self.string_scratchpad[1], &self.string_scratchpad[0]
+                                    "Are you sure you want to send {} to {}?",
+                                    self.format_amount(
+                                        self.parse_amount(&self.string_scratchpad[1]).unwrap()
+                                    ),
+                                    &self.string_scratchpad[0]
                                 )
                                 .into(),
                             ),
@@ -265,7 +610,7 @@ impl MyApp {
         self.boiler_plate_render(ui, watch, &source);
         ui.add_space(20.0);
         let wallet = self.wallet_model.get_active_wallet_data();
-        let pub_key = wallet.pub_key;
+        let pub_key = wallet.pub_key.clone();
         ui.vertical_centered(|ui| {
             // Encode some data into bits.
             ui.heading("Public Key");
@@ -274,516 +619,2191 @@ impl MyApp {
             ui.heading(&pub_key);
             ui.add_space(10.0);
 
+            ui.label(format!("Amount ({}, optional)", self.display_unit.name()));
+            ui.text_edit_singleline(&mut self.string_scratchpad[0]);
+            ui.label("Label (optional)");
+            ui.text_edit_singleline(&mut self.string_scratchpad[1]);
+            ui.label("Message (optional)");
+            ui.text_edit_singleline(&mut self.string_scratchpad[2]);
+            let optional_field =
+                |field: &String| Some(field.trim().to_string()).filter(|field| !field.is_empty());
+            let payment_uri = PaymentUri {
+                address: pub_key.clone(),
+                amount: self.parse_amount(&self.string_scratchpad[0]).ok(),
+                label: optional_field(&self.string_scratchpad[1]),
+                message: optional_field(&self.string_scratchpad[2]),
+            }
+            .to_uri();
+            ui.add_space(10.0);
+
             let img = ui.ctx().load_texture(
                 "my-image",
-                generate_qrcode_from_address(&pub_key).unwrap(),
+                generate_qrcode_from_address(&payment_uri).unwrap(),
                 Default::default(),
             );
 
             ui.add(egui::Image::from_texture(&img));
 
             ui.add_space(10.0);
-            if ui.button("Copy Public Key").clicked() {
-                ui.output_mut(|o| o.copied_text = pub_key);
-            }
+            ui.horizontal(|ui| {
+                ui.label(
+                    get_label(&wallet.labels, LabelType::Addr, &pub_key)
+                        .unwrap_or("No address label"),
+                );
+                if ui.button("✏").on_hover_text("Edit Address Label").clicked() {
+                    self.open_label_dialog(LabelType::Addr, pub_key.clone());
+                }
+            });
+            ui.horizontal(|ui| {
+                if ui.button("Copy Public Key").clicked() {
+                    ui.output_mut(|o| o.copied_text = pub_key);
+                }
+                if ui.button("Copy Payment URI").clicked() {
+                    ui.output_mut(|o| o.copied_text = payment_uri);
+                }
+            });
+            ui.add_space(20.0);
+            ui.horizontal(|ui| {
+                if ui.button("Request Payment").clicked() {
+                    self.change_state(CentralPanelState::ReceivingPaymentRequests);
+                }
+                if ui.button("Sweep Paper Wallet").clicked() {
+                    self.change_state(CentralPanelState::ReceivingSweep {
+                        sweep_transaction: None,
+                    });
+                }
+            });
         });
     }
 
-    pub fn render_contacts_panel(
+    pub fn render_export_history_panel(
         &mut self,
         ctx: &egui::Context,
         ui: &mut Ui,
         watch: bool,
         source: Option<CentralPanelState>,
+        format: ExportFormat,
+        all_wallets: bool,
     ) {
         self.boiler_plate_render(ui, watch, &source);
-        ui.add_space(20.0);
-
         ui.vertical_centered(|ui| {
-            ui.heading("Contact List");
-            ui.add_space(10.0);
-            if ui.button("Add Contact").clicked() {
-                self.change_state(CentralPanelState::ContactsNewContact);
+            ui.add_space(30.0);
+            ui.heading("Export Transaction History");
+            ui.add_space(20.0);
+            let mut selected_format = format;
+            let mut selected_all_wallets = all_wallets;
+            egui::ComboBox::from_label("Format")
+                .selected_text(format.name())
+                .show_ui(ui, |ui| {
+                    for option in EXPORT_FORMATS {
+                        ui.selectable_value(&mut selected_format, option, option.name());
+                    }
+                });
+            ui.checkbox(&mut selected_all_wallets, "All Wallets");
+            if let CentralPanelState::WalletExportHistory {
+                format,
+                all_wallets,
+            } = &mut self.central_panel_state
+            {
+                *format = selected_format;
+                *all_wallets = selected_all_wallets;
             }
-            ui.add_space(10.0);
-            TableBuilder::new(ui)
-                .column(Column::exact(250.0).resizable(false))
-                .column(Column::exact(350.0))
-                .column(Column::exact(100.0))
-                .column(Column::exact(100.0))
-                .header(20.0, |mut header| {
-                    header.col(|ui| {
-                        ui.heading("Wallet Name");
-                    });
-                    header.col(|ui| {
-                        ui.heading("Public Key");
-                    });
-                    header.col(|ui| {
-                        ui.heading("Last Transaction Txid");
-                    });
-                    header.col(|ui| {});
-                })
-                .body(|mut body| {
-                    let contacts = self.wallet_model.json_wallet_data.contacts.clone();
-                    for contact in contacts.iter() {
-                        let last_transaction =
-                            self.wallet_model.last_transaction(&contact.pub_key.clone());
-                        body.row(30.0, |mut row| {
-                            row.col(|ui| {
-                                ui.horizontal(|ui| {
-                                    if ui
-                                        .button(contact.wallet_name.clone())
-                                        .on_hover_text("Change")
-                                        .clicked()
-                                    {
-                                        self.change_state(CentralPanelState::ContactsRename {
-                                            pub_key: contact.pub_key.clone(),
-                                        });
-                                    }
-                                });
-                            });
-
-                            row.col(|ui| {
-                                if ui
-                                    .add(
-                                        egui::Label::new(&contact.pub_key)
-                                            .sense(egui::Sense::click()),
-                                    )
-                                    .on_hover_text("Copy Public Key")
-                                    .clicked()
-                                {
-                                    ui.output_mut(|o| {
-                                        o.copied_text = contact.pub_key.clone();
-                                    });
-                                }
-                                // ui.label(contact.pub_key.clone())
-                                //     .sense(egui::Sense::click()).;
-                            });
-
-                            row.col(|ui| {
-                                let mut last_transaction_string = "None".to_string();
-                                if let Some(last_transaction) = last_transaction.clone() {
-                                    last_transaction_string = format!("{}", last_transaction.txid);
-                                }
-                                ui.label(last_transaction_string);
-                            });
-                            row.col(|ui| {
-                                if let None = last_transaction {
-                                    if ui
-                                        .add(egui::Label::new("✖").sense(egui::Sense::click()))
-                                        .on_hover_text("Delete Contact")
-                                        .clicked()
-                                    {
-                                        ui.output_mut(|o| {
-                                            self.change_state(CentralPanelState::ContactsDelete {
-                                                pub_key: contact.pub_key.clone(),
-                                            });
-                                        });
-                                    }
-                                }
-                            });
-                        });
+            ui.add_space(20.0);
+            ui.label("From (YYYY-MM-DD, optional)");
+            ui.text_edit_singleline(&mut self.string_scratchpad[0]);
+            ui.label("To (YYYY-MM-DD, optional)");
+            ui.text_edit_singleline(&mut self.string_scratchpad[1]);
+            ui.add_space(20.0);
+            if ui.button("Export").clicked() {
+                let parse_date = |date: &str| -> Result<Option<NaiveDate>, String> {
+                    if date.trim().is_empty() {
+                        return Ok(None);
                     }
+                    NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+                        .map(Some)
+                        .map_err(|_| format!("{} is not a YYYY-MM-DD date", date.trim()))
+                };
+                let date_range = parse_date(&self.string_scratchpad[0])
+                    .and_then(|from| Ok((from, parse_date(&self.string_scratchpad[1])?)));
+                let export = date_range.and_then(|(from, to)| {
+                    let height = self
+                        .wallet_model
+                        .json_wallet_data
+                        .wallets
+                        .iter()
+                        .filter_map(|wallet| wallet.tip_height)
+                        .max();
+                    let rows = self.wallet_model.history_export_rows(
+                        selected_all_wallets,
+                        from,
+                        to,
+                        height,
+                    );
+                    export_history(&rows, selected_format).map_err(|error| error.to_string())
                 });
+                let message = match export {
+                    Ok(export) => {
+                        let default_path =
+                            format!("{}/{}", self.folder_path, selected_format.file_name());
+                        let Some(file_path) =
+                            tinyfiledialogs::save_file_dialog("Export History", &default_path)
+                        else {
+                            return;
+                        };
+                        match std::fs::write(&file_path, export) {
+                            Ok(_) => format!("Saved to {}", file_path),
+                            Err(error) => error.to_string(),
+                        }
+                    }
+                    Err(error) => error,
+                };
+                self.dialog_box = Some(DialogBox {
+                    dialog_box_enum: DialogBoxEnum::WalletCreated,
+                    title: "Export History",
+                    dialog_line_edit: Vec::from([DialogLineEdit {
+                        message: Some(message),
+                        line_edit: None,
+                    }]),
+                    optional: false,
+                })
+            }
         });
     }
 
-    pub fn render_new_wallet_creation(
+    pub fn render_cost_basis_panel(
         &mut self,
         ctx: &egui::Context,
         ui: &mut Ui,
         watch: bool,
         source: Option<CentralPanelState>,
-        destination: CentralPanelState,
-        mnemonic_string: &str,
+        method: CostBasisMethod,
+        report: Option<CostBasisReport>,
     ) {
         self.boiler_plate_render(ui, watch, &source);
+        let transactions = self.wallet_model.cost_basis_transactions();
+        let timestamps: Vec<u64> = transactions
+            .iter()
+            .map(|transaction| transaction.timestamp)
+            .collect();
+        self.fiat_valuation.refresh(&timestamps);
+        let currency = self.fiat_valuation.currency;
         ui.vertical_centered(|ui| {
-            ui.add_space(50.0);
-            ui.heading("Write down the following mnemonic");
-            ui.add_space(20.0);
-
-            ui.strong(mnemonic_string);
             ui.add_space(30.0);
-            if ui.button("Copy Mnemonic").clicked() {
-                ui.output_mut(|o| o.copied_text = mnemonic_string.to_string());
-            }
+            ui.heading(format!("Cost Basis Report ({})", currency.name()));
             ui.add_space(20.0);
-            ui.label("Type and confirm the mnemonic above");
+            let mut selected_method = method;
+            egui::ComboBox::from_label("Method")
+                .selected_text(method.name())
+                .show_ui(ui, |ui| {
+                    for option in COST_BASIS_METHODS {
+                        ui.selectable_value(&mut selected_method, option, option.name());
+                    }
+                });
+            if selected_method != method {
+                if let CentralPanelState::WalletCostBasis { method, report } =
+                    &mut self.central_panel_state
+                {
+                    *method = selected_method;
+                    *report = None;
+                }
+            }
             ui.add_space(10.0);
-            ui.text_edit_singleline(&mut self.string_scratchpad[0]);
+            if ui.button("Generate Report").clicked() {
+                let price_at = |timestamp: u64| -> Result<f64, anyhow::Error> {
+                    if let Some(price) = self.fiat_valuation.historical_price(timestamp) {
+                        return Ok(price);
+                    }
+                    match self.fiat_valuation.last_error() {
+                        Some(error) => anyhow::bail!("Unable to load historical prices: {}", error),
+                        None => {
+                            anyhow::bail!("Historical prices are still loading, try again shortly")
+                        }
+                    }
+                };
+                match compute_cost_basis(&transactions, selected_method, &price_at) {
+                    Ok(new_report) => {
+                        if let CentralPanelState::WalletCostBasis { report, .. } =
+                            &mut self.central_panel_state
+                        {
+                            *report = Some(new_report);
+                        }
+                    }
+                    Err(error) => {
+                        self.dialog_box = Some(DialogBox {
+                            dialog_box_enum: DialogBoxEnum::InvalidTransaction,
+                            title: "Report Failed",
+                            dialog_line_edit: Vec::from([DialogLineEdit {
+                                message: Some(error.to_string()),
+                                line_edit: None,
+                            }]),
+                            optional: false,
+                        })
+                    }
+                }
+            }
+            let Some(report) = report else {
+                return;
+            };
+            ui.add_space(20.0);
+            TableBuilder::new(ui)
+                .column(Column::exact(200.0).resizable(true))
+                .column(Column::exact(80.0))
+                .column(Column::exact(120.0))
+                .column(Column::exact(120.0))
+                .column(Column::exact(120.0))
+                .header(20.0, |mut header| {
+                    for title in ["Wallet", "Year", "Proceeds", "Cost Basis", "Gain"] {
+                        header.col(|ui| {
+                            ui.heading(title);
+                        });
+                    }
+                })
+                .body(|mut body| {
+                    for summary in report.year_summaries().iter() {
+                        body.row(30.0, |mut row| {
+                            row.col(|ui| {
+                                ui.label(&summary.wallet_name);
+                            });
+                            row.col(|ui| {
+                                ui.label(summary.year.to_string());
+                            });
+                            for value in [summary.proceeds, summary.cost_basis, summary.gain] {
+                                row.col(|ui| {
+                                    ui.label(format_fiat(value, currency));
+                                });
+                            }
+                        });
+                    }
+                });
+            ui.add_space(20.0);
+            let current_price = self.fiat_valuation.current_price();
+            let remaining_sats: u64 = report.remaining_lots.iter().map(|lot| lot.sats).sum();
+            let remaining_cost: f64 = report.remaining_lots.iter().map(|lot| lot.cost).sum();
+            ui.label(format!(
+                "Unrealized: {} in {} lots, cost basis {}",
+                self.format_amount(remaining_sats),
+                report.remaining_lots.len(),
+                format_fiat(remaining_cost, currency)
+            ));
+            if let Some(price) = current_price {
+                ui.label(format!(
+                    "Unrealized gain at current price: {}",
+                    format_fiat(
+                        sats_to_fiat(remaining_sats, price) - remaining_cost,
+                        currency
+                    )
+                ));
+            }
+            ui.add_space(20.0);
+            if ui.button("Export CSV").clicked() {
+                let file_name = format!("cost_basis_{}.csv", report.method.name().to_lowercase());
+                let default_path = format!("{}/{}", self.folder_path, file_name);
+                let Some(file_path) =
+                    tinyfiledialogs::save_file_dialog("Export Cost Basis Report", &default_path)
+                else {
+                    return;
+                };
+                let message = match report.to_csv(current_price) {
+                    Ok(csv) => match std::fs::write(&file_path, csv) {
+                        Ok(_) => format!("Saved to {}", file_path),
+                        Err(error) => error.to_string(),
+                    },
+                    Err(error) => error.to_string(),
+                };
+                self.dialog_box = Some(DialogBox {
+                    dialog_box_enum: DialogBoxEnum::WalletCreated,
+                    title: "Export Cost Basis Report",
+                    dialog_line_edit: Vec::from([DialogLineEdit {
+                        message: Some(message),
+                        line_edit: None,
+                    }]),
+                    optional: false,
+                })
+            }
+        });
+    }
+
+    pub fn render_transaction_detail_panel(
+        &mut self,
+        ctx: &egui::Context,
+        ui: &mut Ui,
+        watch: bool,
+        source: Option<CentralPanelState>,
+        detail: TransactionDetail,
+    ) {
+        self.boiler_plate_render(ui, watch, &source);
+        ui.vertical_centered(|ui| {
+            ui.add_space(20.0);
+            ui.heading("Transaction");
+            ui.label(&detail.txid);
+            ui.add_space(10.0);
+            let unknown = || "Unknown".to_string();
+            let confirmations = match (detail.confirmations, detail.height) {
+                (_, None) => "Unconfirmed".to_string(),
+                (Some(confirmations), Some(height)) => {
+                    format!("{} (block {})", confirmations, height)
+                }
+                (None, Some(height)) => format!("Block {}", height),
+            };
+            egui::Grid::new("transaction_detail_grid").show(ui, |ui| {
+                ui.label("Fee");
+                ui.label(
+                    detail
+                        .fee
+                        .map(|fee| self.format_amount(fee))
+                        .unwrap_or_else(unknown),
+                );
+                ui.end_row();
+                ui.label("Fee Rate");
+                ui.label(
+                    detail
+                        .fee_rate
+                        .map(|fee_rate| format!("{:.2} sat/vB", fee_rate))
+                        .unwrap_or_else(unknown),
+                );
+                ui.end_row();
+                ui.label("Size");
+                ui.label(format!("{} vB ({} WU)", detail.vsize, detail.weight));
+                ui.end_row();
+                ui.label("Replace-By-Fee");
+                ui.label(if detail.rbf { "Yes" } else { "No" });
+                ui.end_row();
+                ui.label("Locktime");
+                ui.label(detail.locktime.to_string());
+                ui.end_row();
+                ui.label("Confirmations");
+                ui.label(confirmations);
+                ui.end_row();
+            });
+            if detail.rbf && detail.height.is_none() && detail.inputs.iter().any(|input| input.is_mine)
+            {
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    ui.label("New Fee Rate (sat/vB)");
+                    ui.text_edit_singleline(&mut self.string_scratchpad[0]);
+                    if ui.button("Bump Fee").clicked() {
+                        let fee_rate = self.string_scratchpad[0]
+                            .trim()
+                            .parse::<f32>()
+                            .ok()
+                            .filter(|fee_rate| {
+                                detail.fee_rate.map_or(true, |current| *fee_rate > current)
+                            });
+                        let result = match fee_rate {
+                            Some(fee_rate) => self
+                                .wallet_model
+                                .bump_fee(&detail.txid, fee_rate)
+                                .map_err(|error| error.to_string()),
+                            None => Err(
+                                "The new fee rate needs to be higher than the current one"
+                                    .to_string(),
+                            ),
+                        };
+                        match result {
+                            Ok(_) => {
+                                self.sync_scheduler.request_sync_all();
+                                self.change_state(CentralPanelState::WalletMain);
+                            }
+                            Err(error) => {
+                                self.dialog_box = Some(DialogBox {
+                                    dialog_box_enum: DialogBoxEnum::InvalidTransaction,
+                                    title: "Fee Bump Failed",
+                                    dialog_line_edit: Vec::from([DialogLineEdit {
+                                        message: Some(error),
+                                        line_edit: None,
+                                    }]),
+                                    optional: false,
+                                })
+                            }
+                        }
+                    }
+                });
+            }
+            ui.add_space(20.0);
+            ui.heading("Inputs");
+            TableBuilder::new(ui)
+                .column(Column::exact(450.0))
+                .column(Column::exact(150.0))
+                .column(Column::exact(80.0))
+                .header(20.0, |mut header| {
+                    header.col(|ui| {
+                        ui.heading("Previous Output");
+                    });
+                    header.col(|ui| {
+                        ui.heading("Value");
+                    });
+                    header.col(|ui| {
+                        ui.heading("Mine");
+                    });
+                })
+                .body(|mut body| {
+                    for input in detail.inputs.iter() {
+                        body.row(30.0, |mut row| {
+                            row.col(|ui| {
+                                ui.label(&input.previous_output);
+                            });
+                            row.col(|ui| {
+                                match input.value {
+                                    Some(value) => {
+                                        ui.label(self.format_amount(value));
+                                    }
+                                    None => {
+                                        ui.label(unknown()).on_hover_text(
+                                            "Values are only known for inputs spending this wallet's outputs",
+                                        );
+                                    }
+                                }
+                            });
+                            row.col(|ui| {
+                                ui.label(if input.is_mine { "Yes" } else { "" });
+                            });
+                        });
+                    }
+                });
+            ui.add_space(20.0);
+            ui.heading("Outputs");
+            TableBuilder::new(ui)
+                .column(Column::exact(450.0))
+                .column(Column::exact(150.0))
+                .column(Column::exact(80.0))
+                .header(20.0, |mut header| {
+                    header.col(|ui| {
+                        ui.heading("Address");
+                    });
+                    header.col(|ui| {
+                        ui.heading("Value");
+                    });
+                    header.col(|ui| {
+                        ui.heading("Mine");
+                    });
+                })
+                .body(|mut body| {
+                    for output in detail.outputs.iter() {
+                        body.row(30.0, |mut row| {
+                            row.col(|ui| {
+                                ui.label(output.address.as_ref().unwrap_or(&output.script));
+                            });
+                            row.col(|ui| {
+                                ui.label(self.format_amount(output.value));
+                            });
+                            row.col(|ui| {
+                                let ownership = match (output.is_mine, output.is_change) {
+                                    (true, true) => "Change",
+                                    (true, false) => "Yes",
+                                    _ => "",
+                                };
+                                ui.label(ownership);
+                            });
+                        });
+                    }
+                });
+            ui.add_space(20.0);
+            ui.heading("Raw Transaction");
+            if ui.button("Copy Raw Hex").clicked() {
+                ui.output_mut(|o| o.copied_text = detail.raw_hex.clone());
+            }
+            egui::ScrollArea::vertical()
+                .max_height(100.0)
+                .show(ui, |ui| {
+                    ui.add(
+                        egui::TextEdit::multiline(&mut detail.raw_hex.as_str())
+                            .desired_width(f32::INFINITY),
+                    );
+                });
+        });
+    }
+
+    pub fn render_utxos_panel(
+        &mut self,
+        ctx: &egui::Context,
+        ui: &mut Ui,
+        watch: bool,
+        source: Option<CentralPanelState>,
+    ) {
+        self.boiler_plate_render(ui, watch, &source);
+        ui.vertical_centered(|ui| {
+            ui.add_space(20.0);
+            ui.heading("UTXOs & Labels");
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                if ui.button("Import BIP329 Labels").clicked() {
+                    if let Some(file_path) = tinyfiledialogs::open_file_dialog(
+                        "Import Labels",
+                        &self.folder_path,
+                        Some((&["*.jsonl", "*.json"], "BIP329 Labels")),
+                    ) {
+                        let message = match std::fs::read_to_string(&file_path)
+                            .map_err(|error| error.into())
+                            .and_then(|contents| self.wallet_model.import_labels(&contents))
+                        {
+                            Ok(count) => format!("Imported {} labels", count),
+                            Err(error) => error.to_string(),
+                        };
+                        self.dialog_box = Some(DialogBox {
+                            dialog_box_enum: DialogBoxEnum::WalletCreated,
+                            title: "Import Labels",
+                            dialog_line_edit: Vec::from([DialogLineEdit {
+                                message: Some(message),
+                                line_edit: None,
+                            }]),
+                            optional: false,
+                        })
+                    }
+                }
+                if ui.button("Export BIP329 Labels").clicked() {
+                    let default_path = format!("{}/labels.jsonl", self.folder_path);
+                    if let Some(file_path) =
+                        tinyfiledialogs::save_file_dialog("Export Labels", &default_path)
+                    {
+                        let message = match self
+                            .wallet_model
+                            .export_labels()
+                            .and_then(|labels| Ok(std::fs::write(&file_path, labels)?))
+                        {
+                            Ok(_) => format!("Saved to {}", file_path),
+                            Err(error) => error.to_string(),
+                        };
+                        self.dialog_box = Some(DialogBox {
+                            dialog_box_enum: DialogBoxEnum::WalletCreated,
+                            title: "Export Labels",
+                            dialog_line_edit: Vec::from([DialogLineEdit {
+                                message: Some(message),
+                                line_edit: None,
+                            }]),
+                            optional: false,
+                        })
+                    }
+                }
+            });
+            ui.add_space(20.0);
+            ui.separator();
+            let wallet = self.wallet_model.get_active_wallet_data();
+            let mut edited_label = None;
+            TableBuilder::new(ui)
+                .column(Column::exact(200.0).resizable(true))
+                .column(Column::exact(200.0).resizable(true))
+                .column(Column::exact(120.0))
+                .column(Column::exact(200.0))
+                .header(20.0, |mut header| {
+                    header.col(|ui| {
+                        ui.heading("Outpoint");
+                    });
+                    header.col(|ui| {
+                        ui.heading("Address");
+                    });
+                    header.col(|ui| {
+                        ui.heading("Amount");
+                    });
+                    header.col(|ui| {
+                        ui.heading("Label");
+                    });
+                })
+                .body(|mut body| {
+                    for utxo in wallet.utxos.iter() {
+                        let outpoint = utxo.outpoint.to_string();
+                        let address =
+                            Address::from_script(&utxo.txout.script_pubkey, Network::Testnet)
+                                .map(|address| address.to_string())
+                                .unwrap_or_default();
+                        body.row(30.0, |mut row| {
+                            row.col(|ui| {
+                                ui.label(&outpoint).on_hover_text(&outpoint);
+                            });
+                            row.col(|ui| {
+                                ui.horizontal(|ui| {
+                                    if ui.button("✏").on_hover_text("Edit Address Label").clicked()
+                                    {
+                                        edited_label = Some((LabelType::Addr, address.clone()));
+                                    }
+                                    let address_label =
+                                        get_label(&wallet.labels, LabelType::Addr, &address)
+                                            .unwrap_or(&address);
+                                    ui.label(address_label).on_hover_text(&address);
+                                });
+                            });
+                            row.col(|ui| {
+                                ui.label(self.format_amount(utxo.txout.value));
+                            });
+                            row.col(|ui| {
+                                ui.horizontal(|ui| {
+                                    if ui.button("✏").on_hover_text("Edit UTXO Label").clicked() {
+                                        edited_label = Some((LabelType::Output, outpoint.clone()));
+                                    }
+                                    ui.label(
+                                        get_label(&wallet.labels, LabelType::Output, &outpoint)
+                                            .unwrap_or_default(),
+                                    );
+                                });
+                            });
+                        });
+                    }
+                });
+            if let Some((label_type, reference)) = edited_label {
+                self.open_label_dialog(label_type, reference);
+            }
+        });
+    }
+
+    pub fn render_payment_requests_panel(
+        &mut self,
+        ctx: &egui::Context,
+        ui: &mut Ui,
+        watch: bool,
+        source: Option<CentralPanelState>,
+    ) {
+        self.boiler_plate_render(ui, watch, &source);
+        ui.vertical_centered(|ui| {
+            ui.add_space(20.0);
+            ui.heading("Request Payment");
+            ui.add_space(10.0);
+            ui.label(format!("Amount ({})", self.display_unit.name()));
+            ui.text_edit_singleline(&mut self.string_scratchpad[0]);
+            ui.label("Memo");
+            ui.text_edit_singleline(&mut self.string_scratchpad[1]);
+            ui.label("Expires After (Hours)");
+            ui.text_edit_singleline(&mut self.string_scratchpad[2]);
+            ui.add_space(10.0);
+            if ui.button("Create Request").clicked() {
+                let amount = self.parse_amount(&self.string_scratchpad[0]);
+                let expiry_hours = self.string_scratchpad[2].trim().parse::<i64>();
+                let result = match (amount, expiry_hours) {
+                    (Ok(amount), Ok(expiry_hours)) => self
+                        .wallet_model
+                        .add_payment_request(amount, &self.string_scratchpad[1], expiry_hours)
+                        .map_err(|error| error.to_string()),
+                    (Err(error), _) => Err(error.to_string()),
+                    (_, Err(_)) => Err("Expiry needs to be a number of hours".to_string()),
+                };
+                let (title, message) = match result {
+                    Ok(payment_request) => {
+                        self.clear_string_scratchpad();
+                        ("Payment Request Created", Some(payment_request.address))
+                    }
+                    Err(error) => ("Payment Request Not Created", Some(error)),
+                };
+                self.dialog_box = Some(DialogBox {
+                    dialog_box_enum: DialogBoxEnum::WalletCreated,
+                    title: title,
+                    dialog_line_edit: Vec::from([DialogLineEdit {
+                        message: message,
+                        line_edit: None,
+                    }]),
+                    optional: false,
+                })
+            }
+            ui.add_space(20.0);
+            ui.separator();
+            let now = chrono::Local::now().timestamp();
+            let mut deleted_address = None;
+            TableBuilder::new(ui)
+                .column(Column::exact(200.0).resizable(true))
+                .column(Column::exact(200.0).resizable(true))
+                .column(Column::exact(100.0))
+                .column(Column::exact(100.0))
+                .column(Column::exact(120.0))
+                .column(Column::exact(160.0))
+                .column(Column::exact(100.0))
+                .header(20.0, |mut header| {
+                    header.col(|ui| {
+                        ui.heading("Memo");
+                    });
+                    header.col(|ui| {
+                        ui.heading("Address");
+                    });
+                    header.col(|ui| {
+                        ui.heading("Amount");
+                    });
+                    header.col(|ui| {
+                        ui.heading("Received");
+                    });
+                    header.col(|ui| {
+                        ui.heading("Status");
+                    });
+                    header.col(|ui| {
+                        ui.heading("Expires");
+                    });
+                    header.col(|ui| {});
+                })
+                .body(|mut body| {
+                    let payment_requests =
+                        self.wallet_model.get_active_wallet_data().payment_requests;
+                    for payment_request in payment_requests.iter().rev() {
+                        let status = payment_request.status(now);
+                        body.row(30.0, |mut row| {
+                            row.col(|ui| {
+                                ui.label(&payment_request.memo);
+                            });
+                            row.col(|ui| {
+                                ui.label(&payment_request.address);
+                            });
+                            row.col(|ui| {
+                                ui.label(self.format_amount(payment_request.amount));
+                            });
+                            row.col(|ui| {
+                                ui.label(self.format_amount(payment_request.received));
+                            });
+                            row.col(|ui| {
+                                ui.label(status.name());
+                            });
+                            row.col(|ui| {
+                                let expires_at = Local
+                                    .timestamp_opt(payment_request.expires_at, 0)
+                                    .single()
+                                    .map(|expires_at| {
+                                        expires_at.format("%Y-%m-%d %H:%M").to_string()
+                                    })
+                                    .unwrap_or_default();
+                                ui.label(expires_at);
+                            });
+                            row.col(|ui| {
+                                ui.horizontal(|ui| {
+                                    if ui.button("📋").on_hover_text("Copy Payment URI").clicked()
+                                    {
+                                        let payment_uri = PaymentUri {
+                                            address: payment_request.address.clone(),
+                                            amount: Some(payment_request.amount),
+                                            label: None,
+                                            message: Some(payment_request.memo.clone())
+                                                .filter(|memo| !memo.is_empty()),
+                                        };
+                                        ui.output_mut(|o| o.copied_text = payment_uri.to_uri());
+                                    }
+                                    if ui
+                                        .add(egui::Label::new("✖").sense(egui::Sense::click()))
+                                        .on_hover_text("Delete Request")
+                                        .clicked()
+                                    {
+                                        deleted_address = Some(payment_request.address.clone());
+                                    }
+                                });
+                            });
+                        });
+                    }
+                });
+            if let Some(address) = deleted_address {
+                if let Err(error) = self.wallet_model.delete_payment_request(&address) {
+                    self.dialog_box = Some(DialogBox {
+                        dialog_box_enum: DialogBoxEnum::WalletCreated,
+                        title: "Unable To Delete Request",
+                        dialog_line_edit: Vec::from([DialogLineEdit {
+                            message: Some(error.to_string()),
+                            line_edit: None,
+                        }]),
+                        optional: false,
+                    })
+                }
+            }
+        });
+    }
+
+    pub fn render_sweep_panel(
+        &mut self,
+        ctx: &egui::Context,
+        ui: &mut Ui,
+        watch: bool,
+        source: Option<CentralPanelState>,
+        destination: CentralPanelState,
+        sweep_transaction: Option<SweepTransaction>,
+    ) {
+        self.boiler_plate_render(ui, watch, &source);
+        ui.vertical_centered(|ui| {
+            ui.add_space(30.0);
+            ui.heading("Sweep Private Keys");
+            ui.add_space(10.0);
+            ui.label("WIF private keys, one per line");
+            ui.text_edit_multiline(&mut self.string_scratchpad[0]);
+            if ui.button("Load From QR Image").clicked() {
+                if let Some(file_path) = tinyfiledialogs::open_file_dialog(
+                    "Open QR Image",
+                    &self.folder_path,
+                    Some((&["*.png", "*.jpg", "*.jpeg"], "Images")),
+                ) {
+                    match decode_qr_image(&file_path) {
+                        Ok(wif) => {
+                            if !self.string_scratchpad[0].trim().is_empty() {
+                                self.string_scratchpad[0].push('\n');
+                            }
+                            self.string_scratchpad[0].push_str(wif.trim());
+                        }
+                        Err(error) => {
+                            self.dialog_box = Some(DialogBox {
+                                dialog_box_enum: DialogBoxEnum::InvalidTransaction,
+                                title: "Unable To Read QR Code",
+                                dialog_line_edit: Vec::from([DialogLineEdit {
+                                    message: Some(error.to_string()),
+                                    line_edit: None,
+                                }]),
+                                optional: false,
+                            })
+                        }
+                    }
+                }
+            }
+            ui.add_space(20.0);
+            ui.label("Fee Rate (sat/vB)");
+            ui.text_edit_singleline(&mut self.string_scratchpad[1]);
+            // Fee estimates and fund searches share the one sweep worker
+            let searching = self.sweep_worker.is_some();
+            ui.horizontal(|ui| {
+                for (label, target) in SWEEP_FEE_TARGETS {
+                    if ui
+                        .add_enabled(!searching, egui::Button::new(label))
+                        .clicked()
+                    {
+                        self.sweep_worker =
+                            Some(self.wallet_model.estimate_fee_rate_in_background(
+                                target,
+                                self.sync_data_sender.clone(),
+                            ));
+                    }
+                }
+            });
+            ui.add_space(20.0);
+            if ui
+                .add_enabled(!searching, egui::Button::new("Find Funds"))
+                .clicked()
+            {
+                let result = match self.string_scratchpad[1].trim().parse::<f32>() {
+                    Ok(fee_rate) if fee_rate >= 1.0 => self
+                        .wallet_model
+                        .find_sweep_funds_in_background(
+                            &self.string_scratchpad[0],
+                            fee_rate,
+                            self.sync_data_sender.clone(),
+                        )
+                        .map_err(|error| error.to_string()),
+                    _ => Err("Fee rate needs to be a number of at least 1 sat/vB".to_string()),
+                };
+                match result {
+                    Ok(handle) => self.sweep_worker = Some(handle),
+                    Err(error) => self.show_sweep_error(error),
+                }
+            }
+            if searching {
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Waiting for the server...");
+                });
+            }
+            let Some(sweep_transaction) = sweep_transaction else {
+                return;
+            };
+            ui.add_space(30.0);
+            ui.label(format!(
+                "Inputs Found: {}",
+                sweep_transaction.transaction.input.len()
+            ));
+            ui.label(format!(
+                "Amount: {}",
+                self.format_amount(sweep_transaction.amount)
+            ));
+            ui.label(format!(
+                "Fee: {}",
+                self.format_amount(sweep_transaction.fee)
+            ));
+            ui.label(format!("Destination: {}", sweep_transaction.address));
+            ui.add_space(20.0);
+            if ui.button("Sweep").clicked() {
+                let result = self
+                    .wallet_model
+                    .broadcast_sweep_transaction(&sweep_transaction);
+                let (title, message) = match result {
+                    Ok(_) => {
+                        self.change_state(destination);
+                        (
+                            "Sweep Broadcast",
+                            Some(sweep_transaction.transaction.txid().to_string()),
+                        )
+                    }
+                    Err(error) => ("Sweep Failed", Some(error.to_string())),
+                };
+                self.dialog_box = Some(DialogBox {
+                    dialog_box_enum: DialogBoxEnum::WalletCreated,
+                    title: title,
+                    dialog_line_edit: Vec::from([DialogLineEdit {
+                        message: message,
+                        line_edit: None,
+                    }]),
+                    optional: false,
+                })
+            }
+        });
+    }
+
+    pub fn render_contacts_panel(
+        &mut self,
+        ctx: &egui::Context,
+        ui: &mut Ui,
+        watch: bool,
+        source: Option<CentralPanelState>,
+    ) {
+        self.boiler_plate_render(ui, watch, &source);
+        ui.add_space(20.0);
+
+        ui.vertical_centered(|ui| {
+            ui.heading("Contact List");
+            ui.add_space(10.0);
+            if ui.button("Add Contact").clicked() {
+                self.change_state(CentralPanelState::ContactsNewContact);
+            }
+            ui.add_space(10.0);
+            TableBuilder::new(ui)
+                .column(Column::exact(250.0).resizable(false))
+                .column(Column::exact(350.0))
+                .column(Column::exact(100.0))
+                .column(Column::exact(100.0))
+                .header(20.0, |mut header| {
+                    header.col(|ui| {
+                        ui.heading("Wallet Name");
+                    });
+                    header.col(|ui| {
+                        ui.heading("Public Key");
+                    });
+                    header.col(|ui| {
+                        ui.heading("Last Transaction Txid");
+                    });
+                    header.col(|ui| {});
+                })
+                .body(|mut body| {
+                    let contacts = self.wallet_model.json_wallet_data.contacts.clone();
+                    for contact in contacts.iter() {
+                        let last_transaction =
+                            self.wallet_model.last_transaction(&contact.pub_key.clone());
+                        body.row(30.0, |mut row| {
+                            row.col(|ui| {
+                                ui.horizontal(|ui| {
+                                    if ui
+                                        .button(contact.wallet_name.clone())
+                                        .on_hover_text("Change")
+                                        .clicked()
+                                    {
+                                        self.change_state(CentralPanelState::ContactsRename {
+                                            pub_key: contact.pub_key.clone(),
+                                        });
+                                    }
+                                });
+                            });
+
+                            row.col(|ui| {
+                                if ui
+                                    .add(
+                                        egui::Label::new(&contact.pub_key)
+                                            .sense(egui::Sense::click()),
+                                    )
+                                    .on_hover_text("Copy Public Key")
+                                    .clicked()
+                                {
+                                    ui.output_mut(|o| {
+                                        o.copied_text = contact.pub_key.clone();
+                                    });
+                                }
+                                // ui.label(contact.pub_key.clone())
+                                //     .sense(egui::Sense::click()).;
+                            });
+
+                            row.col(|ui| {
+                                let mut last_transaction_string = "None".to_string();
+                                if let Some(last_transaction) = last_transaction.clone() {
+                                    last_transaction_string = format!("{}", last_transaction.txid);
+                                }
+                                ui.label(last_transaction_string);
+                            });
+                            row.col(|ui| {
+                                if let None = last_transaction {
+                                    if ui
+                                        .add(egui::Label::new("✖").sense(egui::Sense::click()))
+                                        .on_hover_text("Delete Contact")
+                                        .clicked()
+                                    {
+                                        ui.output_mut(|o| {
+                                            self.change_state(CentralPanelState::ContactsDelete {
+                                                pub_key: contact.pub_key.clone(),
+                                            });
+                                        });
+                                    }
+                                }
+                            });
+                        });
+                    }
+                });
+        });
+    }
+
+    pub fn render_new_wallet_creation(
+        &mut self,
+        ctx: &egui::Context,
+        ui: &mut Ui,
+        watch: bool,
+        source: Option<CentralPanelState>,
+        destination: CentralPanelState,
+        mnemonic_string: &str,
+    ) {
+        self.boiler_plate_render(ui, watch, &source);
+        ui.vertical_centered(|ui| {
+            ui.add_space(50.0);
+            ui.heading("Write down the following mnemonic");
+            ui.add_space(20.0);
+
+            let words: Vec<&str> = mnemonic_string.split_whitespace().collect();
+            for (row_index, row) in words.chunks(MNEMONIC_WORDS_PER_ROW).enumerate() {
+                let numbered_words: Vec<String> = row
+                    .iter()
+                    .enumerate()
+                    .map(|(word_index, word)| {
+                        format!(
+                            "{}. {}",
+                            row_index * MNEMONIC_WORDS_PER_ROW + word_index + 1,
+                            word
+                        )
+                    })
+                    .collect();
+                ui.strong(numbered_words.join("    "));
+            }
+            ui.add_space(30.0);
+            ui.label("You will be asked for some of these words on the next page");
+            ui.add_space(20.0);
+            if ui.button("I Have Written It Down").clicked() {
+                let current_state = self.central_panel_state.clone();
+                self.change_state(CentralPanelState::WalletBackupQuiz {
+                    mnemonic_string: mnemonic_string.to_string(),
+                    word_indices: generate_backup_quiz_indices(mnemonic_string),
+                    source: Box::new(current_state),
+                    destination: Box::new(destination),
+                });
+            }
+        });
+    }
+
+    pub fn render_backup_quiz_panel(
+        &mut self,
+        ctx: &egui::Context,
+        ui: &mut Ui,
+        watch: bool,
+        source: Option<CentralPanelState>,
+        destination: CentralPanelState,
+        mnemonic_string: &str,
+        word_indices: &[usize],
+    ) {
+        self.boiler_plate_render(ui, watch, &source);
+        let priv_key = generate_xpriv(&mnemonic_string).unwrap().to_string();
+        let existing_pub_key = self
+            .wallet_model
+            .json_wallet_data
+            .wallets
+            .iter()
+            .find(|wallet| wallet.priv_key == Some(priv_key.clone()))
+            .map(|wallet| wallet.pub_key.clone());
+        ui.vertical_centered(|ui| {
+            ui.add_space(50.0);
+            ui.heading("Confirm the following words from your mnemonic");
+            ui.add_space(20.0);
+            for (answer_index, word_index) in word_indices.iter().enumerate() {
+                ui.label(format!("Word #{}", word_index + 1));
+                ui.add_space(10.0);
+                ui.text_edit_singleline(&mut self.string_scratchpad[answer_index]);
+                ui.add_space(10.0);
+            }
+            if existing_pub_key.is_none() {
+                ui.add_space(10.0);
+                ui.label("Wallet Name");
+                ui.add_space(10.0);
+                ui.text_edit_singleline(&mut self.string_scratchpad[BACKUP_QUIZ_WORD_COUNT]);
+            }
+            ui.add_space(20.0);
+            if ui.button("Confirm").clicked() {
+                let answers = &self.string_scratchpad[..word_indices.len()];
+                let correct = is_backup_quiz_correct(&mnemonic_string, word_indices, answers);
+                let (dialog_box_enum, title) = match (correct, existing_pub_key) {
+                    (true, None) => {
+                        self.wallet_model
+                            .add_wallet(
+                                &priv_key,
+                                &mnemonic_string,
+                                &self.string_scratchpad[BACKUP_QUIZ_WORD_COUNT],
+                                true,
+                            )
+                            .unwrap();
+                        self.change_state(destination);
+                        (DialogBoxEnum::WalletCreated, "Wallet Created")
+                    }
+                    (true, Some(pub_key)) => {
+                        self.wallet_model.set_backup_verified(&pub_key).unwrap();
+                        self.change_state(destination);
+                        (DialogBoxEnum::BackupVerified, "Backup Verified")
+                    }
+                    (false, _) => {
+                        self.clear_string_scratchpad();
+                        (DialogBoxEnum::IncorrectMnemonic, "Incorrect Words")
+                    }
+                };
+
+                self.dialog_box = Some(DialogBox {
+                    dialog_box_enum: dialog_box_enum,
+                    title: title,
+                    dialog_line_edit: Vec::from([DialogLineEdit {
+                        message: None,
+                        line_edit: None,
+                    }]),
+                    optional: false,
+                })
+            }
+        });
+    }
+
+    pub fn render_new_wallet_existing(
+        &mut self,
+        ctx: &egui::Context,
+        ui: &mut Ui,
+        watch: bool,
+        source: Option<CentralPanelState>,
+        destination: CentralPanelState,
+    ) {
+        self.boiler_plate_render(ui, watch, &source);
+        ui.vertical_centered(|ui| {
+            ui.add_space(50.0);
+            ui.heading("Type in the mnemonic for an existing wallet");
+            ui.add_space(20.0);
+            ui.text_edit_singleline(&mut self.string_scratchpad[0]);
+            ui.add_space(50.0);
+            ui.heading("Wallet Name");
+            ui.add_space(20.0);
+            ui.text_edit_singleline(&mut self.string_scratchpad[1]);
+
+            if ui.button("Confirm").clicked() {
+                let parse_result = generate_xpriv(&self.string_scratchpad[0]);
+
+                let title = match &parse_result {
+                    Ok(xprv) => {
+                        let wallet_in_use =
+                            self.wallet_model.wallets_contain_wallet(&xprv.to_string());
+                        if !wallet_in_use {
+                            self.wallet_model
+                                .add_wallet(
+                                    &xprv.to_string(),
+                                    &self.string_scratchpad[0],
+                                    &self.string_scratchpad[1],
+                                    true,
+                                )
+                                .unwrap();
+                            self.change_state(destination);
+                            "Wallet Added"
+                        } else {
+                            "Wallet Already In Use"
+                        }
+                    }
+                    Err(_) => "Mnemonic Incorrect",
+                };
+
+                self.dialog_box = Some(DialogBox {
+                    dialog_box_enum: DialogBoxEnum::WalletCreated,
+                    title: title,
+                    dialog_line_edit: Vec::from([DialogLineEdit {
+                        message: None,
+                        line_edit: None,
+                    }]),
+                    optional: false,
+                })
+            }
+        });
+    }
+
+    pub fn render_new_contact(
+        &mut self,
+        ctx: &egui::Context,
+        ui: &mut Ui,
+        watch: bool,
+        source: Option<CentralPanelState>,
+        destination: CentralPanelState,
+    ) {
+        self.boiler_plate_render(ui, watch, &source);
+        ui.vertical_centered(|ui| {
+            ui.add_space(50.0);
+            ui.heading("Public Key");
+            ui.add_space(20.0);
+            ui.text_edit_singleline(&mut self.string_scratchpad[0]);
+            ui.add_space(50.0);
+            ui.heading("Wallet Name");
+            ui.add_space(20.0);
+            ui.text_edit_singleline(&mut self.string_scratchpad[1]);
+
+            if ui.button("Confirm").clicked() {
+                let valid_bitcoin_addr = is_valid_bitcoin_address(&self.string_scratchpad[0]);
+                let mut title = "Wallet Created";
+                let wallet_in_use = self
+                    .wallet_model
+                    .contains_wallet(&self.string_scratchpad[0]);
+                let title = match (valid_bitcoin_addr, wallet_in_use) {
+                    (true, false) => {
+                        self.wallet_model
+                            .add_contact(&self.string_scratchpad[0], &self.string_scratchpad[1]);
+                        self.change_state(destination);
+                        "Wallet Created"
+                    }
+                    (true, true) => "Wallet Already In Use",
+                    (false, _) => "Invalid Bitcoin Address",
+                };
+                self.dialog_box = Some(DialogBox {
+                    dialog_box_enum: DialogBoxEnum::WalletCreated,
+                    title: title,
+                    dialog_line_edit: Vec::from([DialogLineEdit {
+                        message: None,
+                        line_edit: None,
+                    }]),
+                    optional: false,
+                });
+            }
+        });
+    }
+
+    pub fn render_create_password_panel(
+        &mut self,
+        ctx: &egui::Context,
+        ui: &mut Ui,
+        watch: bool,
+        source: Option<CentralPanelState>,
+        destination: CentralPanelState,
+    ) {
+        self.boiler_plate_render(ui, watch, &source);
+        ui.vertical_centered(|ui| {
+            ui.add_space(50.0);
+            ui.label("Type New Password");
+            let password_entry =
+                egui::TextEdit::singleline(&mut self.string_scratchpad[0]).password(true);
+
+            ui.add(password_entry);
+            ui.add_space(20.0);
+            ui.label("Confirm Password");
+            let password_entry_confirmation =
+                egui::TextEdit::singleline(&mut self.string_scratchpad[1]).password(true);
+            ui.add(password_entry_confirmation);
+            let mut password_strength = 0;
+
+            if let Ok(password_strength_estimate) = zxcvbn(&self.string_scratchpad[0], &[]) {
+                password_strength = password_strength_estimate.score();
+            }
+
+            if self.string_scratchpad[0] != self.string_scratchpad[1] {
+                ui.add_space(30.0);
+                ui.label("Passwords not the same");
+            } else {
+                let password_strength_bar =
+                    egui::widgets::ProgressBar::new(password_strength as f32 / 4.0);
+                ui.add_space(30.0);
+                ui.add_sized([285.0, 0.0], password_strength_bar);
+                let password_strength_string: &str;
+                match password_strength {
+                    0 => password_strength_string = "Very Weak",
+                    1 => password_strength_string = "Weak",
+                    2 => password_strength_string = "So-so",
+                    3 => password_strength_string = "Good",
+                    4 => password_strength_string = "Great",
+                    _ => password_strength_string = "NP-Badboy",
+                }
+                ui.add_space(10.0);
+                ui.label(password_strength_string);
+                ui.add_space(30.0);
+                if ui.button("Enter").clicked() {
+                    self.wallet_model
+                        .create_passworded_file(self.string_scratchpad[0].clone())
+                        .unwrap();
+
+                    self.change_state(destination);
+                }
+            };
+        });
+    }
+
+    pub fn render_enter_password_panel(
+        &mut self,
+        ctx: &egui::Context,
+        ui: &mut Ui,
+        watch: bool,
+        source: Option<CentralPanelState>,
+        destination: CentralPanelState,
+    ) {
+        self.boiler_plate_render(ui, watch, &source);
+        ui.vertical_centered(|ui| {
+            ui.add_space(20.0);
+            let password_entry =
+                egui::TextEdit::singleline(&mut self.string_scratchpad[0]).password(true);
+            ui.heading("Enter Password");
+            ui.add_space(20.0);
+            ui.add(password_entry);
+            ui.add_space(20.0);
+
+            if ui.button("Enter").clicked() {
+                if self
+                    .wallet_model
+                    .validate_password(&self.string_scratchpad[0])
+                {
+                    self.initialise_last_interaction_time();
+                    self.change_state(destination);
+                } else {
+                    self.clear_string_scratchpad();
+                }
+            }
+        });
+    }
+
+    pub fn render_settings_panel(
+        &mut self,
+        ctx: &egui::Context,
+        ui: &mut Ui,
+        watch: bool,
+        source: Option<CentralPanelState>,
+    ) {
+        self.boiler_plate_render(ui, watch, &source);
+
+        ui.vertical_centered(|ui| {
+            ui.add_space(20.0);
+            ui.heading("Change Settings");
+            ui.add_space(30.0);
+            if ui.button("Change Password").clicked() {
+                self.change_state(CentralPanelState::SettingsChangePassword);
+            }
+            ui.add_space(20.0);
+            ui.heading("Wallet File Path");
+            if ui.button(&self.folder_path).clicked() {
+                self.folder_path =
+                    tinyfiledialogs::select_folder_dialog("Select Folder", &self.folder_path)
+                        .unwrap_or_else(|| self.folder_path.clone());
+            }
+            ui.horizontal(|ui| {
+                let width = ui.available_width();
+                let mut style: egui::Style = (*ctx.style()).clone();
+                ui.add_space((width - style.spacing.slider_width) / 2.0);
+                ui.add(
+                    egui::Slider::new(&mut self.password_needed_timeout_s, 3..=1200)
+                        .custom_formatter(|n, _| {
+                            let n = n as i32;
+                            let mins = (n / 60) % 60;
+                            let secs = n % 60;
+                            format!("{mins:02} mins {secs:02} secs")
+                        }),
+                );
+            });
+            ui.label("Idle Time before your password is required");
+            ui.add_space(20.0);
+            let mut sync_interval_s = self.sync_scheduler.interval.as_secs();
+            let sync_interval_slider =
+                ui.add(egui::Slider::new(&mut sync_interval_s, 10..=3600).suffix(" s"));
+            ui.label("Time between background wallet syncs");
+            self.sync_scheduler.interval = std::time::Duration::from_secs(sync_interval_s);
+            // Saved once dragging ends rather than on every intermediate value
+            if sync_interval_slider.drag_released()
+                || (sync_interval_slider.changed() && !sync_interval_slider.dragged())
+            {
+                let result = self.wallet_model.set_sync_interval(sync_interval_s);
+                self.show_settings_error(result);
+            }
+            ui.add_space(20.0);
+            let final_confirmations_slider =
+                ui.add(egui::Slider::new(&mut self.final_confirmations, 1..=100));
+            ui.label("Confirmations before a transaction is shown as final");
+            if final_confirmations_slider.drag_released()
+                || (final_confirmations_slider.changed() && !final_confirmations_slider.dragged())
+            {
+                let result = self
+                    .wallet_model
+                    .set_final_confirmations(self.final_confirmations);
+                self.show_settings_error(result);
+            }
+            ui.add_space(20.0);
+            egui::ComboBox::from_label("Fiat Currency")
+                .selected_text(self.fiat_valuation.currency.name())
+                .show_ui(ui, |ui| {
+                    for currency in FIAT_CURRENCIES {
+                        if ui
+                            .selectable_value(
+                                &mut self.fiat_valuation.currency,
+                                currency,
+                                currency.name(),
+                            )
+                            .changed()
+                        {
+                            let result = self.wallet_model.set_fiat_currency(currency);
+                            self.show_settings_error(result);
+                        }
+                    }
+                });
+            ui.horizontal(|ui| {
+                ui.label("Price Source");
+                if ui.button("CoinGecko").clicked() {
+                    self.set_price_source(PriceSource::CoinGecko);
+                }
+                if ui.button("Load Price File").clicked() {
+                    if let Some(file_path) = tinyfiledialogs::open_file_dialog(
+                        "Open Price File",
+                        &self.folder_path,
+                        Some((&["*.json", "*.csv"], "Price Files")),
+                    ) {
+                        self.set_price_source(PriceSource::File { path: file_path });
+                    }
+                }
+            });
+            if let Some(error) = self.fiat_valuation.last_error() {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+            ui.add_space(20.0);
+            egui::ComboBox::from_label("Display Unit")
+                .selected_text(self.display_unit.name())
+                .show_ui(ui, |ui| {
+                    for unit in BITCOIN_UNITS {
+                        if ui
+                            .selectable_value(&mut self.display_unit, unit, unit.name())
+                            .changed()
+                        {
+                            let result = self
+                                .wallet_model
+                                .set_display_format(self.display_unit, self.number_format);
+                            self.show_settings_error(result);
+                        }
+                    }
+                });
+            ui.add_space(20.0);
+            egui::ComboBox::from_label("Blockchain Backend")
+                .selected_text(self.backend_config.kind.name())
+                .show_ui(ui, |ui| {
+                    for kind in BACKEND_KINDS {
+                        if ui
+                            .selectable_value(&mut self.backend_config.kind, kind, kind.name())
+                            .clicked()
+                        {
+                            self.backend_config.url = kind.default_url().to_string();
+                        }
+                    }
+                });
+            if self.backend_config.kind == BackendKind::CompactFilters {
+                ui.label("Peers (comma separated host:port)");
+            } else {
+                ui.label("Server URL");
+            }
+            ui.text_edit_singleline(&mut self.backend_config.url);
+            if self.backend_config.kind == BackendKind::BitcoinCore {
+                ui.horizontal(|ui| {
+                    let is_cookie = matches!(self.backend_config.rpc_auth, RpcAuth::Cookie { .. });
+                    if ui.radio(is_cookie, "Cookie File").clicked() && !is_cookie {
+                        self.backend_config.rpc_auth = RpcAuth::default();
+                    }
+                    if ui.radio(!is_cookie, "Username & Password").clicked() && is_cookie {
+                        self.backend_config.rpc_auth = RpcAuth::UserPass {
+                            username: String::new(),
+                            password: String::new(),
+                        };
+                    }
+                });
+                match &mut self.backend_config.rpc_auth {
+                    RpcAuth::Cookie { file } => {
+                        ui.label("Cookie File Path");
+                        ui.text_edit_singleline(file);
+                    }
+                    RpcAuth::UserPass { username, password } => {
+                        ui.label("RPC Username");
+                        ui.text_edit_singleline(username);
+                        ui.label("RPC Password");
+                        ui.add(egui::TextEdit::singleline(password).password(true));
+                    }
+                }
+            }
             ui.add_space(10.0);
-            ui.label("Wallet Name");
-            ui.add_space(20.0);
-            ui.text_edit_singleline(&mut self.string_scratchpad[1]);
-            if ui.button("Confirm").clicked() {
-                let priv_key = generate_xpriv(&mnemonic_string).unwrap().to_string();
-                let copied_correctly = self.string_scratchpad[0] == mnemonic_string;
-                let wallet_in_use = self.wallet_model.contains_wallet(&priv_key);
-                let title = match (copied_correctly, wallet_in_use) {
-                    (true, false) => {
-                        self.wallet_model
-                            .add_wallet(
-                                &priv_key,
-                                &self.string_scratchpad[0],
-                                &self.string_scratchpad[1],
-                            )
-                            .unwrap();
-                        self.change_state(destination);
-                        "Wallet Created"
+            ui.checkbox(
+                &mut self.backend_config.proxy.enabled,
+                "Use SOCKS5 Proxy (e.g. Tor)",
+            );
+            if self.backend_config.proxy.enabled {
+                ui.label("Proxy Address");
+                ui.text_edit_singleline(&mut self.backend_config.proxy.address);
+                ui.label("Proxy Username (optional)");
+                ui.text_edit_singleline(&mut self.backend_config.proxy.username);
+                ui.label("Proxy Password (optional)");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.backend_config.proxy.password)
+                        .password(true),
+                );
+            }
+            ui.checkbox(
+                &mut self.backend_config.proxy.require_proxy,
+                "Refuse to connect without proxy",
+            );
+            if ui.button("Apply Backend").clicked() {
+                self.backend_config.url = self.backend_config.url.trim().to_string();
+                match self.wallet_model.set_backend(self.backend_config.clone()) {
+                    Ok(_) => {
+                        self.fiat_valuation.set_proxy(&self.backend_config.proxy);
+                        self.sync_scheduler.request_sync_all();
                     }
-                    (true, true) => {
-                        self.change_state(CentralPanelState::WalletNewWallet {
-                            mnemonic_string: generate_mnemonic_string().unwrap(),
-                        });
-                        "Wallet Already In Use"
+                    Err(error) => {
+                        self.dialog_box = Some(DialogBox {
+                            dialog_box_enum: DialogBoxEnum::InvalidTransaction,
+                            title: "Unable To Change Backend",
+                            dialog_line_edit: Vec::from([DialogLineEdit {
+                                message: Some(error.to_string()),
+                                line_edit: None,
+                            }]),
+                            optional: false,
+                        })
                     }
-                    (false, _) => "Incorrectly Copied",
-                };
-
-                self.dialog_box = Some(DialogBox {
-                    dialog_box_enum: DialogBoxEnum::IncorrectMnemonic,
-                    title: title,
-                    dialog_line_edit: Vec::from([DialogLineEdit {
-                        message: None,
-                        line_edit: None,
-                    }]),
-                    optional: false,
-                })
+                }
             }
         });
     }
 
-    pub fn render_new_wallet_existing(
+    pub fn render_delete_wallet_panel(
         &mut self,
         ctx: &egui::Context,
         ui: &mut Ui,
         watch: bool,
         source: Option<CentralPanelState>,
         destination: CentralPanelState,
+        pub_key: String,
     ) {
         self.boiler_plate_render(ui, watch, &source);
+        let (entry_type, wallet) = self.wallet_model.get_wallet_data(&pub_key);
+        let wallet_name = wallet.wallet_name.clone();
         ui.vertical_centered(|ui| {
             ui.add_space(50.0);
-            ui.heading("Type in the mnemonic for an existing wallet");
-            ui.add_space(20.0);
-            ui.text_edit_singleline(&mut self.string_scratchpad[0]);
-            ui.add_space(50.0);
-            ui.heading("Wallet Name");
-            ui.add_space(20.0);
-            ui.text_edit_singleline(&mut self.string_scratchpad[1]);
 
+            ui.heading(format!(
+                "Are you sure you want to delete wallet {}?",
+                wallet_name
+            ));
+            ui.strong(&pub_key);
             if ui.button("Confirm").clicked() {
-                let parse_result = generate_xpriv(&self.string_scratchpad[0]);
-
-                let title = match &parse_result {
-                    Ok(xprv) => {
-                        let wallet_in_use =
-                            self.wallet_model.wallets_contain_wallet(&xprv.to_string());
-                        if !wallet_in_use {
-                            self.wallet_model
-                                .add_wallet(
-                                    &xprv.to_string(),
-                                    &self.string_scratchpad[0],
-                                    &self.string_scratchpad[1],
-                                )
-                                .unwrap();
-                            self.change_state(destination);
-                            "Wallet Added"
-                        } else {
-                            "Wallet Already In Use"
-                        }
+                match entry_type {
+                    EntryType::Wallet => {
+                        self.wallet_model.delete_wallet(&pub_key);
                     }
-                    Err(_) => "Mnemonic Incorrect",
-                };
-
-                self.dialog_box = Some(DialogBox {
-                    dialog_box_enum: DialogBoxEnum::WalletCreated,
-                    title: title,
-                    dialog_line_edit: Vec::from([DialogLineEdit {
-                        message: None,
-                        line_edit: None,
-                    }]),
-                    optional: false,
-                })
+                    EntryType::Contact => {
+                        self.wallet_model.delete_contact(&pub_key);
+                    }
+                }
+                self.change_state(destination);
             }
         });
     }
-
-    pub fn render_new_contact(
+    pub fn render_rename_wallet_panel(
         &mut self,
         ctx: &egui::Context,
         ui: &mut Ui,
         watch: bool,
         source: Option<CentralPanelState>,
         destination: CentralPanelState,
+        pub_key: String,
     ) {
         self.boiler_plate_render(ui, watch, &source);
         ui.vertical_centered(|ui| {
+            let (entry_type, wallet) = self.wallet_model.get_wallet_data(&pub_key);
+
             ui.add_space(50.0);
             ui.heading("Public Key");
             ui.add_space(20.0);
-            ui.text_edit_singleline(&mut self.string_scratchpad[0]);
-            ui.add_space(50.0);
-            ui.heading("Wallet Name");
+
+            ui.label(wallet.pub_key.clone());
+
             ui.add_space(20.0);
-            ui.text_edit_singleline(&mut self.string_scratchpad[1]);
+            ui.heading("Current Wallet Name");
+
+            ui.add_space(10.0);
+            ui.label(wallet.wallet_name.clone());
+            ui.add_space(20.0);
+            ui.heading("New Wallet Name");
+            ui.text_edit_singleline(&mut self.string_scratchpad[0]);
 
             if ui.button("Confirm").clicked() {
-                let valid_bitcoin_addr = is_valid_bitcoin_address(&self.string_scratchpad[0]);
-                let mut title = "Wallet Created";
-                let wallet_in_use = self
-                    .wallet_model
-                    .contains_wallet(&self.string_scratchpad[0]);
-                let title = match (valid_bitcoin_addr, wallet_in_use) {
-                    (true, false) => {
-                        self.wallet_model
-                            .add_contact(&self.string_scratchpad[0], &self.string_scratchpad[1]);
-                        self.change_state(destination);
-                        "Wallet Created"
-                    }
-                    (true, true) => "Wallet Already In Use",
-                    (false, _) => "Invalid Bitcoin Address",
-                };
-                self.dialog_box = Some(DialogBox {
-                    dialog_box_enum: DialogBoxEnum::WalletCreated,
-                    title: title,
-                    dialog_line_edit: Vec::from([DialogLineEdit {
-                        message: None,
-                        line_edit: None,
-                    }]),
-                    optional: false,
-                });
+                self.wallet_model
+                    .rename_wallet(entry_type, &pub_key, &self.string_scratchpad[0]);
+                self.change_state(destination);
             }
         });
     }
 
-    pub fn render_create_password_panel(
+    pub fn render_wallet_secret_panel(
         &mut self,
         ctx: &egui::Context,
         ui: &mut Ui,
         watch: bool,
         source: Option<CentralPanelState>,
-        destination: CentralPanelState,
     ) {
         self.boiler_plate_render(ui, watch, &source);
         ui.vertical_centered(|ui| {
+            let wallet = self.wallet_model.get_active_wallet_data();
             ui.add_space(50.0);
-            ui.label("Type New Password");
-            let password_entry =
-                egui::TextEdit::singleline(&mut self.string_scratchpad[0]).password(true);
-
-            ui.add(password_entry);
+            ui.heading("Mnemonic");
             ui.add_space(20.0);
-            ui.label("Confirm Password");
-            let password_entry_confirmation =
-                egui::TextEdit::singleline(&mut self.string_scratchpad[1]).password(true);
-            ui.add(password_entry_confirmation);
-            let mut password_strength = 0;
 
-            if let Ok(password_strength_estimate) = zxcvbn(&self.string_scratchpad[0], &[]) {
-                password_strength = password_strength_estimate.score();
+            if let Some(mnemonic_string) = wallet.mnemonic {
+                ui.strong(&mnemonic_string);
+                if ui.button("Copy Mnemonic").clicked() {
+                    ui.output_mut(|o| o.copied_text = mnemonic_string);
+                }
+            } else {
+                ui.label("Not available for wallets imported from descriptors");
+            }
+            ui.add_space(20.0);
+            ui.heading("Private Key");
+            ui.add_space(20.0);
+            if let Some(priv_key) = wallet.priv_key {
+                ui.strong(&priv_key);
+                if ui.button("Copy Private Key").clicked() {
+                    ui.output_mut(|o| o.copied_text = priv_key);
+                }
+            } else {
+                ui.label("Not available for wallets imported from descriptors");
             }
+        });
+    }
 
-            if self.string_scratchpad[0] != self.string_scratchpad[1] {
+    pub fn render_wallet_descriptors_panel(
+        &mut self,
+        ctx: &egui::Context,
+        ui: &mut Ui,
+        watch: bool,
+        source: Option<CentralPanelState>,
+    ) {
+        self.boiler_plate_render(ui, watch, &source);
+        let wallet = self.wallet_model.get_active_wallet_data();
+        let (external, internal) = wallet.descriptors().unwrap();
+        let descriptors = [
+            (
+                "Public External Descriptor",
+                public_descriptor_string(&external).ok(),
+            ),
+            (
+                "Public Internal Descriptor",
+                public_descriptor_string(&internal).ok(),
+            ),
+            (
+                "Private External Descriptor",
+                private_descriptor_string(&external).ok().flatten(),
+            ),
+            (
+                "Private Internal Descriptor",
+                private_descriptor_string(&internal).ok().flatten(),
+            ),
+        ];
+        ui.vertical_centered(|ui| {
+            if let Some(policy) = &wallet.policy {
                 ui.add_space(30.0);
-                ui.label("Passwords not the same");
-            } else {
-                let password_strength_bar =
-                    egui::widgets::ProgressBar::new(password_strength as f32 / 4.0);
+                ui.heading("Spending Policy");
+                ui.add_space(10.0);
+                ui.add(egui::Label::new(policy).wrap(true));
+            }
+            for (heading, descriptor) in descriptors {
+                let Some(descriptor) = descriptor else {
+                    continue;
+                };
                 ui.add_space(30.0);
-                ui.add_sized([285.0, 0.0], password_strength_bar);
-                let password_strength_string: &str;
-                match password_strength {
-                    0 => password_strength_string = "Very Weak",
-                    1 => password_strength_string = "Weak",
-                    2 => password_strength_string = "So-so",
-                    3 => password_strength_string = "Good",
-                    4 => password_strength_string = "Great",
-                    _ => password_strength_string = "NP-Badboy",
-                }
+                ui.heading(heading);
                 ui.add_space(10.0);
-                ui.label(password_strength_string);
+                ui.add(egui::Label::new(&descriptor).wrap(true));
+                if ui.button("Copy Descriptor").clicked() {
+                    ui.output_mut(|o| o.copied_text = descriptor);
+                }
+            }
+            if wallet.multisig_policy().is_some() {
                 ui.add_space(30.0);
-                if ui.button("Enter").clicked() {
-                    self.wallet_model
-                        .create_passworded_file(self.string_scratchpad[0].clone())
-                        .unwrap();
+                ui.heading("Export Multisig Configuration");
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    if ui.button("Export BSMS").clicked() {
+                        self.export_multisig_configuration(
+                            "bsms.txt",
+                            export_bsms(&external, &internal),
+                        );
+                    }
+                    if ui.button("Export Coldcard").clicked() {
+                        self.export_multisig_configuration(
+                            "coldcard-multisig.txt",
+                            export_coldcard(&wallet.wallet_name, &external),
+                        );
+                    }
+                });
+            }
+        });
+    }
 
-                    self.change_state(destination);
-                }
-            };
+    fn export_multisig_configuration(
+        &mut self,
+        file_name: &str,
+        configuration: Result<String, anyhow::Error>,
+    ) {
+        let default_path = format!("{}/{}", self.folder_path, file_name);
+        let Some(file_path) =
+            tinyfiledialogs::save_file_dialog("Export Multisig Configuration", &default_path)
+        else {
+            return;
+        };
+        let message = match configuration {
+            Ok(configuration) => match std::fs::write(&file_path, configuration) {
+                Ok(_) => format!("Saved to {}", file_path),
+                Err(error) => error.to_string(),
+            },
+            Err(error) => error.to_string(),
+        };
+        self.dialog_box = Some(DialogBox {
+            dialog_box_enum: DialogBoxEnum::WalletCreated,
+            title: "Export Multisig Configuration",
+            dialog_line_edit: Vec::from([DialogLineEdit {
+                message: Some(message),
+                line_edit: None,
+            }]),
+            optional: false,
         });
     }
 
-    pub fn render_enter_password_panel(
+    pub fn render_new_multisig_panel(
         &mut self,
         ctx: &egui::Context,
         ui: &mut Ui,
         watch: bool,
         source: Option<CentralPanelState>,
         destination: CentralPanelState,
+        cosigner_keys: Vec<String>,
     ) {
         self.boiler_plate_render(ui, watch, &source);
         ui.vertical_centered(|ui| {
+            ui.add_space(30.0);
+            ui.heading("Cosigners");
+            ui.add_space(10.0);
+            let mut removed_cosigner = None;
+            for (index, cosigner_key) in cosigner_keys.iter().enumerate() {
+                let public_key =
+                    public_cosigner_key(cosigner_key).unwrap_or_else(|_| cosigner_key.to_string());
+                ui.add(egui::Label::new(public_key).wrap(true));
+                if ui.small_button("Remove").clicked() {
+                    removed_cosigner = Some(index);
+                }
+            }
+            if let Some(index) = removed_cosigner {
+                if let CentralPanelState::WalletNewMultisig { cosigner_keys } =
+                    &mut self.central_panel_state
+                {
+                    cosigner_keys.remove(index);
+                }
+            }
             ui.add_space(20.0);
-            let password_entry =
-                egui::TextEdit::singleline(&mut self.string_scratchpad[0]).password(true);
-            ui.heading("Enter Password");
+            ui.label("Cosigner Extended Public Key ([fingerprint/path]xpub)");
+            ui.text_edit_singleline(&mut self.string_scratchpad[1]);
+            let mut new_cosigner_key = None;
+            ui.horizontal(|ui| {
+                if ui.button("Add Cosigner").clicked() {
+                    new_cosigner_key = Some(self.string_scratchpad[1].trim().to_string());
+                }
+                egui::ComboBox::from_label("Add Local Seed")
+                    .selected_text("Choose Wallet")
+                    .show_ui(ui, |ui| {
+                        for wallet in self.wallet_model.json_wallet_data.wallets.iter() {
+                            let Some(priv_key) = &wallet.priv_key else {
+                                continue;
+                            };
+                            if ui.selectable_label(false, &wallet.wallet_name).clicked() {
+                                new_cosigner_key = local_cosigner_key(priv_key).ok();
+                            }
+                        }
+                    });
+            });
+            if let Some(new_cosigner_key) = new_cosigner_key {
+                if let CentralPanelState::WalletNewMultisig { cosigner_keys } =
+                    &mut self.central_panel_state
+                {
+                    if !new_cosigner_key.is_empty() && !cosigner_keys.contains(&new_cosigner_key) {
+                        cosigner_keys.push(new_cosigner_key);
+                    }
+                }
+                self.string_scratchpad[1].clear();
+            }
             ui.add_space(20.0);
-            ui.add(password_entry);
+            ui.label("Required Signatures (M)");
+            ui.text_edit_singleline(&mut self.string_scratchpad[0]);
+            ui.add_space(20.0);
+            ui.label("Wallet Name");
+            ui.text_edit_singleline(&mut self.string_scratchpad[2]);
             ui.add_space(20.0);
 
-            if ui.button("Enter").clicked() {
-                if self
-                    .wallet_model
-                    .validate_password(&self.string_scratchpad[0])
-                {
-                    self.initialise_last_interaction_time();
-                    self.change_state(destination);
-                } else {
-                    self.clear_string_scratchpad();
-                }
+            if ui.button("Confirm").clicked() {
+                let result = match self.string_scratchpad[0].trim().parse::<usize>() {
+                    Ok(threshold) => self
+                        .wallet_model
+                        .add_multisig_wallet(threshold, &cosigner_keys, &self.string_scratchpad[2])
+                        .map_err(|error| error.to_string()),
+                    Err(_) => Err("Required signatures needs to be a number".to_string()),
+                };
+                let (title, message) = match result {
+                    Ok(_) => {
+                        self.change_state(destination);
+                        ("Wallet Created", None)
+                    }
+                    Err(error) => ("Invalid Multisig Wallet", Some(error)),
+                };
+                self.dialog_box = Some(DialogBox {
+                    dialog_box_enum: DialogBoxEnum::WalletCreated,
+                    title: title,
+                    dialog_line_edit: Vec::from([DialogLineEdit {
+                        message: message,
+                        line_edit: None,
+                    }]),
+                    optional: false,
+                })
             }
         });
     }
 
-    pub fn render_settings_panel(
+    pub fn render_bip85_panel(
         &mut self,
         ctx: &egui::Context,
         ui: &mut Ui,
         watch: bool,
         source: Option<CentralPanelState>,
+        destination: CentralPanelState,
+        application: Bip85Application,
+        derived: Option<String>,
     ) {
         self.boiler_plate_render(ui, watch, &source);
-
+        let Some(priv_key) = self.wallet_model.get_active_wallet_data().priv_key else {
+            return;
+        };
         ui.vertical_centered(|ui| {
-            ui.add_space(20.0);
-            ui.heading("Change Settings");
             ui.add_space(30.0);
-            if ui.button("Change Password").clicked() {
-                self.change_state(CentralPanelState::SettingsChangePassword);
+            ui.heading("Derive BIP85 Child Seed");
+            ui.add_space(20.0);
+            let mut selected_application = application;
+            let hex_num_bytes = match application {
+                Bip85Application::Hex { num_bytes } => num_bytes,
+                _ => 32,
+            };
+            egui::ComboBox::from_label("Application")
+                .selected_text(application.name())
+                .show_ui(ui, |ui| {
+                    for option in [
+                        Bip85Application::Mnemonic { word_count: 12 },
+                        Bip85Application::Mnemonic { word_count: 18 },
+                        Bip85Application::Mnemonic { word_count: 24 },
+                        Bip85Application::Wif,
+                        Bip85Application::Hex {
+                            num_bytes: hex_num_bytes,
+                        },
+                    ] {
+                        ui.selectable_value(&mut selected_application, option, option.name());
+                    }
+                });
+            if let Bip85Application::Hex { num_bytes } = &mut selected_application {
+                ui.add(egui::Slider::new(num_bytes, HEX_MIN_BYTES..=HEX_MAX_BYTES).text("Bytes"));
+            }
+            if selected_application != application {
+                if let CentralPanelState::WalletBip85 {
+                    application,
+                    derived,
+                } = &mut self.central_panel_state
+                {
+                    *application = selected_application;
+                    *derived = None;
+                }
             }
             ui.add_space(20.0);
-            ui.heading("Wallet File Path");
-            if ui.button(&self.folder_path).clicked() {
-                self.folder_path =
-                    tinyfiledialogs::select_folder_dialog("Select Folder", &self.folder_path)
-                        .unwrap_or_else(|| self.folder_path.clone());
+            ui.label("Index");
+            ui.text_edit_singleline(&mut self.string_scratchpad[0]);
+            ui.add_space(20.0);
+            if ui.button("Derive").clicked() {
+                let result = match self.string_scratchpad[0].trim().parse::<u32>() {
+                    Ok(index) => derive_bip85(&priv_key, selected_application, index)
+                        .map_err(|error| error.to_string()),
+                    Err(_) => Err("Index needs to be a number".to_string()),
+                };
+                match result {
+                    Ok(new_derived) => {
+                        if let CentralPanelState::WalletBip85 { derived, .. } =
+                            &mut self.central_panel_state
+                        {
+                            *derived = Some(new_derived);
+                        }
+                    }
+                    Err(error) => {
+                        self.dialog_box = Some(DialogBox {
+                            dialog_box_enum: DialogBoxEnum::InvalidTransaction,
+                            title: "Derivation Failed",
+                            dialog_line_edit: Vec::from([DialogLineEdit {
+                                message: Some(error),
+                                line_edit: None,
+                            }]),
+                            optional: false,
+                        })
+                    }
+                }
+            }
+            let Some(derived) = derived else {
+                return;
+            };
+            ui.add_space(30.0);
+            ui.add(egui::Label::new(egui::RichText::new(&derived).strong()).wrap(true));
+            if ui.button("Copy").clicked() {
+                ui.output_mut(|o| o.copied_text = derived.clone());
+            }
+            if let Bip85Application::Mnemonic { .. } = application {
+                ui.add_space(30.0);
+                ui.label("Wallet Name");
+                ui.text_edit_singleline(&mut self.string_scratchpad[1]);
+                if ui.button("Add As New Wallet").clicked() {
+                    let result = self
+                        .wallet_model
+                        .add_bip85_wallet(&derived, &self.string_scratchpad[1]);
+                    let (title, message) = match result {
+                        Ok(_) => {
+                            self.change_state(destination);
+                            ("Wallet Created", None)
+                        }
+                        Err(error) => ("Wallet Not Created", Some(error.to_string())),
+                    };
+                    self.dialog_box = Some(DialogBox {
+                        dialog_box_enum: DialogBoxEnum::WalletCreated,
+                        title: title,
+                        dialog_line_edit: Vec::from([DialogLineEdit {
+                            message: message,
+                            line_edit: None,
+                        }]),
+                        optional: false,
+                    })
+                }
             }
-            ui.horizontal(|ui| {
-                let width = ui.available_width();
-                let mut style: egui::Style = (*ctx.style()).clone();
-                ui.add_space((width - style.spacing.slider_width) / 2.0);
-                ui.add(
-                    egui::Slider::new(&mut self.password_needed_timeout_s, 3..=1200)
-                        .custom_formatter(|n, _| {
-                            let n = n as i32;
-                            let mins = (n / 60) % 60;
-                            let secs = n % 60;
-                            format!("{mins:02} mins {secs:02} secs")
-                        }),
-                );
-            });
-            ui.label("Idle Time before your password is required");
         });
     }
 
-    pub fn render_delete_wallet_panel(
+    pub fn render_new_policy_panel(
         &mut self,
         ctx: &egui::Context,
         ui: &mut Ui,
         watch: bool,
         source: Option<CentralPanelState>,
         destination: CentralPanelState,
-        pub_key: String,
+        policy_keys: Vec<(String, String)>,
+        preview: Option<String>,
     ) {
         self.boiler_plate_render(ui, watch, &source);
-        let (entry_type, wallet) = self.wallet_model.get_wallet_data(&pub_key);
-        let wallet_name = wallet.wallet_name.clone();
         ui.vertical_centered(|ui| {
-            ui.add_space(50.0);
+            ui.add_space(30.0);
+            ui.heading("Keys");
+            ui.add_space(10.0);
+            for (key_name, key) in policy_keys.iter() {
+                let public_key = public_cosigner_key(key).unwrap_or_else(|_| key.to_string());
+                ui.add(egui::Label::new(format!("{}: {}", key_name, public_key)).wrap(true));
+            }
+            ui.add_space(20.0);
+            ui.label("Key Name");
+            ui.text_edit_singleline(&mut self.string_scratchpad[1]);
+            ui.label("Extended Public Key ([fingerprint/path]xpub)");
+            ui.text_edit_singleline(&mut self.string_scratchpad[2]);
+            let mut new_key = None;
+            ui.horizontal(|ui| {
+                if ui.button("Add Key").clicked() {
+                    new_key = Some(self.string_scratchpad[2].trim().to_string());
+                }
+                egui::ComboBox::from_label("Add Local Seed")
+                    .selected_text("Choose Wallet")
+                    .show_ui(ui, |ui| {
+                        for wallet in self.wallet_model.json_wallet_data.wallets.iter() {
+                            let Some(priv_key) = &wallet.priv_key else {
+                                continue;
+                            };
+                            if ui.selectable_label(false, &wallet.wallet_name).clicked() {
+                                new_key = local_cosigner_key(priv_key).ok();
+                            }
+                        }
+                    });
+            });
+            let key_name = self.string_scratchpad[1].trim().to_string();
+            if let (Some(new_key), false) = (new_key, key_name.is_empty()) {
+                if let CentralPanelState::WalletNewPolicy { policy_keys, .. } =
+                    &mut self.central_panel_state
+                {
+                    policy_keys.retain(|(existing_name, _)| *existing_name != key_name);
+                    policy_keys.push((key_name, new_key));
+                }
+                self.string_scratchpad[1].clear();
+                self.string_scratchpad[2].clear();
+            }
+            ui.add_space(20.0);
+            ui.label("Policy, e.g. or(99@pk(me),1@and(pk(spouse),older(52560)))");
+            ui.text_edit_multiline(&mut self.string_scratchpad[0]);
+            ui.add_space(10.0);
+            if ui.button("Compile").clicked() {
+                let compiled = compile_policy(&self.string_scratchpad[0], &policy_keys)
+                    .and_then(|(external, _)| public_descriptor_string(&external));
+                if let CentralPanelState::WalletNewPolicy { preview, .. } =
+                    &mut self.central_panel_state
+                {
+                    *preview = Some(compiled.unwrap_or_else(|error| error.to_string()));
+                }
+            }
+            if let Some(preview) = preview {
+                ui.add_space(10.0);
+                ui.heading("Compiled Descriptor");
+                ui.add(egui::Label::new(preview).wrap(true));
+            }
+            ui.add_space(20.0);
+            ui.label("Wallet Name");
+            ui.text_edit_singleline(&mut self.string_scratchpad[3]);
+            ui.add_space(20.0);
 
-            ui.heading(format!(
-                "Are you sure you want to delete wallet {}?",
-                wallet_name
-            ));
-            ui.strong(&pub_key);
             if ui.button("Confirm").clicked() {
-                match entry_type {
-                    EntryType::Wallet => {
-                        self.wallet_model.delete_wallet(&pub_key);
-                    }
-                    EntryType::Contact => {
-                        self.wallet_model.delete_contact(&pub_key);
+                let result = self.wallet_model.add_policy_wallet(
+                    &self.string_scratchpad[0],
+                    &policy_keys,
+                    &self.string_scratchpad[3],
+                );
+                let (title, message) = match result {
+                    Ok(_) => {
+                        self.change_state(destination);
+                        ("Wallet Created", None)
                     }
-                }
-                self.change_state(destination);
+                    Err(error) => ("Invalid Policy", Some(error.to_string())),
+                };
+                self.dialog_box = Some(DialogBox {
+                    dialog_box_enum: DialogBoxEnum::WalletCreated,
+                    title: title,
+                    dialog_line_edit: Vec::from([DialogLineEdit {
+                        message: message,
+                        line_edit: None,
+                    }]),
+                    optional: false,
+                })
             }
         });
     }
-    pub fn render_rename_wallet_panel(
+
+    pub fn render_multisig_psbt_panel(
         &mut self,
         ctx: &egui::Context,
         ui: &mut Ui,
         watch: bool,
         source: Option<CentralPanelState>,
         destination: CentralPanelState,
-        pub_key: String,
+        psbt: String,
     ) {
         self.boiler_plate_render(ui, watch, &source);
+        let wallet = self.wallet_model.get_active_wallet_data();
+        let multisig_policy = wallet.multisig_policy();
+        let (threshold, _) = multisig_policy.unwrap_or((1, 1));
+        let status = PartiallySignedTransaction::from_str(&psbt)
+            .ok()
+            .map(|parsed_psbt| multisig_status(&parsed_psbt, threshold));
         ui.vertical_centered(|ui| {
-            let (entry_type, wallet) = self.wallet_model.get_wallet_data(&pub_key);
-
-            ui.add_space(50.0);
-            ui.heading("Public Key");
-            ui.add_space(20.0);
-
-            ui.label(wallet.pub_key.clone());
-
-            ui.add_space(20.0);
-            ui.heading("Current Wallet Name");
-
+            ui.add_space(30.0);
+            ui.heading("Partially Signed Transaction");
             ui.add_space(10.0);
-            ui.label(wallet.wallet_name.clone());
+            match &status {
+                Some(status) => {
+                    match (multisig_policy, status.finalized) {
+                        (Some(_), _) => ui.label(format!(
+                            "{} of {} signatures",
+                            status.signatures, status.threshold
+                        )),
+                        (None, true) => ui.label("Ready to broadcast"),
+                        (None, false) => ui.label("Awaiting signatures"),
+                    };
+                    ui.add(egui::Label::new(&psbt).wrap(true));
+                    if ui.button("Copy PSBT").clicked() {
+                        ui.output_mut(|o| o.copied_text = psbt.clone());
+                    }
+                }
+                None => {
+                    ui.label("No PSBT loaded");
+                }
+            }
             ui.add_space(20.0);
-            ui.heading("New Wallet Name");
+            ui.label("Paste a PSBT from a cosigner");
             ui.text_edit_singleline(&mut self.string_scratchpad[0]);
+            ui.add_space(20.0);
 
-            if ui.button("Confirm").clicked() {
-                self.wallet_model
-                    .rename_wallet(entry_type, &pub_key, &self.string_scratchpad[0]);
-                self.change_state(destination);
+            let mut result = None;
+            ui.horizontal(|ui| {
+                if ui.button("Load / Combine").clicked() {
+                    let other_psbt = self.string_scratchpad[0].clone();
+                    result = Some(match status {
+                        Some(_) => self.wallet_model.combine_multisig_psbts(&psbt, &other_psbt),
+                        None => self.wallet_model.sign_multisig_psbt(&other_psbt),
+                    });
+                }
+                if status.is_some() && ui.button("Sign").clicked() {
+                    result = Some(self.wallet_model.sign_multisig_psbt(&psbt));
+                }
+                if let Some(MultisigStatus {
+                    finalized: true, ..
+                }) = status
+                {
+                    if ui.button("Broadcast").clicked() {
+                        match self.wallet_model.broadcast_psbt(&psbt) {
+                            Ok(_) => self.change_state(destination.clone()),
+                            Err(error) => result = Some(Err(error)),
+                        }
+                    }
+                }
+            });
+            match result {
+                Some(Ok(new_psbt)) => {
+                    self.change_state(CentralPanelState::WalletMultisigPsbt { psbt: new_psbt })
+                }
+                Some(Err(error)) => {
+                    self.dialog_box = Some(DialogBox {
+                        dialog_box_enum: DialogBoxEnum::InvalidTransaction,
+                        title: "Invalid PSBT",
+                        dialog_line_edit: Vec::from([DialogLineEdit {
+                            message: Some(error.to_string()),
+                            line_edit: None,
+                        }]),
+                        optional: false,
+                    })
+                }
+                None => {}
             }
         });
     }
 
-    pub fn render_wallet_secret_panel(
+    pub fn render_import_descriptors_panel(
         &mut self,
         ctx: &egui::Context,
         ui: &mut Ui,
         watch: bool,
         source: Option<CentralPanelState>,
+        destination: CentralPanelState,
     ) {
         self.boiler_plate_render(ui, watch, &source);
         ui.vertical_centered(|ui| {
-            let wallet = self.wallet_model.get_active_wallet_data();
-            let mnemonic_string = wallet.mnemonic.unwrap();
-            let priv_key = wallet.priv_key.unwrap();
             ui.add_space(50.0);
-            ui.heading("Mnemonic");
+            ui.heading("External (Receive) Descriptor");
             ui.add_space(20.0);
-
-            ui.strong(&mnemonic_string);
-            if ui.button("Copy Mnemonic").clicked() {
-                ui.output_mut(|o| o.copied_text = mnemonic_string);
-            }
+            ui.text_edit_singleline(&mut self.string_scratchpad[0]);
+            ui.add_space(30.0);
+            ui.heading("Internal (Change) Descriptor");
             ui.add_space(20.0);
-            ui.heading("Private Key");
+            ui.text_edit_singleline(&mut self.string_scratchpad[1]);
+            ui.add_space(30.0);
+            ui.heading("Wallet Name");
             ui.add_space(20.0);
-            ui.strong(&priv_key);
-            if ui.button("Copy Private Key").clicked() {
-                ui.output_mut(|o| o.copied_text = priv_key);
+            ui.text_edit_singleline(&mut self.string_scratchpad[2]);
+
+            if ui.button("Confirm").clicked() {
+                let result = self.wallet_model.add_descriptor_wallet(
+                    &self.string_scratchpad[0],
+                    &self.string_scratchpad[1],
+                    &self.string_scratchpad[2],
+                );
+                let (title, message) = match result {
+                    Ok(_) => {
+                        self.change_state(destination);
+                        ("Wallet Added", None)
+                    }
+                    Err(error) => ("Invalid Descriptors", Some(error.to_string())),
+                };
+
+                self.dialog_box = Some(DialogBox {
+                    dialog_box_enum: DialogBoxEnum::WalletCreated,
+                    title: title,
+                    dialog_line_edit: Vec::from([DialogLineEdit {
+                        message: message,
+                        line_edit: None,
+                    }]),
+                    optional: false,
+                })
             }
         });
     }
@@ -813,6 +2833,20 @@ impl MyApp {
             CentralPanelState::WalletMain => self.render_wallet_main_panel(ctx, ui, true, None),
             CentralPanelState::SendingMain => self.render_sending_panel(ctx, ui, true, None),
             CentralPanelState::ReceivingMain => self.render_receiving_panel(ctx, ui, true, None),
+            CentralPanelState::ReceivingPaymentRequests => self.render_payment_requests_panel(
+                ctx,
+                ui,
+                true,
+                Some(CentralPanelState::ReceivingMain),
+            ),
+            CentralPanelState::ReceivingSweep { sweep_transaction } => self.render_sweep_panel(
+                ctx,
+                ui,
+                true,
+                Some(CentralPanelState::ReceivingMain),
+                CentralPanelState::ReceivingMain,
+                sweep_transaction.clone(),
+            ),
             CentralPanelState::ContactsMain => self.render_contacts_panel(ctx, ui, true, None),
             CentralPanelState::SettingsMain => self.render_settings_panel(ctx, ui, true, None),
             CentralPanelState::WalletDelete => self.render_delete_wallet_panel(
@@ -852,6 +2886,20 @@ impl MyApp {
                     CentralPanelState::WalletMain,
                     &mnemonic_string.clone(),
                 ),
+            CentralPanelState::WalletBackupQuiz {
+                mnemonic_string,
+                word_indices,
+                source,
+                destination,
+            } => self.render_backup_quiz_panel(
+                ctx,
+                ui,
+                **destination != CentralPanelState::WalletNotInitialised,
+                Some(*source.clone()),
+                *destination.clone(),
+                &mnemonic_string.clone(),
+                &word_indices.clone(),
+            ),
             CentralPanelState::WalletExistingWallet => self.render_new_wallet_existing(
                 ctx,
                 ui,
@@ -859,6 +2907,90 @@ impl MyApp {
                 Some(CentralPanelState::WalletMain),
                 CentralPanelState::WalletMain,
             ),
+            CentralPanelState::WalletDescriptors => self.render_wallet_descriptors_panel(
+                ctx,
+                ui,
+                true,
+                Some(CentralPanelState::WalletMain),
+            ),
+            CentralPanelState::WalletCostBasis { method, report } => self.render_cost_basis_panel(
+                ctx,
+                ui,
+                true,
+                Some(CentralPanelState::WalletMain),
+                *method,
+                report.clone(),
+            ),
+            CentralPanelState::WalletExportHistory {
+                format,
+                all_wallets,
+            } => self.render_export_history_panel(
+                ctx,
+                ui,
+                true,
+                Some(CentralPanelState::WalletMain),
+                *format,
+                *all_wallets,
+            ),
+            CentralPanelState::WalletTransactionDetail { detail } => self
+                .render_transaction_detail_panel(
+                    ctx,
+                    ui,
+                    true,
+                    Some(CentralPanelState::WalletMain),
+                    detail.clone(),
+                ),
+            CentralPanelState::WalletUtxos => {
+                self.render_utxos_panel(ctx, ui, true, Some(CentralPanelState::WalletMain))
+            }
+            CentralPanelState::WalletImportDescriptors => self.render_import_descriptors_panel(
+                ctx,
+                ui,
+                true,
+                Some(CentralPanelState::WalletMain),
+                CentralPanelState::WalletMain,
+            ),
+            CentralPanelState::WalletNewMultisig { cosigner_keys } => self
+                .render_new_multisig_panel(
+                    ctx,
+                    ui,
+                    true,
+                    Some(CentralPanelState::WalletMain),
+                    CentralPanelState::WalletMain,
+                    cosigner_keys.clone(),
+                ),
+            CentralPanelState::WalletMultisigPsbt { psbt } => self.render_multisig_psbt_panel(
+                ctx,
+                ui,
+                true,
+                Some(CentralPanelState::WalletMain),
+                CentralPanelState::WalletMain,
+                psbt.clone(),
+            ),
+            CentralPanelState::WalletBip85 {
+                application,
+                derived,
+            } => self.render_bip85_panel(
+                ctx,
+                ui,
+                true,
+                Some(CentralPanelState::WalletMain),
+                CentralPanelState::WalletMain,
+                *application,
+                derived.clone(),
+            ),
+            CentralPanelState::WalletNewPolicy {
+                policy_keys,
+                preview,
+            } => self.render_new_policy_panel(
+                ctx,
+                ui,
+                true,
+                Some(CentralPanelState::WalletMain),
+                CentralPanelState::WalletMain,
+                policy_keys.clone(),
+                preview.clone(),
+            ),
             CentralPanelState::ContactsNewContact => self.render_new_contact(
                 ctx,
                 ui,
@@ -900,6 +3032,14 @@ impl MyApp {
             return;
         } else {
             self.wallet_model.initialise_from_wallet_file().unwrap();
+            self.backend_config = self.wallet_model.json_wallet_data.backend.clone();
+            self.display_unit = self.wallet_model.json_wallet_data.display_unit;
+            self.number_format = self.wallet_model.json_wallet_data.number_format;
+            self.fiat_valuation.currency = self.wallet_model.json_wallet_data.fiat_currency;
+            self.sync_scheduler.interval =
+                std::time::Duration::from_secs(self.wallet_model.json_wallet_data.sync_interval_s);
+            self.final_confirmations = self.wallet_model.json_wallet_data.final_confirmations;
+            self.load_price_source(self.wallet_model.json_wallet_data.price_source.clone());
             if self.wallet_model.json_wallet_data.wallets.is_empty() {
                 self.central_panel_state = CentralPanelState::NoWalletsInWalletFile {
                     mnemonic_string: generate_mnemonic_string().unwrap(),
@@ -914,7 +3054,7 @@ impl MyApp {
     }
 
     pub fn clear_string_scratchpad(&mut self) {
-        self.string_scratchpad = [String::new(), String::new(), String::new()];
+        self.string_scratchpad = [String::new(), String::new(), String::new(), String::new()];
     }
     pub fn initialise_last_interaction_time(&mut self) {
         self.last_interaction_time = chrono::offset::Local::now();
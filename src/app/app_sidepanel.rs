@@ -88,11 +88,14 @@ impl MyApp {
     }
 
     pub fn side_panel_app_initialising(&mut self) -> bool {
-        match self.central_panel_state {
+        match &self.central_panel_state {
             CentralPanelState::WalletFileNotAvailable
             | CentralPanelState::NoWalletsInWalletFile { mnemonic_string: _ }
             | CentralPanelState::WalletNotInitialised
             | CentralPanelState::PasswordNeeded { .. } => true,
+            CentralPanelState::WalletBackupQuiz { destination, .. } => {
+                **destination == CentralPanelState::WalletNotInitialised
+            }
             _ => false,
         }
     }
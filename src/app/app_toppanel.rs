@@ -1,8 +1,10 @@
+use crate::sync_scheduler::ConnectionStatus;
+
 use super::{MyApp, SidePanel};
 impl MyApp {
     pub fn render_toppanel(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         egui::TopBottomPanel::top("Headerbar")
-            .exact_height(70.0)
+            .exact_height(90.0)
             .show(ctx, |ui| {
                 if self.dialog_box.is_some() {
                     ui.set_enabled(false);
@@ -21,6 +23,25 @@ impl MyApp {
                         SidePanel::Settings => "Settings",
                     };
                     ui.heading(title);
+
+                    let connection_status = self.connection_status();
+                    let colour = match connection_status {
+                        ConnectionStatus::NotSynced => egui::Color32::GRAY,
+                        ConnectionStatus::Connected => egui::Color32::GREEN,
+                        ConnectionStatus::Syncing => egui::Color32::YELLOW,
+                        ConnectionStatus::Offline => egui::Color32::GRAY,
+                        ConnectionStatus::Error(_) => egui::Color32::RED,
+                    };
+                    let badge = ui.colored_label(colour, connection_status.name());
+                    match &connection_status {
+                        ConnectionStatus::Error(error) => {
+                            badge.on_hover_text(error);
+                        }
+                        ConnectionStatus::Offline => {
+                            badge.on_hover_text("Showing cached balances and history");
+                        }
+                        _ => {}
+                    }
                 })
             });
     }
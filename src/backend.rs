@@ -0,0 +1,484 @@
+use bdk::bitcoin::{Network, Transaction};
+use bdk::bitcoincore_rpc::{self, RpcApi};
+use bdk::blockchain::compact_filters::{
+    CompactFiltersBlockchain, CompactFiltersError, Mempool, Peer,
+};
+use bdk::blockchain::esplora::{EsploraBlockchainConfig, EsploraError};
+use bdk::blockchain::rpc::{wallet_name_from_descriptor, Auth, RpcBlockchain, RpcConfig};
+use bdk::blockchain::{
+    Blockchain, ConfigurableBlockchain, ElectrumBlockchain, EsploraBlockchain, GetHeight, Progress,
+};
+use bdk::database::MemoryDatabase;
+use bdk::electrum_client::{Client, ConfigBuilder};
+use bdk::wallet::Wallet;
+use bdk::{FeeRate, KeychainKind, SyncOptions};
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::proxy::{is_local, ProxyConfig};
+use crate::wallet_file_manager::get_wallet_path;
+
+pub const DEFAULT_ELECTRUM_URL: &str = "ssl://electrum.blockstream.info:60002";
+pub const DEFAULT_ESPLORA_URL: &str = "https://blockstream.info/testnet/api";
+pub const DEFAULT_RPC_URL: &str = "http://127.0.0.1:18332";
+pub const DEFAULT_COMPACT_FILTERS_PEERS: &str = "127.0.0.1:18333";
+const COMPACT_FILTERS_DIRECTORY: &str = "compact_filters";
+const ESPLORA_STOP_GAP: usize = 20;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum BackendKind {
+    Electrum,
+    Esplora,
+    BitcoinCore,
+    CompactFilters,
+}
+
+pub const BACKEND_KINDS: [BackendKind; 4] = [
+    BackendKind::Electrum,
+    BackendKind::Esplora,
+    BackendKind::BitcoinCore,
+    BackendKind::CompactFilters,
+];
+
+// Latest progress (0-100) and message reported by each wallet's running sync
+pub type SyncProgress = Arc<Mutex<HashMap<String, (f32, Option<String>)>>>;
+
+pub struct ProgressReporter {
+    pub pub_key: String,
+    pub sync_progress: SyncProgress,
+}
+
+impl Progress for ProgressReporter {
+    fn update(&self, progress: f32, message: Option<String>) -> Result<(), bdk::Error> {
+        self.sync_progress
+            .lock()
+            .unwrap()
+            .insert(self.pub_key.clone(), (progress, message));
+        return Ok(());
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum RpcAuth {
+    Cookie { file: String },
+    UserPass { username: String, password: String },
+}
+
+impl Default for RpcAuth {
+    fn default() -> Self {
+        RpcAuth::Cookie {
+            file: String::new(),
+        }
+    }
+}
+
+impl RpcAuth {
+    fn to_bdk_auth(&self) -> Auth {
+        match self {
+            RpcAuth::Cookie { file } => Auth::Cookie {
+                file: PathBuf::from(file),
+            },
+            RpcAuth::UserPass { username, password } => Auth::UserPass {
+                username: username.clone(),
+                password: password.clone(),
+            },
+        }
+    }
+
+    fn to_rpc_auth(&self) -> bitcoincore_rpc::Auth {
+        match self {
+            RpcAuth::Cookie { file } => bitcoincore_rpc::Auth::CookieFile(PathBuf::from(file)),
+            RpcAuth::UserPass { username, password } => {
+                bitcoincore_rpc::Auth::UserPass(username.clone(), password.clone())
+            }
+        }
+    }
+}
+
+fn default_network() -> Network {
+    return Network::Testnet;
+}
+
+impl BackendKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BackendKind::Electrum => "Electrum",
+            BackendKind::Esplora => "Esplora",
+            BackendKind::BitcoinCore => "Bitcoin Core RPC",
+            BackendKind::CompactFilters => "Compact Block Filters",
+        }
+    }
+
+    pub fn default_url(&self) -> &'static str {
+        match self {
+            BackendKind::Electrum => DEFAULT_ELECTRUM_URL,
+            BackendKind::Esplora => DEFAULT_ESPLORA_URL,
+            BackendKind::BitcoinCore => DEFAULT_RPC_URL,
+            BackendKind::CompactFilters => DEFAULT_COMPACT_FILTERS_PEERS,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BackendConfig {
+    pub kind: BackendKind,
+    pub url: String,
+    #[serde(default)]
+    pub rpc_auth: RpcAuth,
+    #[serde(default = "default_network")]
+    pub network: Network,
+    #[serde(default)]
+    pub proxy: ProxyConfig,
+}
+
+impl Default for BackendConfig {
+    fn default() -> Self {
+        BackendConfig {
+            kind: BackendKind::Electrum,
+            url: DEFAULT_ELECTRUM_URL.to_string(),
+            rpc_auth: RpcAuth::default(),
+            network: default_network(),
+            proxy: ProxyConfig::default(),
+        }
+    }
+}
+
+// Everything the app needs from a chain source, so callers never name a concrete backend
+pub trait WalletBackend: Send + Sync {
+    fn sync(
+        &self,
+        wallet: &Wallet<MemoryDatabase>,
+        progress: Option<Box<dyn Progress>>,
+    ) -> Result<(), bdk::Error>;
+    fn broadcast(&self, transaction: &Transaction) -> Result<(), bdk::Error>;
+    fn estimate_fee(&self, target: usize) -> Result<FeeRate, bdk::Error>;
+    fn get_height(&self) -> Result<u32, bdk::Error>;
+}
+
+struct BdkBackend<B> {
+    blockchain: B,
+}
+
+impl<B> WalletBackend for BdkBackend<B>
+where
+    B: Blockchain + GetHeight + Send + Sync,
+{
+    fn sync(
+        &self,
+        wallet: &Wallet<MemoryDatabase>,
+        progress: Option<Box<dyn Progress>>,
+    ) -> Result<(), bdk::Error> {
+        return wallet.sync(&self.blockchain, SyncOptions { progress });
+    }
+
+    fn broadcast(&self, transaction: &Transaction) -> Result<(), bdk::Error> {
+        return Blockchain::broadcast(&self.blockchain, transaction);
+    }
+
+    fn estimate_fee(&self, target: usize) -> Result<FeeRate, bdk::Error> {
+        return Blockchain::estimate_fee(&self.blockchain, target);
+    }
+
+    fn get_height(&self) -> Result<u32, bdk::Error> {
+        return GetHeight::get_height(&self.blockchain);
+    }
+}
+
+// Bitcoin Core tracks each wallet's descriptors in a watch-only wallet of its own,
+// so syncing opens that wallet while chain queries go through a wallet-less client
+struct RpcBackend {
+    config: BackendConfig,
+    client: bitcoincore_rpc::Client,
+}
+
+impl WalletBackend for RpcBackend {
+    fn sync(
+        &self,
+        wallet: &Wallet<MemoryDatabase>,
+        progress: Option<Box<dyn Progress>>,
+    ) -> Result<(), bdk::Error> {
+        let Some(external_descriptor) = wallet.public_descriptor(KeychainKind::External)? else {
+            return Err(bdk::Error::Generic("Wallet has no descriptor".to_string()));
+        };
+        let internal_descriptor = wallet.public_descriptor(KeychainKind::Internal)?;
+        let wallet_name = wallet_name_from_descriptor(
+            external_descriptor,
+            internal_descriptor,
+            self.config.network,
+            wallet.secp_ctx(),
+        )?;
+        let blockchain = RpcBlockchain::from_config(&RpcConfig {
+            url: self.config.url.clone(),
+            auth: self.config.rpc_auth.to_bdk_auth(),
+            network: self.config.network,
+            wallet_name,
+            sync_params: None,
+        })?;
+        return wallet.sync(&blockchain, SyncOptions { progress });
+    }
+
+    fn broadcast(&self, transaction: &Transaction) -> Result<(), bdk::Error> {
+        self.client.send_raw_transaction(transaction)?;
+        return Ok(());
+    }
+
+    fn estimate_fee(&self, target: usize) -> Result<FeeRate, bdk::Error> {
+        let estimate = self.client.estimate_smart_fee(target as u16, None)?;
+        let Some(fee_rate) = estimate.fee_rate else {
+            return Err(bdk::Error::Generic(
+                "Bitcoin Core has no fee estimate yet".to_string(),
+            ));
+        };
+        return Ok(FeeRate::from_btc_per_kvb(fee_rate.to_btc() as f32));
+    }
+
+    fn get_height(&self) -> Result<u32, bdk::Error> {
+        return Ok(self.client.get_block_count()? as u32);
+    }
+}
+
+fn check_proxy(proxy: &ProxyConfig, url: &str) -> Result<(), bdk::Error> {
+    return proxy
+        .check_connection(url)
+        .map_err(|error| bdk::Error::Generic(error.to_string()));
+}
+
+pub fn connect_backend(config: &BackendConfig) -> Result<Arc<dyn WalletBackend>, bdk::Error> {
+    match config.kind {
+        BackendKind::Electrum => {
+            check_proxy(&config.proxy, &config.url)?;
+            let electrum_config = ConfigBuilder::new()
+                .socks5(config.proxy.electrum_socks5())
+                .build();
+            let client = Client::from_config(&config.url, electrum_config)?;
+            return Ok(Arc::new(BdkBackend {
+                blockchain: ElectrumBlockchain::from(client),
+            }));
+        }
+        BackendKind::Esplora => {
+            check_proxy(&config.proxy, &config.url)?;
+            let esplora_config = EsploraBlockchainConfig {
+                proxy: config.proxy.socks5_url(),
+                ..EsploraBlockchainConfig::new(config.url.clone(), ESPLORA_STOP_GAP)
+            };
+            return Ok(Arc::new(BdkBackend {
+                blockchain: EsploraBlockchain::from_config(&esplora_config)?,
+            }));
+        }
+        BackendKind::BitcoinCore => {
+            // The RPC client has no SOCKS support, so only a local node can be used with a proxy
+            if config.proxy.enabled && !is_local(&config.url) {
+                return Err(bdk::Error::Generic(
+                    "Bitcoin Core RPC can't be used through a proxy, only a local node".to_string(),
+                ));
+            }
+            check_proxy(&config.proxy, &config.url)?;
+            let client = bitcoincore_rpc::Client::new(&config.url, config.rpc_auth.to_rpc_auth())?;
+            let chain = client.get_blockchain_info()?.chain;
+            if chain != config.network {
+                return Err(bdk::Error::InvalidNetwork {
+                    requested: config.network,
+                    found: chain,
+                });
+            }
+            return Ok(Arc::new(RpcBackend {
+                config: config.clone(),
+                client,
+            }));
+        }
+        BackendKind::CompactFilters => {
+            // Filters and headers are kept on disk so later syncs only fetch new blocks
+            let mempool = Arc::new(Mempool::default());
+            let mut peers = Vec::new();
+            for address in config.url.split(',').map(str::trim) {
+                if address.is_empty() {
+                    continue;
+                }
+                check_proxy(&config.proxy, address)?;
+                let peer = if config.proxy.enabled {
+                    Peer::connect_proxy(
+                        address,
+                        config.proxy.address.trim(),
+                        config.proxy.credentials(),
+                        Arc::clone(&mempool),
+                        config.network,
+                    )?
+                } else {
+                    Peer::connect(address, Arc::clone(&mempool), config.network)?
+                };
+                peers.push(peer);
+            }
+            if peers.is_empty() {
+                return Err(bdk::Error::Generic("No peers configured".to_string()));
+            }
+            let Some(storage_directory) = get_wallet_path(COMPACT_FILTERS_DIRECTORY) else {
+                return Err(bdk::Error::Generic(
+                    "Documents directory not found".to_string(),
+                ));
+            };
+            return Ok(Arc::new(BdkBackend {
+                blockchain: CompactFiltersBlockchain::new(
+                    peers,
+                    storage_directory.join(config.network.to_string()),
+                    None,
+                )?,
+            }));
+        }
+    }
+}
+
+pub fn is_offline_error(error: &bdk::Error) -> bool {
+    match error {
+        bdk::Error::Electrum(error) => matches!(
+            error,
+            bdk::electrum_client::Error::IOError(_)
+                | bdk::electrum_client::Error::SharedIOError(_)
+                | bdk::electrum_client::Error::AllAttemptsErrored(_)
+        ),
+        bdk::Error::Esplora(error) => matches!(**error, EsploraError::UreqTransport(_)),
+        bdk::Error::Rpc(bitcoincore_rpc::Error::JsonRpc(
+            bitcoincore_rpc::jsonrpc::Error::Transport(_),
+        )) => true,
+        bdk::Error::CompactFilters(CompactFiltersError::Io(_)) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bdk::bitcoin::Network;
+    use bdk::database::MemoryDatabase;
+    use bdk::wallet::{AddressIndex, Wallet};
+    use electrsd::bitcoind::bitcoincore_rpc::RpcApi;
+    use electrsd::bitcoind::{BitcoinD, Conf, P2P};
+
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use crate::backend::{
+        connect_backend, BackendConfig, BackendKind, ProgressReporter, RpcAuth,
+        DEFAULT_ELECTRUM_URL,
+    };
+    use crate::proxy::ProxyConfig;
+
+    #[test]
+    fn test_backend_config() {
+        let config = BackendConfig::default();
+        assert_eq!(config.kind, BackendKind::Electrum);
+        assert_eq!(config.url, DEFAULT_ELECTRUM_URL);
+
+        let json = r#"{"kind": "Esplora", "url": "http://localhost:3002"}"#;
+        let config: BackendConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.kind, BackendKind::Esplora);
+        assert_eq!(
+            BackendKind::Esplora.default_url(),
+            "https://blockstream.info/testnet/api"
+        );
+        assert!(!config.proxy.enabled);
+
+        // Proxy checks fail before any connection is attempted
+        let refused = BackendConfig {
+            proxy: ProxyConfig {
+                require_proxy: true,
+                ..ProxyConfig::default()
+            },
+            ..BackendConfig::default()
+        };
+        assert!(connect_backend(&refused).is_err());
+        let remote_rpc = BackendConfig {
+            kind: BackendKind::BitcoinCore,
+            url: "http://192.0.2.1:18332".to_string(),
+            proxy: ProxyConfig {
+                enabled: true,
+                ..ProxyConfig::default()
+            },
+            ..BackendConfig::default()
+        };
+        assert!(connect_backend(&remote_rpc).is_err());
+    }
+
+    #[test]
+    fn test_bitcoin_core_regtest() {
+        let bitcoind = BitcoinD::new(electrsd::bitcoind::exe_path().unwrap()).unwrap();
+        let config = BackendConfig {
+            kind: BackendKind::BitcoinCore,
+            url: bitcoind.rpc_url(),
+            rpc_auth: RpcAuth::Cookie {
+                file: bitcoind.params.cookie_file.to_string_lossy().to_string(),
+            },
+            network: Network::Regtest,
+            ..BackendConfig::default()
+        };
+        let backend = connect_backend(&config).unwrap();
+        let height = backend.get_height().unwrap();
+
+        let wallet = Wallet::new(
+            "wpkh(tpubD6NzVbkrYhZ4XgiXtGrdW5XDAPFCL9h7we1vwNCpn8tGbBcgfVYjXyhWo4E1xkh56hjod1RhGjxbaTLV3X4FyWuejifB9jusQ46QzG87VKp/0/*)",
+            None,
+            Network::Regtest,
+            MemoryDatabase::new(),
+        )
+        .unwrap();
+        wallet.get_address(AddressIndex::New).unwrap();
+        backend.sync(&wallet, None).unwrap();
+        assert!(backend.get_height().unwrap() >= height);
+
+        let wrong_network = BackendConfig {
+            network: Network::Testnet,
+            ..config
+        };
+        assert!(connect_backend(&wrong_network).is_err());
+    }
+
+    #[test]
+    fn test_compact_filters_regtest() {
+        let mut conf = Conf::default();
+        conf.args.push("-blockfilterindex=1");
+        conf.args.push("-peerblockfilters=1");
+        conf.p2p = P2P::Yes;
+        let bitcoind = BitcoinD::with_conf(electrsd::bitcoind::exe_path().unwrap(), &conf).unwrap();
+        let address = bitcoind
+            .client
+            .get_new_address(None, None)
+            .unwrap()
+            .assume_checked();
+        bitcoind.client.generate_to_address(10, &address).unwrap();
+        // Peers are only served filters once the background index has caught up
+        for _ in 0..100 {
+            let index_info: serde_json::Value = bitcoind.client.call("getindexinfo", &[]).unwrap();
+            if index_info["basic block filter index"]["synced"] == true {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+
+        let config = BackendConfig {
+            kind: BackendKind::CompactFilters,
+            url: bitcoind.params.p2p_socket.unwrap().to_string(),
+            rpc_auth: RpcAuth::default(),
+            network: Network::Regtest,
+            ..BackendConfig::default()
+        };
+        let backend = connect_backend(&config).unwrap();
+
+        let wallet = Wallet::new(
+            "wpkh(tpubD6NzVbkrYhZ4XgiXtGrdW5XDAPFCL9h7we1vwNCpn8tGbBcgfVYjXyhWo4E1xkh56hjod1RhGjxbaTLV3X4FyWuejifB9jusQ46QzG87VKp/0/*)",
+            None,
+            Network::Regtest,
+            MemoryDatabase::new(),
+        )
+        .unwrap();
+        wallet.get_address(AddressIndex::New).unwrap();
+        let sync_progress = Arc::new(Mutex::new(HashMap::new()));
+        let progress = ProgressReporter {
+            pub_key: "regtest".to_string(),
+            sync_progress: Arc::clone(&sync_progress),
+        };
+        backend.sync(&wallet, Some(Box::new(progress))).unwrap();
+        assert!(sync_progress.lock().unwrap().contains_key("regtest"));
+        assert!(backend.get_height().unwrap() > 0);
+    }
+}
@@ -0,0 +1,154 @@
+use bdk::bitcoin::{Address, Network};
+
+use std::str::FromStr;
+
+use crate::units::{format_decimal_amount, parse_decimal_amount};
+
+const BIP21_SCHEME: &str = "bitcoin:";
+const BTC_DECIMALS: usize = 8;
+
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct PaymentUri {
+    pub address: String,
+    pub amount: Option<u64>,
+    pub label: Option<String>,
+    pub message: Option<String>,
+}
+
+impl PaymentUri {
+    pub fn to_uri(&self) -> String {
+        let mut params = Vec::new();
+        if let Some(amount) = self.amount {
+            params.push(format!(
+                "amount={}",
+                format_decimal_amount(amount, BTC_DECIMALS, '.')
+            ));
+        }
+        if let Some(label) = &self.label {
+            params.push(format!("label={}", percent_encode(label)));
+        }
+        if let Some(message) = &self.message {
+            params.push(format!("message={}", percent_encode(message)));
+        }
+        if params.is_empty() {
+            return format!("{}{}", BIP21_SCHEME, self.address);
+        }
+        return format!("{}{}?{}", BIP21_SCHEME, self.address, params.join("&"));
+    }
+}
+
+pub fn is_payment_uri(uri: &str) -> bool {
+    let uri = uri.trim();
+    return uri
+        .get(..BIP21_SCHEME.len())
+        .map_or(false, |scheme| scheme.eq_ignore_ascii_case(BIP21_SCHEME));
+}
+
+pub fn parse_payment_uri(uri: &str) -> Result<PaymentUri, anyhow::Error> {
+    let uri = uri.trim();
+    if !is_payment_uri(uri) {
+        anyhow::bail!("Payment URIs must start with {}", BIP21_SCHEME);
+    }
+    let (address, query) = uri[BIP21_SCHEME.len()..]
+        .split_once('?')
+        .unwrap_or((&uri[BIP21_SCHEME.len()..], ""));
+    Address::from_str(address)?.require_network(Network::Testnet)?;
+
+    let mut payment_uri = PaymentUri {
+        address: address.to_string(),
+        ..Default::default()
+    };
+    for param in query.split('&').filter(|param| !param.is_empty()) {
+        let (key, value) = param.split_once('=').unwrap_or((param, ""));
+        let value = percent_decode(value)?;
+        match key.to_lowercase().as_str() {
+            "amount" => payment_uri.amount = Some(parse_decimal_amount(&value, BTC_DECIMALS, '.')?),
+            "label" => payment_uri.label = Some(value),
+            "message" => payment_uri.message = Some(value),
+            key if key.starts_with("req-") => {
+                anyhow::bail!("Unsupported required parameter {}", key)
+            }
+            _ => {}
+        }
+    }
+    return Ok(payment_uri);
+}
+
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    return encoded;
+}
+
+fn percent_decode(value: &str) -> Result<String, anyhow::Error> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::new();
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'%' => {
+                let Some(hex) = value.get(index + 1..index + 3) else {
+                    anyhow::bail!("Invalid percent encoding in {}", value);
+                };
+                decoded.push(u8::from_str_radix(hex, 16)?);
+                index += 3;
+            }
+            b'+' => {
+                decoded.push(b' ');
+                index += 1;
+            }
+            byte => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    return Ok(String::from_utf8(decoded)?);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bip21::{is_payment_uri, parse_payment_uri, PaymentUri};
+
+    const ADDRESS: &str = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+
+    #[test]
+    fn test_payment_uri() {
+        let payment_uri = parse_payment_uri(&format!(
+            "bitcoin:{}?amount=0.0005&label=Luke%20Jr&message=Donation+for%20project&foo=bar",
+            ADDRESS
+        ))
+        .unwrap();
+        assert_eq!(payment_uri.amount, Some(50_000));
+        assert_eq!(payment_uri.label.as_deref(), Some("Luke Jr"));
+        assert_eq!(payment_uri.message.as_deref(), Some("Donation for project"));
+
+        let round_trip = parse_payment_uri(&payment_uri.to_uri()).unwrap();
+        assert_eq!(round_trip, payment_uri);
+        assert_eq!(
+            PaymentUri {
+                address: ADDRESS.to_string(),
+                amount: Some(100_000_000),
+                ..Default::default()
+            }
+            .to_uri(),
+            format!("bitcoin:{}?amount=1", ADDRESS)
+        );
+
+        assert!(parse_payment_uri(&format!(
+            "bitcoin:{}?req-somethingyoudontunderstand=50",
+            ADDRESS
+        ))
+        .is_err());
+        assert!(parse_payment_uri(&format!("bitcoin:{}?amount=0.000000001", ADDRESS)).is_err());
+        assert!(parse_payment_uri("bitcoin:notanaddress").is_err());
+        assert!(is_payment_uri("BITCOIN:") && !is_payment_uri("bitcoiné:"));
+    }
+}
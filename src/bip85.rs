@@ -0,0 +1,116 @@
+use bdk::bitcoin::bip32::{DerivationPath, ExtendedPrivKey};
+use bdk::bitcoin::hashes::{sha512, Hash, HashEngine, Hmac, HmacEngine};
+use bdk::bitcoin::secp256k1::{Secp256k1, SecretKey};
+use bdk::bitcoin::PrivateKey;
+use bdk::keys::bip39::Mnemonic;
+
+use std::str::FromStr;
+
+const BIP85_HMAC_KEY: &[u8] = b"bip-entropy-from-k";
+const BIP85_PURPOSE: u32 = 83696968;
+const BIP39_APPLICATION: u32 = 39;
+const BIP39_ENGLISH: u32 = 0;
+const WIF_APPLICATION: u32 = 2;
+const HEX_APPLICATION: u32 = 128169;
+pub const HEX_MIN_BYTES: u32 = 16;
+pub const HEX_MAX_BYTES: u32 = 64;
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum Bip85Application {
+    Mnemonic { word_count: u32 },
+    Wif,
+    Hex { num_bytes: u32 },
+}
+
+impl Bip85Application {
+    pub fn name(&self) -> String {
+        match self {
+            Bip85Application::Mnemonic { word_count } => format!("{} Word Mnemonic", word_count),
+            Bip85Application::Wif => "WIF Private Key".to_string(),
+            Bip85Application::Hex { num_bytes } => format!("{} Byte Hex Entropy", num_bytes),
+        }
+    }
+}
+
+fn derive_entropy(xpriv: &ExtendedPrivKey, path: &str) -> Result<[u8; 64], anyhow::Error> {
+    let secp = Secp256k1::new();
+    let derivation_path = DerivationPath::from_str(path)?;
+    let child = xpriv.derive_priv(&secp, &derivation_path)?;
+    let mut engine = HmacEngine::<sha512::Hash>::new(BIP85_HMAC_KEY);
+    engine.input(&child.private_key.secret_bytes());
+    return Ok(Hmac::<sha512::Hash>::from_engine(engine).to_byte_array());
+}
+
+pub fn derive_bip85(
+    priv_key: &str,
+    application: Bip85Application,
+    index: u32,
+) -> Result<String, anyhow::Error> {
+    let xpriv = ExtendedPrivKey::from_str(priv_key)?;
+    match application {
+        Bip85Application::Mnemonic { word_count } => {
+            let entropy_length = match word_count {
+                12 => 16,
+                18 => 24,
+                24 => 32,
+                _ => anyhow::bail!("Mnemonics must be 12, 18 or 24 words"),
+            };
+            let path = format!(
+                "m/{}'/{}'/{}'/{}'/{}'",
+                BIP85_PURPOSE, BIP39_APPLICATION, BIP39_ENGLISH, word_count, index
+            );
+            let entropy = derive_entropy(&xpriv, &path)?;
+            return Ok(Mnemonic::from_entropy(&entropy[..entropy_length])?.to_string());
+        }
+        Bip85Application::Wif => {
+            let path = format!("m/{}'/{}'/{}'", BIP85_PURPOSE, WIF_APPLICATION, index);
+            let entropy = derive_entropy(&xpriv, &path)?;
+            let secret_key = SecretKey::from_slice(&entropy[..32])?;
+            return Ok(PrivateKey::new(secret_key, xpriv.network).to_wif());
+        }
+        Bip85Application::Hex { num_bytes } => {
+            if num_bytes < HEX_MIN_BYTES || num_bytes > HEX_MAX_BYTES {
+                anyhow::bail!(
+                    "Hex entropy must be between {} and {} bytes",
+                    HEX_MIN_BYTES,
+                    HEX_MAX_BYTES
+                );
+            }
+            let path = format!(
+                "m/{}'/{}'/{}'/{}'",
+                BIP85_PURPOSE, HEX_APPLICATION, num_bytes, index
+            );
+            let entropy = derive_entropy(&xpriv, &path)?;
+            return Ok(hex::encode(&entropy[..num_bytes as usize]));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bip85::{derive_bip85, Bip85Application};
+
+    // Test vectors from BIP85
+    const MASTER_KEY: &str = "xprv9s21ZrQH143K2LBWUUQRFXhucrQqBpKdRRxNVq2zBqsx8HVqFk2uYo8kmbaLLHRdqtQpUm98uKfu3vCVeinJWoNwS6JR26nqVPUsBf5UGaJ";
+
+    #[test]
+    fn test_bip85_vectors() {
+        assert_eq!(
+            derive_bip85(MASTER_KEY, Bip85Application::Mnemonic { word_count: 12 }, 0).unwrap(),
+            "girl mad pet galaxy egg matter matrix prison refuse sense ordinary nose"
+        );
+        assert_eq!(
+            derive_bip85(MASTER_KEY, Bip85Application::Wif, 0).unwrap(),
+            "Kzyv4uF39d4Jrw2W7UryTHwZr1zQVNk4dAFyqE6BuMrMh1Za7uhp"
+        );
+        assert_eq!(
+            derive_bip85(MASTER_KEY, Bip85Application::Hex { num_bytes: 64 }, 0).unwrap(),
+            "492db4698cf3b73a5a24998aa3e9d7fa96275d85724a91e71aa2d645442f878555d078fd1f1f67e368976f04137b1f7a0d19232136ca50c44614af72b5582a5c"
+        );
+        assert!(derive_bip85(MASTER_KEY, Bip85Application::Hex { num_bytes: 8 }, 0).is_err());
+        assert_ne!(
+            derive_bip85(MASTER_KEY, Bip85Application::Mnemonic { word_count: 24 }, 0).unwrap(),
+            derive_bip85(MASTER_KEY, Bip85Application::Mnemonic { word_count: 24 }, 1).unwrap()
+        );
+    }
+}
@@ -1,9 +1,11 @@
 // Send testnet coin back to https://bitcoinfaucet.uo1.net/send.php
 
 use bdk::bitcoin::bip32::ExtendedPrivKey;
-use bdk::bitcoin::Transaction;
+use bdk::bitcoin::{OutPoint, Script, Transaction, TxOut, Txid};
 
-use bdk::template::Bip84;
+use bdk::bitcoin::secp256k1::Secp256k1;
+use bdk::miniscript::descriptor::{Descriptor, DescriptorPublicKey, DescriptorType};
+use bdk::template::{Bip84, DescriptorTemplate};
 use bdk::{self, BlockTime, KeychainKind, TransactionDetails};
 use bdk::{
     bitcoin::Address,
@@ -17,9 +19,11 @@ use bdk::{
     },
     miniscript::ScriptContext,
     wallet::{AddressIndex, Wallet},
-    SignOptions, SyncOptions,
+    FeeRate, SignOptions, SyncOptions,
 };
 use qrcode_generator::QrCodeEcc;
+use rand::seq::index::sample;
+use serde::{Deserialize, Serialize};
 
 type TransactionTotal = i64;
 type Fee = u64;
@@ -27,6 +31,7 @@ type TransactionAddress = String;
 type TransactionId = String;
 type ConfirmationTime = BlockTime;
 const QRCODE_DIMENSION: usize = 300;
+pub const BACKUP_QUIZ_WORD_COUNT: usize = 3;
 
 #[derive(PartialEq)]
 pub enum TransactionDirection {
@@ -34,6 +39,41 @@ pub enum TransactionDirection {
     From,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum TransactionKind {
+    Incoming,
+    Outgoing,
+    SelfTransfer,
+    Consolidation,
+    MultiRecipient,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TransactionClassification {
+    pub kind: TransactionKind,
+    // Addresses of outputs that aren't ours, left empty for incoming transactions
+    pub counterparties: Vec<String>,
+    pub change: u64,
+}
+
+impl TransactionKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TransactionKind::Incoming => "Incoming",
+            TransactionKind::Outgoing => "Outgoing",
+            TransactionKind::SelfTransfer => "Self Transfer",
+            TransactionKind::Consolidation => "Consolidation",
+            TransactionKind::MultiRecipient => "Multiple Recipients",
+        }
+    }
+}
+
+impl TransactionClassification {
+    pub fn counterparty(&self) -> Option<String> {
+        return self.counterparties.first().cloned();
+    }
+}
+
 use std::str::FromStr;
 
 pub fn generate_mnemonic<Ctx>() -> Result<GeneratedKey<Mnemonic, Ctx>, anyhow::Error>
@@ -49,6 +89,26 @@ pub fn generate_mnemonic_string() -> Result<String, anyhow::Error> {
     return Ok(mnemonic.to_string());
 }
 
+pub fn generate_backup_quiz_indices(mnemonic: &str) -> Vec<usize> {
+    let word_count = mnemonic.split_whitespace().count();
+    let mut rng = rand::thread_rng();
+    let mut word_indices =
+        sample(&mut rng, word_count, BACKUP_QUIZ_WORD_COUNT.min(word_count)).into_vec();
+    word_indices.sort();
+    return word_indices;
+}
+
+pub fn is_backup_quiz_correct(mnemonic: &str, word_indices: &[usize], answers: &[String]) -> bool {
+    let words: Vec<&str> = mnemonic.split_whitespace().collect();
+    if word_indices.len() > answers.len() {
+        return false;
+    }
+    return word_indices
+        .iter()
+        .zip(answers.iter())
+        .all(|(index, answer)| words.get(*index) == Some(&answer.trim().to_lowercase().as_str()));
+}
+
 pub fn generate_xpriv(mnemonic: &str) -> Result<ExtendedPrivKey, anyhow::Error> {
     let mnemonic = Mnemonic::parse(mnemonic)?;
     // Generate the extended key
@@ -69,6 +129,84 @@ pub fn generate_wallet(priv_key: &str) -> Result<Wallet<MemoryDatabase>, anyhow:
     return Ok(wallet);
 }
 
+pub fn generate_wallet_from_descriptors(
+    external_descriptor: &str,
+    internal_descriptor: &str,
+) -> Result<Wallet<MemoryDatabase>, anyhow::Error> {
+    let wallet = Wallet::new(
+        external_descriptor,
+        Some(internal_descriptor),
+        Network::Testnet,
+        MemoryDatabase::new(),
+    )?;
+    return Ok(wallet);
+}
+
+pub fn generate_bip84_descriptors(priv_key: &str) -> Result<(String, String), anyhow::Error> {
+    let xpriv = ExtendedPrivKey::from_str(priv_key)?;
+    let (external, external_key_map, _) =
+        Bip84(xpriv, KeychainKind::External).build(Network::Testnet)?;
+    let (internal, internal_key_map, _) =
+        Bip84(xpriv, KeychainKind::Internal).build(Network::Testnet)?;
+    return Ok((
+        external.to_string_with_secret(&external_key_map),
+        internal.to_string_with_secret(&internal_key_map),
+    ));
+}
+
+pub fn public_descriptor_string(descriptor: &str) -> Result<String, anyhow::Error> {
+    let (descriptor, _) = Descriptor::parse_descriptor(&Secp256k1::new(), descriptor)?;
+    return Ok(descriptor.to_string());
+}
+
+pub fn private_descriptor_string(descriptor: &str) -> Result<Option<String>, anyhow::Error> {
+    let (descriptor, key_map) = Descriptor::parse_descriptor(&Secp256k1::new(), descriptor)?;
+    if key_map.is_empty() {
+        return Ok(None);
+    }
+    return Ok(Some(descriptor.to_string_with_secret(&key_map)));
+}
+
+pub fn checksummed_descriptor(descriptor: &str) -> Result<String, anyhow::Error> {
+    let (descriptor, key_map) = Descriptor::parse_descriptor(&Secp256k1::new(), descriptor)?;
+    if key_map.is_empty() {
+        return Ok(descriptor.to_string());
+    }
+    return Ok(descriptor.to_string_with_secret(&key_map));
+}
+
+fn single_key_descriptor_type(
+    descriptor: &Descriptor<DescriptorPublicKey>,
+) -> Result<DescriptorType, anyhow::Error> {
+    if !descriptor.has_wildcard() {
+        anyhow::bail!("Descriptor must end in a wildcard derivation path (/*)");
+    }
+    let descriptor_type = descriptor.desc_type();
+    match (descriptor_type, descriptor) {
+        (DescriptorType::Pkh | DescriptorType::Wpkh | DescriptorType::ShWpkh, _) => {}
+        (DescriptorType::Tr, Descriptor::Tr(tr)) if tr.tap_tree().is_none() => {}
+        _ => anyhow::bail!("Only single key wpkh, sh(wpkh), tr and pkh descriptors are supported"),
+    }
+    return Ok(descriptor_type);
+}
+
+pub fn validate_single_key_descriptor_pair(
+    external_descriptor: &str,
+    internal_descriptor: &str,
+) -> Result<(), anyhow::Error> {
+    let secp = Secp256k1::new();
+    let (external, _) = Descriptor::parse_descriptor(&secp, external_descriptor)?;
+    let (internal, _) = Descriptor::parse_descriptor(&secp, internal_descriptor)?;
+    if single_key_descriptor_type(&external)? != single_key_descriptor_type(&internal)? {
+        anyhow::bail!("External and internal descriptors must be the same script type");
+    }
+    if external == internal {
+        anyhow::bail!("External and internal descriptors must be different");
+    }
+    generate_wallet_from_descriptors(external_descriptor, internal_descriptor)?;
+    return Ok(());
+}
+
 pub fn is_valid_bitcoin_address(address: &str) -> bool {
     if let Ok(addr) = Address::from_str(address) {
         let network = Network::Testnet;
@@ -83,11 +221,8 @@ pub fn make_transaction(
     wallet: &Wallet<MemoryDatabase>,
     recipient_str: &str,
     amount: u64,
-) -> Transaction {
-    let recipient_address = Address::from_str(recipient_str)
-        .unwrap()
-        .require_network(Network::Testnet)
-        .unwrap();
+) -> Result<Transaction, anyhow::Error> {
+    let recipient_address = Address::from_str(recipient_str)?.require_network(Network::Testnet)?;
     let mut tx_builder = wallet.build_tx();
     tx_builder
         .add_recipient(
@@ -97,10 +232,29 @@ pub fn make_transaction(
         )
         .enable_rbf();
 
-    let (mut psbt, _tx_details) = tx_builder.finish().unwrap();
+    // Fails when the balance can't cover the amount plus the fee
+    let (mut psbt, _tx_details) = tx_builder.finish()?;
 
-    let _finalized = wallet.sign(&mut psbt, SignOptions::default()).unwrap();
-    return psbt.extract_tx();
+    if !wallet.sign(&mut psbt, SignOptions::default())? {
+        anyhow::bail!("Transaction could not be fully signed");
+    }
+    return Ok(psbt.extract_tx());
+}
+pub fn make_fee_bump(
+    wallet: &Wallet<MemoryDatabase>,
+    txid: Txid,
+    fee_rate: f32,
+) -> Result<Transaction, anyhow::Error> {
+    let mut tx_builder = wallet.build_fee_bump(txid)?;
+    tx_builder
+        .fee_rate(FeeRate::from_sat_per_vb(fee_rate))
+        .enable_rbf();
+    let (mut psbt, _tx_details) = tx_builder.finish()?;
+
+    if !wallet.sign(&mut psbt, SignOptions::default())? {
+        anyhow::bail!("Transaction could not be fully signed");
+    }
+    return Ok(psbt.extract_tx());
 }
 pub fn bitcoin_test() -> Result<(), Box<dyn std::error::Error>> {
     let external_descriptor = "wpkh(tprv8ZgxMBicQKsPdy6LMhUtFHAgpocR8GC6QmwMSFpZs7h6Eziw3SpThFfczTDh5rW2krkqffa11UpX3XkeTTB2FvzZKWXqPY54Y6Rq4AQ5R8L/84'/0'/0'/0/*)";
@@ -166,44 +320,73 @@ pub fn bitcoin_test() -> Result<(), Box<dyn std::error::Error>> {
     );
     Ok(())
 }
-pub fn extract_address_from_transaction(
+// prevout looks up the output an input spends, when the wallet knows it
+pub fn classify_transaction(
     transaction_details: &TransactionDetails,
-) -> Option<String> {
-    let transaction_amount = transaction_details.received as i64 - transaction_details.sent as i64;
-    let transaction_total = if transaction_amount > 0 {
-        transaction_amount as u64
-    } else {
-        transaction_amount.abs() as u64 + transaction_details.fee.unwrap()
-    };
-    let transaction_direction = if transaction_total > 0 {
-        TransactionDirection::From
-    } else {
-        TransactionDirection::To
-    };
-    let outputs = transaction_details.transaction.clone().unwrap().output;
-
+    is_mine: impl Fn(&Script) -> bool,
+    prevout: impl Fn(&OutPoint) -> Option<TxOut>,
+) -> TransactionClassification {
+    let (inputs, outputs) = transaction_details
+        .transaction
+        .as_ref()
+        .map(|transaction| (transaction.input.as_slice(), transaction.output.as_slice()))
+        .unwrap_or_default();
+    let our_inputs = inputs
+        .iter()
+        .filter(|input| {
+            prevout(&input.previous_output).map_or(false, |prevout| is_mine(&prevout.script_pubkey))
+        })
+        .count();
+    let incoming = our_inputs == 0;
+
+    let mut counterparties = Vec::new();
+    let mut external_outputs = 0;
+    let mut change = 0;
     for output in outputs.iter() {
-        let address = Address::from_script(&output.script_pubkey, Network::Testnet).unwrap();
-        let value = output.value;
-        match transaction_direction {
-            TransactionDirection::To => {
-                if transaction_total == value {
-                    return Some(address.to_string());
-                }
-            }
-            TransactionDirection::From => {
-                if transaction_total != value {
-                    return Some(address.to_string());
-                }
+        if is_mine(&output.script_pubkey) {
+            change += output.value;
+            continue;
+        }
+        // OP_RETURN data carriers aren't payments to anyone
+        if output.script_pubkey.is_op_return() && output.value == 0 {
+            continue;
+        }
+        external_outputs += 1;
+        // The other outputs of an incoming transaction are the sender's change
+        if incoming {
+            continue;
+        }
+        if let Ok(address) = Address::from_script(&output.script_pubkey, Network::Testnet) {
+            let address = address.to_string();
+            if !counterparties.contains(&address) {
+                counterparties.push(address);
             }
         }
     }
-
-    None
+    let kind = if incoming {
+        TransactionKind::Incoming
+    } else if external_outputs == 0 && our_inputs > 1 && outputs.len() == 1 {
+        TransactionKind::Consolidation
+    } else if external_outputs == 0 {
+        TransactionKind::SelfTransfer
+    } else if external_outputs == 1 {
+        TransactionKind::Outgoing
+    } else {
+        TransactionKind::MultiRecipient
+    };
+    if kind == TransactionKind::Incoming {
+        change = 0;
+    }
+    return TransactionClassification {
+        kind,
+        counterparties,
+        change,
+    };
 }
 
 pub fn get_transaction_details(
     transaction_details: TransactionDetails,
+    classification: Option<&TransactionClassification>,
 ) -> (
     TransactionDirection,
     TransactionAddress,
@@ -213,19 +396,18 @@ pub fn get_transaction_details(
     Option<ConfirmationTime>,
 ) {
     let transaction_total = transaction_details.received as i64 - transaction_details.sent as i64;
-    let transaction = transaction_details.transaction.clone().unwrap();
     let transaction_id = transaction_details.txid.to_string();
-    let transaction_address =
-        extract_address_from_transaction(&transaction_details.clone()).unwrap();
+    let transaction_address = classification
+        .and_then(|classification| classification.counterparty())
+        .unwrap_or_default();
 
-    let fee = transaction_details.fee.unwrap();
+    let fee = transaction_details.fee.unwrap_or(0);
     let confirmation_time = transaction_details.clone().confirmation_time;
     let transaction_direction = if transaction_total < 0 {
         TransactionDirection::To
     } else {
         TransactionDirection::From
     };
-    // extract_address_from_transaction_test(&transaction_details);
     return (
         transaction_direction,
         transaction_address,
@@ -250,9 +432,20 @@ pub fn generate_qrcode_from_address(address: &str) -> Result<egui::ColorImage, i
 mod tests {
     use std::str::FromStr;
 
+    use bdk::bitcoin::absolute::LockTime;
     use bdk::bitcoin::bip32::ExtendedPrivKey;
+    use bdk::bitcoin::hashes::Hash;
+    use bdk::bitcoin::script::Builder;
+    use bdk::bitcoin::{
+        Address, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid, Witness,
+    };
+    use bdk::TransactionDetails;
 
-    use crate::bitcoin_wallet::generate_xpriv;
+    use crate::bitcoin_wallet::{
+        classify_transaction, generate_backup_quiz_indices, generate_bip84_descriptors,
+        generate_xpriv, is_backup_quiz_correct, private_descriptor_string,
+        public_descriptor_string, validate_single_key_descriptor_pair, TransactionKind,
+    };
 
     #[test]
     fn test_generating_wallet() {
@@ -270,4 +463,162 @@ mod tests {
         let xpriv_str_1 = xpriv_1.to_string();
         println!("{}", &xpriv_str_1);
     }
+
+    #[test]
+    fn test_backup_quiz() {
+        let mnemonic =
+            "limb capital decade way negative task moral empty virus fragile copper elegant";
+        let word_indices = generate_backup_quiz_indices(mnemonic);
+        assert_eq!(word_indices.len(), 3);
+        assert!(word_indices.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let words: Vec<&str> = mnemonic.split_whitespace().collect();
+        let answers: Vec<String> = word_indices
+            .iter()
+            .map(|index| format!(" {} ", words[*index].to_uppercase()))
+            .collect();
+        assert!(is_backup_quiz_correct(mnemonic, &word_indices, &answers));
+
+        let wrong_answers = vec!["abandon".to_string(); 3];
+        assert!(!is_backup_quiz_correct(
+            mnemonic,
+            &word_indices,
+            &wrong_answers
+        ));
+        assert!(!is_backup_quiz_correct(mnemonic, &word_indices, &[]));
+    }
+
+    #[test]
+    fn test_descriptor_export_and_import() {
+        let mnemonic =
+            "limb capital decade way negative task moral empty virus fragile copper elegant";
+        let priv_key = generate_xpriv(mnemonic).unwrap().to_string();
+        let (external, internal) = generate_bip84_descriptors(&priv_key).unwrap();
+        assert!(external.starts_with("wpkh(tprv") && external.contains('#'));
+        assert!(validate_single_key_descriptor_pair(&external, &internal).is_ok());
+        assert!(private_descriptor_string(&external).unwrap().is_some());
+
+        let public_external = public_descriptor_string(&external).unwrap();
+        let public_internal = public_descriptor_string(&internal).unwrap();
+        assert!(public_external.starts_with("wpkh([") && public_external.contains("tpub"));
+        assert_eq!(private_descriptor_string(&public_external).unwrap(), None);
+        assert!(validate_single_key_descriptor_pair(&public_external, &public_internal).is_ok());
+
+        let (external_body, checksum) = public_external.split_once('#').unwrap();
+        let corrupted_checksum: String = checksum.chars().rev().collect();
+        let corrupted = format!("{}#{}", external_body, corrupted_checksum);
+        assert!(validate_single_key_descriptor_pair(&corrupted, &public_internal).is_err());
+
+        let pkh_external = external_body.replacen("wpkh(", "pkh(", 1);
+        assert!(validate_single_key_descriptor_pair(&pkh_external, &public_internal).is_err());
+    }
+
+    #[test]
+    fn test_classify_transaction() {
+        let script = |address: &str| {
+            Address::from_str(address)
+                .unwrap()
+                .assume_checked()
+                .script_pubkey()
+        };
+        let ours = script("tb1qw2c3lxufxqe2x9s4rdzh65tpf4d7fssjgh8nv6");
+        let theirs = script("mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn");
+        let other = script("tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7");
+        let op_return = Builder::new()
+            .push_opcode(bdk::bitcoin::opcodes::all::OP_RETURN)
+            .into_script();
+        // Inputs spending vouts below THEIR_VOUT come from our outputs
+        const THEIR_VOUT: u32 = 100;
+        let transaction_details = |our_inputs: u32, outputs: Vec<(ScriptBuf, u64)>| {
+            let vouts = match our_inputs {
+                0 => THEIR_VOUT..THEIR_VOUT + 1,
+                _ => 0..our_inputs,
+            };
+            let transaction = Transaction {
+                version: 2,
+                lock_time: LockTime::ZERO,
+                input: vouts
+                    .map(|vout| TxIn {
+                        previous_output: OutPoint::new(Txid::all_zeros(), vout),
+                        script_sig: ScriptBuf::new(),
+                        sequence: Sequence::MAX,
+                        witness: Witness::new(),
+                    })
+                    .collect(),
+                output: outputs
+                    .into_iter()
+                    .map(|(script_pubkey, value)| TxOut {
+                        value,
+                        script_pubkey,
+                    })
+                    .collect(),
+            };
+            TransactionDetails {
+                txid: transaction.txid(),
+                transaction: Some(transaction),
+                received: 0,
+                sent: 0,
+                fee: None,
+                confirmation_time: None,
+            }
+        };
+        let is_mine = |script: &bdk::bitcoin::Script| script == ours.as_script();
+        let prevout = |outpoint: &OutPoint| {
+            Some(TxOut {
+                value: 10_000,
+                script_pubkey: match outpoint.vout < THEIR_VOUT {
+                    true => ours.clone(),
+                    false => theirs.clone(),
+                },
+            })
+        };
+
+        let incoming = transaction_details(0, vec![(ours.clone(), 5_000), (theirs.clone(), 900)]);
+        let classification = classify_transaction(&incoming, is_mine, prevout);
+        assert_eq!(classification.kind, TransactionKind::Incoming);
+        assert_eq!(classification.change, 0);
+        assert!(classification.counterparties.is_empty());
+
+        let outgoing = transaction_details(
+            1,
+            vec![(theirs.clone(), 5_000), (ours.clone(), 900), (op_return, 0)],
+        );
+        let classification = classify_transaction(&outgoing, is_mine, prevout);
+        assert_eq!(classification.kind, TransactionKind::Outgoing);
+        assert_eq!(
+            classification.counterparty(),
+            Some("mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn".to_string())
+        );
+        assert_eq!(classification.change, 900);
+
+        let multi = transaction_details(1, vec![(theirs.clone(), 5_000), (other, 900)]);
+        assert_eq!(
+            classify_transaction(&multi, is_mine, prevout).kind,
+            TransactionKind::MultiRecipient
+        );
+
+        let consolidation = transaction_details(3, vec![(ours.clone(), 5_000)]);
+        assert_eq!(
+            classify_transaction(&consolidation, is_mine, prevout).kind,
+            TransactionKind::Consolidation
+        );
+        // Only our own inputs count towards a consolidation
+        let single_input = transaction_details(1, vec![(ours.clone(), 5_000)]);
+        assert_eq!(
+            classify_transaction(&single_input, is_mine, prevout).kind,
+            TransactionKind::SelfTransfer
+        );
+
+        let nonstandard = ScriptBuf::from_bytes(vec![0xff, 0xfe]);
+        let self_transfer =
+            transaction_details(1, vec![(ours.clone(), 5_000), (ours.clone(), 100)]);
+        assert_eq!(
+            classify_transaction(&self_transfer, is_mine, prevout).kind,
+            TransactionKind::SelfTransfer
+        );
+        let unknown_output = transaction_details(1, vec![(nonstandard, 5_000)]);
+        let classification = classify_transaction(&unknown_output, is_mine, prevout);
+        assert_eq!(classification.kind, TransactionKind::Outgoing);
+        assert_eq!(classification.counterparty(), None);
+    }
 }
@@ -0,0 +1,384 @@
+use chrono::{Datelike, TimeZone, Utc};
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::fiat::sats_to_fiat;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum CostBasisMethod {
+    Fifo,
+    Lifo,
+    Hifo,
+}
+
+pub const COST_BASIS_METHODS: [CostBasisMethod; 3] = [
+    CostBasisMethod::Fifo,
+    CostBasisMethod::Lifo,
+    CostBasisMethod::Hifo,
+];
+
+impl CostBasisMethod {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CostBasisMethod::Fifo => "FIFO",
+            CostBasisMethod::Lifo => "LIFO",
+            CostBasisMethod::Hifo => "HIFO",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct WalletTransaction {
+    pub wallet_id: String,
+    pub wallet_name: String,
+    pub txid: String,
+    pub timestamp: u64,
+    pub net_sats: i64,
+    pub fee: u64,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct Lot {
+    pub wallet_name: String,
+    pub txid: String,
+    pub acquired: u64,
+    pub sats: u64,
+    pub cost: f64,
+}
+
+impl Lot {
+    fn unit_cost(&self) -> f64 {
+        if self.sats == 0 {
+            return 0.0;
+        }
+        return self.cost / self.sats as f64;
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct Disposal {
+    pub wallet_name: String,
+    pub txid: String,
+    pub disposed: u64,
+    pub sats: u64,
+    pub proceeds: f64,
+    pub cost_basis: f64,
+}
+
+impl Disposal {
+    pub fn gain(&self) -> f64 {
+        return self.proceeds - self.cost_basis;
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct YearSummary {
+    pub wallet_name: String,
+    pub year: i32,
+    pub proceeds: f64,
+    pub cost_basis: f64,
+    pub gain: f64,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct CostBasisReport {
+    pub method: CostBasisMethod,
+    pub disposals: Vec<Disposal>,
+    pub remaining_lots: Vec<Lot>,
+}
+
+fn timestamp_year(timestamp: u64) -> i32 {
+    return Utc
+        .timestamp_opt(timestamp as i64, 0)
+        .single()
+        .map_or(1970, |date_time| date_time.year());
+}
+
+fn format_timestamp(timestamp: u64) -> String {
+    return Utc
+        .timestamp_opt(timestamp as i64, 0)
+        .single()
+        .map(|date_time| date_time.format("%Y-%m-%d").to_string())
+        .unwrap_or_default();
+}
+
+fn consume_lots(lots: &mut Vec<Lot>, sats: u64, method: CostBasisMethod) -> Vec<Lot> {
+    let mut consumed = Vec::new();
+    let mut remaining = sats;
+    while remaining > 0 && !lots.is_empty() {
+        let index = match method {
+            CostBasisMethod::Fifo => 0,
+            CostBasisMethod::Lifo => lots.len() - 1,
+            CostBasisMethod::Hifo => (0..lots.len())
+                .max_by(|a, b| lots[*a].unit_cost().total_cmp(&lots[*b].unit_cost()))
+                .unwrap_or(0),
+        };
+        if lots[index].sats <= remaining {
+            remaining -= lots[index].sats;
+            consumed.push(lots.remove(index));
+        } else {
+            let lot = &mut lots[index];
+            let part_cost = lot.unit_cost() * remaining as f64;
+            consumed.push(Lot {
+                sats: remaining,
+                cost: part_cost,
+                ..lot.clone()
+            });
+            lot.sats -= remaining;
+            lot.cost -= part_cost;
+            remaining = 0;
+        }
+    }
+    // Coins with no known acquisition are treated as having zero cost basis
+    if remaining > 0 {
+        consumed.push(Lot {
+            wallet_name: String::new(),
+            txid: String::new(),
+            acquired: 0,
+            sats: remaining,
+            cost: 0.0,
+        });
+    }
+    return consumed;
+}
+
+fn insert_lot(lots: &mut Vec<Lot>, lot: Lot) {
+    let index = lots.partition_point(|existing| existing.acquired <= lot.acquired);
+    lots.insert(index, lot);
+}
+
+pub fn compute_cost_basis(
+    transactions: &[WalletTransaction],
+    method: CostBasisMethod,
+    price_at: &dyn Fn(u64) -> Result<f64, anyhow::Error>,
+) -> Result<CostBasisReport, anyhow::Error> {
+    // Group both sides of transfers between our own wallets under one txid
+    let mut groups: BTreeMap<(u64, String), Vec<&WalletTransaction>> = BTreeMap::new();
+    for transaction in transactions.iter() {
+        groups
+            .entry((transaction.timestamp, transaction.txid.clone()))
+            .or_default()
+            .push(transaction);
+    }
+
+    let mut lots: HashMap<String, Vec<Lot>> = HashMap::new();
+    let mut disposals = Vec::new();
+    for ((timestamp, txid), group) in groups.iter() {
+        let price = price_at(*timestamp)?;
+        let mut transferred: Vec<Lot> = Vec::new();
+        let mut internal_received: u64 = group
+            .iter()
+            .filter(|transaction| transaction.net_sats > 0)
+            .map(|transaction| transaction.net_sats as u64)
+            .sum();
+
+        for sender in group.iter().filter(|transaction| transaction.net_sats < 0) {
+            let wallet_lots = lots.entry(sender.wallet_id.clone()).or_default();
+            let outflow = sender.net_sats.unsigned_abs();
+            let fee = sender.fee.min(outflow);
+            let transfer = (outflow - fee).min(internal_received);
+            internal_received -= transfer;
+            transferred.extend(consume_lots(wallet_lots, transfer, method));
+
+            let external = outflow - fee - transfer;
+            let disposed_sats = external + fee;
+            if disposed_sats == 0 {
+                continue;
+            }
+            let cost_basis: f64 = consume_lots(wallet_lots, disposed_sats, method)
+                .iter()
+                .map(|lot| lot.cost)
+                .sum();
+            disposals.push(Disposal {
+                wallet_name: sender.wallet_name.clone(),
+                txid: txid.clone(),
+                disposed: *timestamp,
+                sats: disposed_sats,
+                // Fees are spent with no proceeds
+                proceeds: sats_to_fiat(external, price),
+                cost_basis,
+            });
+        }
+
+        for receiver in group.iter().filter(|transaction| transaction.net_sats > 0) {
+            let wallet_lots = lots.entry(receiver.wallet_id.clone()).or_default();
+            let mut received = receiver.net_sats as u64;
+            while received > 0 && !transferred.is_empty() {
+                let mut lot = transferred.remove(0);
+                if lot.sats > received {
+                    let mut rest = lot.clone();
+                    let part_cost = lot.unit_cost() * received as f64;
+                    rest.sats -= received;
+                    rest.cost -= part_cost;
+                    transferred.insert(0, rest);
+                    lot.sats = received;
+                    lot.cost = part_cost;
+                }
+                received -= lot.sats;
+                lot.wallet_name = receiver.wallet_name.clone();
+                insert_lot(wallet_lots, lot);
+            }
+            if received > 0 {
+                insert_lot(
+                    wallet_lots,
+                    Lot {
+                        wallet_name: receiver.wallet_name.clone(),
+                        txid: txid.clone(),
+                        acquired: *timestamp,
+                        sats: received,
+                        cost: sats_to_fiat(received, price),
+                    },
+                );
+            }
+        }
+    }
+
+    let mut remaining_lots: Vec<Lot> = lots.into_values().flatten().collect();
+    remaining_lots.sort_by(|a, b| {
+        (a.wallet_name.as_str(), a.acquired).cmp(&(b.wallet_name.as_str(), b.acquired))
+    });
+    return Ok(CostBasisReport {
+        method,
+        disposals,
+        remaining_lots,
+    });
+}
+
+impl CostBasisReport {
+    pub fn year_summaries(&self) -> Vec<YearSummary> {
+        let mut summaries: BTreeMap<(String, i32), YearSummary> = BTreeMap::new();
+        for disposal in self.disposals.iter() {
+            let year = timestamp_year(disposal.disposed);
+            let summary = summaries
+                .entry((disposal.wallet_name.clone(), year))
+                .or_insert(YearSummary {
+                    wallet_name: disposal.wallet_name.clone(),
+                    year,
+                    proceeds: 0.0,
+                    cost_basis: 0.0,
+                    gain: 0.0,
+                });
+            summary.proceeds += disposal.proceeds;
+            summary.cost_basis += disposal.cost_basis;
+            summary.gain += disposal.gain();
+        }
+        return summaries.into_values().collect();
+    }
+
+    pub fn to_csv(&self, current_price: Option<f64>) -> Result<String, anyhow::Error> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record([
+            "Type",
+            "Wallet",
+            "Year",
+            "Txid",
+            "Acquired",
+            "Disposed",
+            "Sats",
+            "Proceeds",
+            "Cost Basis",
+            "Gain",
+        ])?;
+        for disposal in self.disposals.iter() {
+            writer.write_record([
+                "Realized".to_string(),
+                disposal.wallet_name.clone(),
+                timestamp_year(disposal.disposed).to_string(),
+                disposal.txid.clone(),
+                String::new(),
+                format_timestamp(disposal.disposed),
+                disposal.sats.to_string(),
+                format!("{:.2}", disposal.proceeds),
+                format!("{:.2}", disposal.cost_basis),
+                format!("{:.2}", disposal.gain()),
+            ])?;
+        }
+        for lot in self.remaining_lots.iter() {
+            let value = current_price.map(|price| sats_to_fiat(lot.sats, price));
+            writer.write_record([
+                "Unrealized".to_string(),
+                lot.wallet_name.clone(),
+                timestamp_year(lot.acquired).to_string(),
+                lot.txid.clone(),
+                format_timestamp(lot.acquired),
+                String::new(),
+                lot.sats.to_string(),
+                value.map_or(String::new(), |value| format!("{:.2}", value)),
+                format!("{:.2}", lot.cost),
+                value.map_or(String::new(), |value| format!("{:.2}", value - lot.cost)),
+            ])?;
+        }
+        return Ok(String::from_utf8(writer.into_inner()?)?);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cost_basis::{compute_cost_basis, CostBasisMethod, WalletTransaction};
+
+    const DAY: u64 = 86_400;
+
+    fn transaction(
+        wallet: &str,
+        txid: &str,
+        day: u64,
+        net_sats: i64,
+        fee: u64,
+    ) -> WalletTransaction {
+        WalletTransaction {
+            wallet_id: wallet.to_string(),
+            wallet_name: wallet.to_string(),
+            txid: txid.to_string(),
+            timestamp: day * DAY,
+            net_sats,
+            fee,
+        }
+    }
+
+    #[test]
+    fn test_cost_basis_methods_and_internal_transfers() {
+        let transactions = vec![
+            transaction("spending", "buy1", 1, 100_000_000, 0),
+            transaction("spending", "buy2", 2, 100_000_000, 0),
+            // Move half a coin to savings, paying a 1000 sat fee
+            transaction("spending", "move", 3, -50_001_000, 1_000),
+            transaction("savings", "move", 3, 50_000_000, 0),
+            transaction("spending", "sell", 4, -100_000_000, 0),
+        ];
+        let prices = |timestamp: u64| -> Result<f64, anyhow::Error> {
+            Ok(match timestamp / DAY {
+                1 => 10_000.0,
+                2 => 20_000.0,
+                _ => 30_000.0,
+            })
+        };
+
+        let fifo = compute_cost_basis(&transactions, CostBasisMethod::Fifo, &prices).unwrap();
+        // The transfer is not a disposal, only its fee is
+        assert_eq!(fifo.disposals.len(), 2);
+        assert_eq!(fifo.disposals[0].sats, 1_000);
+        assert_eq!(fifo.disposals[0].proceeds, 0.0);
+        let sale = &fifo.disposals[1];
+        assert_eq!(sale.proceeds, 30_000.0);
+        assert!((sale.cost_basis - (4_999.9 + 10_000.2)).abs() < 0.01);
+        let savings_lot = fifo
+            .remaining_lots
+            .iter()
+            .find(|lot| lot.wallet_name == "savings")
+            .unwrap();
+        assert_eq!(savings_lot.sats, 50_000_000);
+        assert_eq!(savings_lot.txid, "buy1");
+        assert!((savings_lot.cost - 5_000.0).abs() < 0.01);
+
+        let lifo = compute_cost_basis(&transactions, CostBasisMethod::Lifo, &prices).unwrap();
+        assert!((lifo.disposals[1].cost_basis - (9_999.8 + 10_000.0 * 0.50001)).abs() < 0.01);
+
+        let hifo = compute_cost_basis(&transactions, CostBasisMethod::Hifo, &prices).unwrap();
+        assert_eq!(hifo.disposals[1].cost_basis, lifo.disposals[1].cost_basis);
+        assert_eq!(hifo.year_summaries().len(), 1);
+        assert!(hifo
+            .to_csv(Some(40_000.0))
+            .unwrap()
+            .contains("Unrealized,savings,1970"));
+    }
+}
@@ -0,0 +1,525 @@
+use chrono::{NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::proxy::ProxyConfig;
+
+const SATS_PER_BTC: f64 = 100_000_000.0;
+const COINGECKO_API_URL: &str = "https://api.coingecko.com/api/v3";
+const CURRENT_PRICE_REFRESH_S: u64 = 300;
+const HISTORY_RETRY_MIN_S: u64 = 30;
+const HISTORY_RETRY_MAX_S: u64 = 1800;
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum FiatCurrency {
+    Usd,
+    Eur,
+    Gbp,
+}
+
+impl Default for FiatCurrency {
+    fn default() -> Self {
+        FiatCurrency::Usd
+    }
+}
+
+pub const FIAT_CURRENCIES: [FiatCurrency; 3] =
+    [FiatCurrency::Usd, FiatCurrency::Eur, FiatCurrency::Gbp];
+
+impl FiatCurrency {
+    pub fn code(&self) -> &'static str {
+        match self {
+            FiatCurrency::Usd => "usd",
+            FiatCurrency::Eur => "eur",
+            FiatCurrency::Gbp => "gbp",
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            FiatCurrency::Usd => "$",
+            FiatCurrency::Eur => "€",
+            FiatCurrency::Gbp => "£",
+        }
+    }
+
+    pub fn name(&self) -> String {
+        return self.code().to_uppercase();
+    }
+}
+
+pub trait PriceProvider: Send + Sync {
+    fn current_price(&self, currency: FiatCurrency) -> Result<f64, anyhow::Error>;
+    fn historical_price(
+        &self,
+        currency: FiatCurrency,
+        date: NaiveDate,
+    ) -> Result<f64, anyhow::Error>;
+    // Dates the provider has no price for are left out rather than failing the batch
+    fn historical_prices(
+        &self,
+        currency: FiatCurrency,
+        dates: &[NaiveDate],
+    ) -> Result<HashMap<NaiveDate, f64>, anyhow::Error> {
+        let mut prices = HashMap::new();
+        let mut last_error = None;
+        for date in dates.iter() {
+            match self.historical_price(currency, *date) {
+                Ok(price) => {
+                    prices.insert(*date, price);
+                }
+                Err(error) => last_error = Some(error),
+            }
+        }
+        match last_error {
+            Some(error) if prices.is_empty() => Err(error),
+            _ => Ok(prices),
+        }
+    }
+    // Only providers that go over the network care about the proxy
+    fn set_proxy(&self, _proxy: &ProxyConfig) {}
+}
+
+pub struct HttpPriceProvider {
+    base_url: String,
+    proxy: Mutex<ProxyConfig>,
+}
+
+impl HttpPriceProvider {
+    pub fn new(proxy: &ProxyConfig) -> Self {
+        HttpPriceProvider {
+            base_url: COINGECKO_API_URL.to_string(),
+            proxy: Mutex::new(proxy.clone()),
+        }
+    }
+
+    fn get_json(&self, path: &str) -> Result<serde_json::Value, anyhow::Error> {
+        let url = format!("{}{}", self.base_url, path);
+        let agent = self.proxy.lock().unwrap().http_agent(&url)?;
+        return Ok(agent.get(&url).call()?.into_json()?);
+    }
+}
+
+impl PriceProvider for HttpPriceProvider {
+    fn current_price(&self, currency: FiatCurrency) -> Result<f64, anyhow::Error> {
+        let response = self.get_json(&format!(
+            "/simple/price?ids=bitcoin&vs_currencies={}",
+            currency.code()
+        ))?;
+        let Some(price) = response["bitcoin"][currency.code()].as_f64() else {
+            anyhow::bail!("No {} price in response", currency.name());
+        };
+        return Ok(price);
+    }
+
+    fn historical_price(
+        &self,
+        currency: FiatCurrency,
+        date: NaiveDate,
+    ) -> Result<f64, anyhow::Error> {
+        let response = self.get_json(&format!(
+            "/coins/bitcoin/history?date={}&localization=false",
+            date.format("%d-%m-%Y")
+        ))?;
+        let Some(price) = response["market_data"]["current_price"][currency.code()].as_f64() else {
+            anyhow::bail!("No {} price for {}", currency.name(), date);
+        };
+        return Ok(price);
+    }
+
+    // One range request covers every date, where per-day lookups hit the rate limit
+    fn historical_prices(
+        &self,
+        currency: FiatCurrency,
+        dates: &[NaiveDate],
+    ) -> Result<HashMap<NaiveDate, f64>, anyhow::Error> {
+        let (Some(first), Some(last)) = (dates.iter().min(), dates.iter().max()) else {
+            return Ok(HashMap::new());
+        };
+        let from = Utc.from_utc_datetime(&first.and_hms_opt(0, 0, 0).unwrap());
+        let to = Utc.from_utc_datetime(&last.and_hms_opt(23, 59, 59).unwrap());
+        let response = self.get_json(&format!(
+            "/coins/bitcoin/market_chart/range?vs_currency={}&from={}&to={}",
+            currency.code(),
+            from.timestamp(),
+            to.timestamp()
+        ))?;
+        let Some(samples) = response["prices"].as_array() else {
+            anyhow::bail!("No {} prices in response", currency.name());
+        };
+        let mut daily = BTreeMap::new();
+        for sample in samples.iter() {
+            let (Some(timestamp_ms), Some(price)) = (sample[0].as_f64(), sample[1].as_f64()) else {
+                continue;
+            };
+            if let Some(date) = timestamp_date(timestamp_ms as u64 / 1000) {
+                daily.entry(date).or_insert(price);
+            }
+        }
+        return Ok(prices_for_dates(&daily, dates));
+    }
+
+    fn set_proxy(&self, proxy: &ProxyConfig) {
+        *self.proxy.lock().unwrap() = proxy.clone();
+    }
+}
+
+// Uses the closest earlier sample for dates the series skips
+fn prices_for_dates(
+    daily: &BTreeMap<NaiveDate, f64>,
+    dates: &[NaiveDate],
+) -> HashMap<NaiveDate, f64> {
+    return dates
+        .iter()
+        .filter_map(|date| {
+            daily
+                .range(..=*date)
+                .next_back()
+                .map(|(_, price)| (*date, *price))
+        })
+        .collect();
+}
+
+#[derive(Deserialize)]
+struct DailyRate {
+    date: NaiveDate,
+    usd: Option<f64>,
+    eur: Option<f64>,
+    gbp: Option<f64>,
+}
+
+impl DailyRate {
+    fn price(&self, currency: FiatCurrency) -> Option<f64> {
+        match currency {
+            FiatCurrency::Usd => self.usd,
+            FiatCurrency::Eur => self.eur,
+            FiatCurrency::Gbp => self.gbp,
+        }
+    }
+}
+
+// Daily rates loaded from a JSON array or CSV with date,usd,eur,gbp columns
+pub struct FilePriceProvider {
+    rates: BTreeMap<NaiveDate, DailyRate>,
+}
+
+impl FilePriceProvider {
+    pub fn from_file(path: &str) -> Result<Self, anyhow::Error> {
+        let contents = fs::read_to_string(path)?;
+        let is_json = Path::new(path)
+            .extension()
+            .map_or(false, |extension| extension.eq_ignore_ascii_case("json"));
+        return match is_json {
+            true => Self::from_json(&contents),
+            false => Self::from_csv(&contents),
+        };
+    }
+
+    pub fn from_json(contents: &str) -> Result<Self, anyhow::Error> {
+        let rates: Vec<DailyRate> = serde_json::from_str(contents)?;
+        return Self::from_rates(rates);
+    }
+
+    pub fn from_csv(contents: &str) -> Result<Self, anyhow::Error> {
+        let mut reader = csv::Reader::from_reader(contents.as_bytes());
+        let rates = reader
+            .deserialize()
+            .collect::<Result<Vec<DailyRate>, csv::Error>>()?;
+        return Self::from_rates(rates);
+    }
+
+    fn from_rates(rates: Vec<DailyRate>) -> Result<Self, anyhow::Error> {
+        if rates.is_empty() {
+            anyhow::bail!("Price file contains no rates");
+        }
+        return Ok(FilePriceProvider {
+            rates: rates.into_iter().map(|rate| (rate.date, rate)).collect(),
+        });
+    }
+}
+
+impl PriceProvider for FilePriceProvider {
+    fn current_price(&self, currency: FiatCurrency) -> Result<f64, anyhow::Error> {
+        let Some(price) = self
+            .rates
+            .values()
+            .rev()
+            .find_map(|rate| rate.price(currency))
+        else {
+            anyhow::bail!("No {} prices in file", currency.name());
+        };
+        return Ok(price);
+    }
+
+    fn historical_price(
+        &self,
+        currency: FiatCurrency,
+        date: NaiveDate,
+    ) -> Result<f64, anyhow::Error> {
+        let Some(price) = self
+            .rates
+            .range(..=date)
+            .rev()
+            .find_map(|(_, rate)| rate.price(currency))
+        else {
+            anyhow::bail!("No {} price on or before {}", currency.name(), date);
+        };
+        return Ok(price);
+    }
+}
+
+// Saved in the wallet file so a loaded price file is used again after a restart
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub enum PriceSource {
+    CoinGecko,
+    File { path: String },
+}
+
+impl Default for PriceSource {
+    fn default() -> Self {
+        PriceSource::CoinGecko
+    }
+}
+
+impl PriceSource {
+    pub fn provider(&self, proxy: &ProxyConfig) -> Result<Arc<dyn PriceProvider>, anyhow::Error> {
+        match self {
+            PriceSource::CoinGecko => return Ok(Arc::new(HttpPriceProvider::new(proxy))),
+            PriceSource::File { path } => return Ok(Arc::new(FilePriceProvider::from_file(path)?)),
+        }
+    }
+}
+
+pub fn timestamp_date(timestamp: u64) -> Option<NaiveDate> {
+    return Utc
+        .timestamp_opt(timestamp as i64, 0)
+        .single()
+        .map(|date_time| date_time.date_naive());
+}
+
+pub fn sats_to_fiat(sats: u64, price: f64) -> f64 {
+    return sats as f64 / SATS_PER_BTC * price;
+}
+
+pub fn fiat_to_sats(fiat: f64, price: f64) -> Option<u64> {
+    if !fiat.is_finite() || fiat < 0.0 || price <= 0.0 {
+        return None;
+    }
+    return Some((fiat / price * SATS_PER_BTC).round() as u64);
+}
+
+pub fn format_fiat(value: f64, currency: FiatCurrency) -> String {
+    return format!("{}{:.2}", currency.symbol(), value);
+}
+
+#[derive(Default)]
+struct PriceCache {
+    current: HashMap<FiatCurrency, f64>,
+    historical: HashMap<(FiatCurrency, NaiveDate), f64>,
+    last_current_refresh: Option<Instant>,
+    history_retry_at: Option<Instant>,
+    history_backoff: Option<Duration>,
+    refreshing: bool,
+    current_error: Option<String>,
+    history_error: Option<String>,
+}
+
+impl PriceCache {
+    fn back_off_history(&mut self) {
+        let backoff = match self.history_backoff {
+            Some(backoff) => (backoff * 2).min(Duration::from_secs(HISTORY_RETRY_MAX_S)),
+            None => Duration::from_secs(HISTORY_RETRY_MIN_S),
+        };
+        self.history_backoff = Some(backoff);
+        self.history_retry_at = Some(Instant::now() + backoff);
+    }
+}
+
+// Prices are fetched on a background thread so the UI never waits on the network
+pub struct FiatValuation {
+    pub currency: FiatCurrency,
+    provider: Arc<dyn PriceProvider>,
+    cache: Arc<Mutex<PriceCache>>,
+}
+
+impl FiatValuation {
+    pub fn new(provider: Arc<dyn PriceProvider>) -> Self {
+        FiatValuation {
+            currency: FiatCurrency::Usd,
+            provider,
+            cache: Arc::new(Mutex::new(PriceCache::default())),
+        }
+    }
+
+    pub fn set_provider(&mut self, provider: Arc<dyn PriceProvider>) {
+        self.provider = provider;
+        self.cache = Arc::new(Mutex::new(PriceCache::default()));
+    }
+
+    pub fn set_proxy(&self, proxy: &ProxyConfig) {
+        self.provider.set_proxy(proxy);
+    }
+
+    pub fn current_price(&self) -> Option<f64> {
+        return self
+            .cache
+            .lock()
+            .unwrap()
+            .current
+            .get(&self.currency)
+            .copied();
+    }
+
+    pub fn historical_price(&self, timestamp: u64) -> Option<f64> {
+        let date = timestamp_date(timestamp)?;
+        let cache = self.cache.lock().unwrap();
+        return cache.historical.get(&(self.currency, date)).copied();
+    }
+
+    pub fn last_error(&self) -> Option<String> {
+        let cache = self.cache.lock().unwrap();
+        return cache.current_error.clone().or(cache.history_error.clone());
+    }
+
+    pub fn refresh(&self, timestamps: &[u64]) {
+        let currency = self.currency;
+        let mut cache = self.cache.lock().unwrap();
+        if cache.refreshing {
+            return;
+        }
+        let current_stale = !cache.current.contains_key(&currency)
+            || cache.last_current_refresh.map_or(true, |last_refresh| {
+                last_refresh.elapsed() > Duration::from_secs(CURRENT_PRICE_REFRESH_S)
+            });
+        let missing_dates: HashSet<NaiveDate> = timestamps
+            .iter()
+            .filter_map(|timestamp| timestamp_date(*timestamp))
+            .filter(|date| !cache.historical.contains_key(&(currency, *date)))
+            .collect();
+        // Missing history is retried on its own backoff rather than every frame
+        let history_due = !missing_dates.is_empty()
+            && cache
+                .history_retry_at
+                .map_or(true, |retry_at| Instant::now() >= retry_at);
+        if !current_stale && !history_due {
+            return;
+        }
+        cache.refreshing = true;
+        if current_stale {
+            cache.last_current_refresh = Some(Instant::now());
+        }
+        drop(cache);
+        let missing_dates: Vec<NaiveDate> = match history_due {
+            true => missing_dates.into_iter().collect(),
+            false => Vec::new(),
+        };
+
+        let provider = Arc::clone(&self.provider);
+        let cache = Arc::clone(&self.cache);
+        thread::spawn(move || {
+            let current = match current_stale {
+                true => Some(provider.current_price(currency)),
+                false => None,
+            };
+            let historical = match missing_dates.is_empty() {
+                true => None,
+                false => Some(provider.historical_prices(currency, &missing_dates)),
+            };
+            let mut cache = cache.lock().unwrap();
+            match current {
+                Some(Ok(price)) => {
+                    cache.current.insert(currency, price);
+                    cache.current_error = None;
+                }
+                Some(Err(error)) => cache.current_error = Some(error.to_string()),
+                None => {}
+            }
+            match historical {
+                Some(Ok(prices)) => {
+                    let unpriced = missing_dates.len() - prices.len();
+                    for (date, price) in prices {
+                        cache.historical.insert((currency, date), price);
+                    }
+                    if unpriced == 0 {
+                        cache.history_backoff = None;
+                        cache.history_retry_at = None;
+                        cache.history_error = None;
+                    } else {
+                        cache.history_error = Some(format!(
+                            "No {} price for {} transaction dates",
+                            currency.name(),
+                            unpriced
+                        ));
+                        cache.back_off_history();
+                    }
+                }
+                Some(Err(error)) => {
+                    cache.history_error = Some(error.to_string());
+                    cache.back_off_history();
+                }
+                None => {}
+            }
+            cache.refreshing = false;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use std::collections::BTreeMap;
+
+    use crate::fiat::{
+        fiat_to_sats, prices_for_dates, sats_to_fiat, FiatCurrency, FilePriceProvider,
+        PriceProvider,
+    };
+
+    #[test]
+    fn test_file_price_provider() {
+        let csv = "date,usd,eur,gbp\n2024-01-01,42000,38000,33000\n2024-01-03,44000,40000,\n";
+        let provider = FilePriceProvider::from_csv(csv).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        assert_eq!(
+            provider.historical_price(FiatCurrency::Usd, date).unwrap(),
+            42000.0
+        );
+        assert_eq!(provider.current_price(FiatCurrency::Usd).unwrap(), 44000.0);
+        assert_eq!(provider.current_price(FiatCurrency::Gbp).unwrap(), 33000.0);
+        let before_first = NaiveDate::from_ymd_opt(2023, 12, 31).unwrap();
+        assert!(provider
+            .historical_price(FiatCurrency::Eur, before_first)
+            .is_err());
+
+        let json = r#"[{"date": "2024-01-01", "usd": 42000.0, "eur": null, "gbp": null}]"#;
+        let provider = FilePriceProvider::from_json(json).unwrap();
+        assert!(provider.current_price(FiatCurrency::Eur).is_err());
+
+        assert_eq!(sats_to_fiat(50_000_000, 42000.0), 21000.0);
+        assert_eq!(fiat_to_sats(21000.0, 42000.0), Some(50_000_000));
+        assert_eq!(fiat_to_sats(-1.0, 42000.0), None);
+
+        let csv = "date,usd,eur,gbp\n2024-01-01,42000,38000,33000\n";
+        let provider = FilePriceProvider::from_csv(csv).unwrap();
+        let prices = provider
+            .historical_prices(FiatCurrency::Usd, &[before_first, date])
+            .unwrap();
+        assert_eq!(prices.len(), 1);
+        assert_eq!(prices[&date], 42000.0);
+        assert!(provider
+            .historical_prices(FiatCurrency::Usd, &[before_first])
+            .is_err());
+
+        let first = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let daily = BTreeMap::from([(first, 42000.0)]);
+        let prices = prices_for_dates(&daily, &[before_first, first, date]);
+        assert_eq!(prices.get(&before_first), None);
+        assert_eq!(prices[&date], 42000.0);
+    }
+}
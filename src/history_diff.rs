@@ -0,0 +1,254 @@
+use bdk::bitcoin::{OutPoint, Txid};
+use bdk::TransactionDetails;
+use serde::{Deserialize, Serialize};
+
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum TransactionState {
+    // Spends an output of a transaction that was replaced or conflicted
+    Conflicted,
+    // Another transaction in the history spends the same inputs
+    Replaced { by: String },
+    // Left the mempool without a visible replacement
+    Dropped,
+    // Was confirmed, then its block left the best chain
+    ReorgedOut,
+}
+
+impl TransactionState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TransactionState::Conflicted => "Conflicted",
+            TransactionState::Replaced { .. } => "Replaced",
+            TransactionState::Dropped => "Dropped",
+            TransactionState::ReorgedOut => "Reorged Out",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryDiff {
+    pub states: HashMap<String, TransactionState>,
+    // Transactions no longer returned by the backend, kept so they stay visible
+    pub removed: Vec<TransactionDetails>,
+    pub lost_confirmations: Vec<Txid>,
+}
+
+// Removed transactions stay listed until the backend returns them again
+pub fn diff_history(
+    old_transactions: &[TransactionDetails],
+    old_removed: &[TransactionDetails],
+    old_states: &HashMap<String, TransactionState>,
+    new_transactions: &[TransactionDetails],
+) -> HistoryDiff {
+    let new_by_txid: HashMap<Txid, &TransactionDetails> = new_transactions
+        .iter()
+        .map(|transaction_details| (transaction_details.txid, transaction_details))
+        .collect();
+    let mut spenders: HashMap<OutPoint, Txid> = HashMap::new();
+    for transaction_details in new_transactions.iter() {
+        for input in transaction_details
+            .transaction
+            .iter()
+            .flat_map(|tx| tx.input.iter())
+        {
+            spenders.insert(input.previous_output, transaction_details.txid);
+        }
+    }
+    let was_reorged_out =
+        |txid: &Txid| old_states.get(&txid.to_string()) == Some(&TransactionState::ReorgedOut);
+
+    let mut states = HashMap::new();
+    let mut removed = Vec::new();
+    let mut lost_confirmations = Vec::new();
+    for old in old_transactions.iter() {
+        let was_confirmed = old.confirmation_time.is_some();
+        match new_by_txid.get(&old.txid) {
+            Some(new) if new.confirmation_time.is_none() => {
+                if was_confirmed {
+                    lost_confirmations.push(old.txid);
+                }
+                if was_confirmed || was_reorged_out(&old.txid) {
+                    states.insert(old.txid.to_string(), TransactionState::ReorgedOut);
+                }
+            }
+            Some(_) => {}
+            None => {
+                if was_confirmed {
+                    lost_confirmations.push(old.txid);
+                }
+                removed.push(old.clone());
+            }
+        }
+    }
+    for old in old_removed.iter() {
+        let listed = removed
+            .iter()
+            .any(|transaction_details| transaction_details.txid == old.txid);
+        if !new_by_txid.contains_key(&old.txid) && !listed {
+            removed.push(old.clone());
+        }
+    }
+
+    for transaction_details in removed.iter() {
+        let replaced_by = transaction_details
+            .transaction
+            .iter()
+            .flat_map(|tx| tx.input.iter())
+            .find_map(|input| spenders.get(&input.previous_output));
+        let state = match replaced_by {
+            Some(by) => TransactionState::Replaced { by: by.to_string() },
+            None if transaction_details.confirmation_time.is_some()
+                || was_reorged_out(&transaction_details.txid) =>
+            {
+                TransactionState::ReorgedOut
+            }
+            None => TransactionState::Dropped,
+        };
+        states.insert(transaction_details.txid.to_string(), state);
+    }
+
+    // Children of invalidated transactions can never confirm, however deep the chain
+    let mut invalidated: HashSet<Txid> = removed
+        .iter()
+        .filter(|transaction_details| {
+            matches!(
+                states[&transaction_details.txid.to_string()],
+                TransactionState::Replaced { .. }
+            )
+        })
+        .map(|transaction_details| transaction_details.txid)
+        .collect();
+    loop {
+        let conflicted: Vec<Txid> = removed
+            .iter()
+            .filter(|transaction_details| !invalidated.contains(&transaction_details.txid))
+            .filter(|transaction_details| {
+                transaction_details
+                    .transaction
+                    .iter()
+                    .flat_map(|tx| tx.input.iter())
+                    .any(|input| invalidated.contains(&input.previous_output.txid))
+            })
+            .map(|transaction_details| transaction_details.txid)
+            .collect();
+        if conflicted.is_empty() {
+            break;
+        }
+        for txid in conflicted {
+            states.insert(txid.to_string(), TransactionState::Conflicted);
+            invalidated.insert(txid);
+        }
+    }
+
+    return HistoryDiff {
+        states,
+        removed,
+        lost_confirmations,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use bdk::bitcoin::absolute::LockTime;
+    use bdk::bitcoin::hashes::Hash;
+    use bdk::bitcoin::{OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid, Witness};
+    use bdk::{BlockTime, TransactionDetails};
+
+    use std::collections::HashMap;
+
+    use crate::history_diff::{diff_history, TransactionState};
+
+    fn transaction(
+        previous_output: OutPoint,
+        value: u64,
+        height: Option<u32>,
+    ) -> TransactionDetails {
+        let transaction = Transaction {
+            version: 2,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output,
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value,
+                script_pubkey: ScriptBuf::new(),
+            }],
+        };
+        return TransactionDetails {
+            txid: transaction.txid(),
+            transaction: Some(transaction),
+            received: value,
+            sent: 0,
+            fee: None,
+            confirmation_time: height.map(|height| BlockTime {
+                height,
+                timestamp: 0,
+            }),
+        };
+    }
+
+    #[test]
+    fn test_diff_history() {
+        let funding = OutPoint::new(Txid::all_zeros(), 0);
+        let confirmed = transaction(OutPoint::new(Txid::all_zeros(), 1), 1_000, Some(10));
+        let reorged = transaction(OutPoint::new(Txid::all_zeros(), 2), 2_000, Some(11));
+        let original = transaction(funding, 5_000, None);
+        let child = transaction(OutPoint::new(original.txid, 0), 4_000, None);
+        let dropped = transaction(OutPoint::new(Txid::all_zeros(), 3), 3_000, None);
+        let replacement = transaction(funding, 4_500, None);
+        let mut reorged_unconfirmed = reorged.clone();
+        reorged_unconfirmed.confirmation_time = None;
+
+        let old = vec![
+            confirmed.clone(),
+            reorged.clone(),
+            original.clone(),
+            child.clone(),
+            dropped.clone(),
+        ];
+        let new = vec![
+            confirmed.clone(),
+            reorged_unconfirmed.clone(),
+            replacement.clone(),
+        ];
+        let diff = diff_history(&old, &[], &HashMap::new(), &new);
+        assert_eq!(diff.lost_confirmations, vec![reorged.txid]);
+        assert_eq!(diff.removed.len(), 3);
+        assert_eq!(diff.states.get(&confirmed.txid.to_string()), None);
+        assert_eq!(
+            diff.states[&reorged.txid.to_string()],
+            TransactionState::ReorgedOut
+        );
+        assert_eq!(
+            diff.states[&original.txid.to_string()],
+            TransactionState::Replaced {
+                by: replacement.txid.to_string()
+            }
+        );
+        assert_eq!(
+            diff.states[&child.txid.to_string()],
+            TransactionState::Conflicted
+        );
+        assert_eq!(
+            diff.states[&dropped.txid.to_string()],
+            TransactionState::Dropped
+        );
+
+        // Removed transactions persist across syncs, and clear once they come back
+        let mut newer = new.clone();
+        newer.push(dropped.clone());
+        let diff = diff_history(&new, &diff.removed, &diff.states, &newer);
+        assert_eq!(diff.removed.len(), 2);
+        assert_eq!(diff.states.get(&dropped.txid.to_string()), None);
+        assert_eq!(
+            diff.states[&reorged.txid.to_string()],
+            TransactionState::ReorgedOut
+        );
+        assert!(diff.lost_confirmations.is_empty());
+    }
+}
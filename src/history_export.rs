@@ -0,0 +1,291 @@
+use chrono::{NaiveDate, TimeZone, Utc};
+use serde::Serialize;
+
+use crate::fiat::timestamp_date;
+use crate::units::format_decimal_amount;
+
+const BTC_DECIMALS: usize = 8;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    Koinly,
+    CoinTracking,
+}
+
+pub const EXPORT_FORMATS: [ExportFormat; 4] = [
+    ExportFormat::Csv,
+    ExportFormat::Json,
+    ExportFormat::Koinly,
+    ExportFormat::CoinTracking,
+];
+
+impl ExportFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Json => "JSON",
+            ExportFormat::Koinly => "Koinly CSV",
+            ExportFormat::CoinTracking => "CoinTracking CSV",
+        }
+    }
+
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "transactions.csv",
+            ExportFormat::Json => "transactions.json",
+            ExportFormat::Koinly => "transactions_koinly.csv",
+            ExportFormat::CoinTracking => "transactions_cointracking.csv",
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ExportRow {
+    pub wallet: String,
+    pub txid: String,
+    pub timestamp: Option<u64>,
+    pub direction: String,
+    // Outgoing amounts exclude the fee, which is reported separately
+    pub amount_sats: u64,
+    pub fee_sats: u64,
+    pub counterparty: String,
+    pub label: String,
+    pub confirmations: Option<u32>,
+}
+
+impl ExportRow {
+    fn is_outgoing(&self) -> bool {
+        return self.direction == "Sent";
+    }
+
+    fn date_string(&self, format: &str) -> String {
+        return self
+            .timestamp
+            .and_then(|timestamp| Utc.timestamp_opt(timestamp as i64, 0).single())
+            .map(|date_time| date_time.format(format).to_string())
+            .unwrap_or_default();
+    }
+}
+
+pub fn in_date_range(
+    timestamp: Option<u64>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> bool {
+    if from.is_none() && to.is_none() {
+        return true;
+    }
+    // Unconfirmed transactions have no date to filter on
+    let Some(date) = timestamp.and_then(timestamp_date) else {
+        return false;
+    };
+    return from.map_or(true, |from| date >= from) && to.map_or(true, |to| date <= to);
+}
+
+fn btc_amount(sats: u64) -> String {
+    return format_decimal_amount(sats, BTC_DECIMALS, '.');
+}
+
+fn export_csv(rows: &[ExportRow]) -> Result<String, anyhow::Error> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record([
+        "Wallet",
+        "Txid",
+        "Date",
+        "Direction",
+        "Amount (sats)",
+        "Fee (sats)",
+        "Counterparty",
+        "Label",
+        "Confirmations",
+    ])?;
+    for row in rows.iter() {
+        writer.write_record([
+            row.wallet.clone(),
+            row.txid.clone(),
+            row.date_string("%Y-%m-%dT%H:%M:%SZ"),
+            row.direction.clone(),
+            row.amount_sats.to_string(),
+            row.fee_sats.to_string(),
+            row.counterparty.clone(),
+            row.label.clone(),
+            row.confirmations
+                .map(|confirmations| confirmations.to_string())
+                .unwrap_or_default(),
+        ])?;
+    }
+    return Ok(String::from_utf8(writer.into_inner()?)?);
+}
+
+fn export_koinly(rows: &[ExportRow]) -> Result<String, anyhow::Error> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record([
+        "Date",
+        "Sent Amount",
+        "Sent Currency",
+        "Received Amount",
+        "Received Currency",
+        "Fee Amount",
+        "Fee Currency",
+        "Net Worth Amount",
+        "Net Worth Currency",
+        "Label",
+        "Description",
+        "TxHash",
+    ])?;
+    for row in rows.iter() {
+        let (sent, received) = match row.is_outgoing() {
+            true => (btc_amount(row.amount_sats), String::new()),
+            false => (String::new(), btc_amount(row.amount_sats)),
+        };
+        let (fee, fee_currency) = match row.is_outgoing() && row.fee_sats > 0 {
+            true => (btc_amount(row.fee_sats), "BTC"),
+            false => (String::new(), ""),
+        };
+        let sent_currency = if sent.is_empty() { "" } else { "BTC" };
+        let received_currency = if received.is_empty() { "" } else { "BTC" };
+        writer.write_record([
+            row.date_string("%Y-%m-%d %H:%M UTC").as_str(),
+            sent.as_str(),
+            sent_currency,
+            received.as_str(),
+            received_currency,
+            fee.as_str(),
+            fee_currency,
+            "",
+            "",
+            "",
+            row.label.as_str(),
+            row.txid.as_str(),
+        ])?;
+    }
+    return Ok(String::from_utf8(writer.into_inner()?)?);
+}
+
+fn export_cointracking(rows: &[ExportRow]) -> Result<String, anyhow::Error> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record([
+        "Type",
+        "Buy Amount",
+        "Buy Currency",
+        "Sell Amount",
+        "Sell Currency",
+        "Fee",
+        "Fee Currency",
+        "Exchange",
+        "Trade-Group",
+        "Comment",
+        "Date",
+        "Tx-ID",
+    ])?;
+    for row in rows.iter() {
+        let (transaction_type, buy, buy_currency, sell, sell_currency) = match row.is_outgoing() {
+            true => (
+                "Withdrawal",
+                String::new(),
+                "",
+                btc_amount(row.amount_sats),
+                "BTC",
+            ),
+            false => (
+                "Deposit",
+                btc_amount(row.amount_sats),
+                "BTC",
+                String::new(),
+                "",
+            ),
+        };
+        let (fee, fee_currency) = match row.is_outgoing() && row.fee_sats > 0 {
+            true => (btc_amount(row.fee_sats), "BTC"),
+            false => (String::new(), ""),
+        };
+        writer.write_record([
+            transaction_type,
+            buy.as_str(),
+            buy_currency,
+            sell.as_str(),
+            sell_currency,
+            fee.as_str(),
+            fee_currency,
+            row.wallet.as_str(),
+            "",
+            row.label.as_str(),
+            row.date_string("%Y-%m-%d %H:%M:%S").as_str(),
+            row.txid.as_str(),
+        ])?;
+    }
+    return Ok(String::from_utf8(writer.into_inner()?)?);
+}
+
+pub fn export_history(rows: &[ExportRow], format: ExportFormat) -> Result<String, anyhow::Error> {
+    match format {
+        ExportFormat::Csv => export_csv(rows),
+        ExportFormat::Json => Ok(serde_json::to_string_pretty(rows)?),
+        ExportFormat::Koinly => export_koinly(rows),
+        ExportFormat::CoinTracking => export_cointracking(rows),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::history_export::{export_history, in_date_range, ExportFormat, ExportRow};
+
+    #[test]
+    fn test_export_history() {
+        let rows = vec![
+            ExportRow {
+                wallet: "Savings".to_string(),
+                txid: "aa".repeat(32),
+                timestamp: Some(1_704_067_200),
+                direction: "Received".to_string(),
+                amount_sats: 150_000,
+                fee_sats: 0,
+                counterparty: "Alice".to_string(),
+                label: "Salary".to_string(),
+                confirmations: Some(6),
+            },
+            ExportRow {
+                wallet: "Savings".to_string(),
+                txid: "bb".repeat(32),
+                timestamp: None,
+                direction: "Sent".to_string(),
+                amount_sats: 50_000,
+                fee_sats: 141,
+                counterparty: "Bob".to_string(),
+                label: String::new(),
+                confirmations: None,
+            },
+        ];
+
+        let csv = export_history(&rows, ExportFormat::Csv).unwrap();
+        assert_eq!(csv.lines().count(), 3);
+        assert!(csv.contains("2024-01-01T00:00:00Z,Received,150000,0,Alice,Salary,6"));
+
+        let koinly = export_history(&rows, ExportFormat::Koinly).unwrap();
+        assert!(koinly.contains("2024-01-01 00:00 UTC,,,0.0015,BTC"));
+        assert!(koinly.contains(",0.0005,BTC,,,0.00000141,BTC,"));
+
+        let cointracking = export_history(&rows, ExportFormat::CoinTracking).unwrap();
+        assert!(cointracking.contains("Deposit,0.0015,BTC"));
+        assert!(cointracking.contains("Withdrawal,,,0.0005,BTC"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&export_history(&rows, ExportFormat::Json).unwrap()).unwrap();
+        assert_eq!(json[1]["fee_sats"], 141);
+
+        let from = NaiveDate::from_ymd_opt(2024, 1, 1);
+        let to = NaiveDate::from_ymd_opt(2024, 1, 31);
+        assert!(in_date_range(rows[0].timestamp, from, to));
+        assert!(!in_date_range(
+            rows[0].timestamp,
+            NaiveDate::from_ymd_opt(2024, 1, 2),
+            None
+        ));
+        assert!(!in_date_range(rows[1].timestamp, from, to));
+        assert!(in_date_range(rows[1].timestamp, None, None));
+    }
+}
@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelType {
+    Tx,
+    Addr,
+    Pubkey,
+    Input,
+    Output,
+    Xpub,
+}
+
+// A BIP329 label record, stored as-is so exports round trip
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Label {
+    #[serde(rename = "type")]
+    pub label_type: LabelType,
+    #[serde(rename = "ref")]
+    pub reference: String,
+    pub label: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spendable: Option<bool>,
+}
+
+pub fn get_label<'a>(
+    labels: &'a [Label],
+    label_type: LabelType,
+    reference: &str,
+) -> Option<&'a str> {
+    return labels
+        .iter()
+        .find(|label| label.label_type == label_type && label.reference == reference)
+        .map(|label| label.label.as_str());
+}
+
+pub fn set_label(labels: &mut Vec<Label>, label_type: LabelType, reference: &str, text: &str) {
+    let text = text.trim();
+    let index = labels
+        .iter()
+        .position(|label| label.label_type == label_type && label.reference == reference);
+    match (index, text.is_empty()) {
+        (Some(index), true) => {
+            labels.remove(index);
+        }
+        (Some(index), false) => labels[index].label = text.to_string(),
+        (None, false) => labels.push(Label {
+            label_type,
+            reference: reference.to_string(),
+            label: text.to_string(),
+            origin: None,
+            spendable: None,
+        }),
+        (None, true) => {}
+    }
+}
+
+pub fn export_bip329(labels: &[Label]) -> Result<String, anyhow::Error> {
+    let mut lines = Vec::new();
+    for label in labels.iter() {
+        lines.push(serde_json::to_string(label)?);
+    }
+    return Ok(lines.join("\n") + "\n");
+}
+
+pub fn import_bip329(contents: &str) -> Result<Vec<Label>, anyhow::Error> {
+    let mut labels = Vec::new();
+    for (line_number, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let value: serde_json::Value = serde_json::from_str(line)
+            .map_err(|error| anyhow::anyhow!("Line {}: {}", line_number + 1, error))?;
+        // Unknown record types are skipped, as BIP329 allows for future types
+        if let Ok(label) = serde_json::from_value::<Label>(value) {
+            labels.push(label);
+        }
+    }
+    return Ok(labels);
+}
+
+pub fn merge_labels(labels: &mut Vec<Label>, imported: Vec<Label>) -> usize {
+    let mut count = 0;
+    for imported_label in imported {
+        let existing = labels.iter_mut().find(|label| {
+            label.label_type == imported_label.label_type
+                && label.reference == imported_label.reference
+        });
+        match existing {
+            Some(label) => *label = imported_label,
+            None => labels.push(imported_label),
+        }
+        count += 1;
+    }
+    return count;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::labels::{
+        export_bip329, get_label, import_bip329, merge_labels, set_label, LabelType,
+    };
+
+    const BIP329_EXAMPLE: &str = r#"{ "type": "tx", "ref": "f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd", "label": "Transaction", "origin": "wpkh([d34db33f/84'/0'/0'])" }
+{ "type": "addr", "ref": "bc1q34aq5drpuwy3wgl9lhup9892qp6svr8ldzyy7c", "label": "Address" }
+{ "type": "output", "ref": "f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd:0", "label": "Output", "spendable": false }
+{ "type": "somethingnew", "ref": "abc", "label": "Future" }
+"#;
+
+    #[test]
+    fn test_bip329_labels() {
+        let imported = import_bip329(BIP329_EXAMPLE).unwrap();
+        assert_eq!(imported.len(), 3);
+        assert_eq!(imported[2].spendable, Some(false));
+
+        let mut labels = Vec::new();
+        set_label(
+            &mut labels,
+            LabelType::Addr,
+            "bc1q34aq5drpuwy3wgl9lhup9892qp6svr8ldzyy7c",
+            "Old",
+        );
+        assert_eq!(merge_labels(&mut labels, imported), 3);
+        assert_eq!(labels.len(), 3);
+        assert_eq!(
+            get_label(
+                &labels,
+                LabelType::Addr,
+                "bc1q34aq5drpuwy3wgl9lhup9892qp6svr8ldzyy7c"
+            ),
+            Some("Address")
+        );
+
+        let exported = export_bip329(&labels).unwrap();
+        assert_eq!(import_bip329(&exported).unwrap(), labels);
+        assert!(exported.contains(r#""origin":"wpkh([d34db33f/84'/0'/0'])""#));
+
+        set_label(
+            &mut labels,
+            LabelType::Addr,
+            "bc1q34aq5drpuwy3wgl9lhup9892qp6svr8ldzyy7c",
+            " ",
+        );
+        assert_eq!(labels.len(), 2);
+        assert!(import_bip329("not json").is_err());
+    }
+}
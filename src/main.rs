@@ -3,7 +3,25 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
 mod app;
+mod backend;
+mod bip21;
+mod bip85;
 mod bitcoin_wallet;
+mod cost_basis;
+mod fiat;
+mod history_diff;
+mod history_export;
+mod labels;
+mod multisig;
+mod payment_request;
+mod policy;
+mod proxy;
+mod sweep;
+mod sync_scheduler;
+#[cfg(test)]
+mod test_support;
+mod transaction_detail;
+mod units;
 mod wallet_file_manager;
 use app::MyApp;
 use eframe::egui;
@@ -0,0 +1,299 @@
+use bdk::bitcoin::bip32::ExtendedPrivKey;
+use bdk::bitcoin::psbt::PartiallySignedTransaction;
+use bdk::bitcoin::secp256k1::Secp256k1;
+use bdk::bitcoin::Network;
+use bdk::bitcoin::{Address, Transaction};
+use bdk::database::MemoryDatabase;
+use bdk::descriptor::calc_checksum;
+use bdk::miniscript::descriptor::{
+    Descriptor, DescriptorPublicKey, DescriptorSecretKey, SortedMultiVec, WshInner,
+};
+use bdk::miniscript::Segwitv0;
+use bdk::wallet::{AddressIndex, Wallet};
+use bdk::SignOptions;
+
+use std::str::FromStr;
+
+use crate::bitcoin_wallet::{checksummed_descriptor, generate_wallet_from_descriptors};
+
+pub const MULTISIG_DERIVATION_PATH: &str = "48'/1'/0'/2'";
+const MAX_MULTISIG_COSIGNERS: usize = 15;
+const COLDCARD_MAX_NAME_LENGTH: usize = 20;
+
+pub struct MultisigStatus {
+    pub signatures: usize,
+    pub threshold: usize,
+    pub finalized: bool,
+}
+
+pub fn local_cosigner_key(priv_key: &str) -> Result<String, anyhow::Error> {
+    let xpriv = ExtendedPrivKey::from_str(priv_key)?;
+    return Ok(format!("{}/{}", xpriv, MULTISIG_DERIVATION_PATH));
+}
+
+pub fn public_cosigner_key(cosigner_key: &str) -> Result<String, anyhow::Error> {
+    if let Ok(secret_key) = DescriptorSecretKey::from_str(cosigner_key) {
+        return Ok(secret_key.to_public(&Secp256k1::new())?.to_string());
+    }
+    let public_key = DescriptorPublicKey::from_str(cosigner_key)?;
+    return Ok(public_key.to_string());
+}
+
+fn is_extended_cosigner_key(cosigner_key: &str) -> bool {
+    match DescriptorSecretKey::from_str(cosigner_key) {
+        Ok(secret_key) => matches!(secret_key, DescriptorSecretKey::XPrv(_)),
+        Err(_) => matches!(
+            DescriptorPublicKey::from_str(cosigner_key),
+            Ok(DescriptorPublicKey::XPub(_))
+        ),
+    }
+}
+
+pub fn generate_multisig_descriptors(
+    threshold: usize,
+    cosigner_keys: &[String],
+) -> Result<(String, String), anyhow::Error> {
+    if cosigner_keys.len() < 2 || cosigner_keys.len() > MAX_MULTISIG_COSIGNERS {
+        anyhow::bail!(
+            "A multisig wallet needs between 2 and {} cosigners",
+            MAX_MULTISIG_COSIGNERS
+        );
+    }
+    if threshold == 0 || threshold > cosigner_keys.len() {
+        anyhow::bail!("Threshold must be between 1 and the number of cosigners");
+    }
+    for cosigner_key in cosigner_keys.iter() {
+        if cosigner_key.contains('*') {
+            anyhow::bail!("Cosigner keys must not include a wildcard derivation");
+        }
+        if !is_extended_cosigner_key(cosigner_key.trim()) {
+            anyhow::bail!("Cosigner keys must be extended keys");
+        }
+    }
+
+    let descriptor_for_chain = |chain: u32| {
+        let keys: Vec<String> = cosigner_keys
+            .iter()
+            .map(|cosigner_key| format!("{}/{}/*", cosigner_key.trim(), chain))
+            .collect();
+        checksummed_descriptor(&format!(
+            "wsh(sortedmulti({},{}))",
+            threshold,
+            keys.join(",")
+        ))
+    };
+    let external = descriptor_for_chain(0)?;
+    let internal = descriptor_for_chain(1)?;
+    generate_wallet_from_descriptors(&external, &internal)?;
+    return Ok((external, internal));
+}
+
+fn sorted_multi(
+    descriptor: &str,
+) -> Result<SortedMultiVec<DescriptorPublicKey, Segwitv0>, anyhow::Error> {
+    let (descriptor, _) = Descriptor::parse_descriptor(&Secp256k1::new(), descriptor)?;
+    match descriptor {
+        Descriptor::Wsh(wsh) => match wsh.as_inner() {
+            WshInner::SortedMulti(sorted_multi) => Ok(sorted_multi.clone()),
+            _ => anyhow::bail!("Descriptor is not a sortedmulti descriptor"),
+        },
+        _ => anyhow::bail!("Descriptor is not a wsh descriptor"),
+    }
+}
+
+pub fn multisig_policy(descriptor: &str) -> Option<(usize, usize)> {
+    let sorted_multi = sorted_multi(descriptor).ok()?;
+    return Some((sorted_multi.k, sorted_multi.pks.len()));
+}
+
+fn first_address(external: &str, internal: &str) -> Result<Address, anyhow::Error> {
+    let wallet = generate_wallet_from_descriptors(external, internal)?;
+    return Ok(wallet.get_address(AddressIndex::Peek(0))?.address);
+}
+
+pub fn export_bsms(external: &str, internal: &str) -> Result<String, anyhow::Error> {
+    let public_external = Descriptor::parse_descriptor(&Secp256k1::new(), external)?
+        .0
+        .to_string();
+    let (descriptor_body, _) = public_external
+        .split_once('#')
+        .unwrap_or((&public_external, ""));
+    let descriptor_template = descriptor_body.replace("/0/*", "/**");
+    let checksum = calc_checksum(&descriptor_template)?;
+    let address = first_address(external, internal)?;
+    return Ok(format!(
+        "BSMS 1.0\n{}#{}\n/0/*,/1/*\n{}\n",
+        descriptor_template, checksum, address
+    ));
+}
+
+pub fn export_coldcard(wallet_name: &str, external: &str) -> Result<String, anyhow::Error> {
+    let sorted_multi = sorted_multi(external)?;
+    let name: String = wallet_name
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == ' ' || *c == '-' || *c == '_')
+        .take(COLDCARD_MAX_NAME_LENGTH)
+        .collect();
+    let mut lines = vec![
+        "# Coldcard Multisig setup file (exported from Rust Bitcoin Wallet)".to_string(),
+        "#".to_string(),
+        format!("Name: {}", name),
+        format!("Policy: {} of {}", sorted_multi.k, sorted_multi.pks.len()),
+        "Format: P2WSH".to_string(),
+    ];
+    for public_key in sorted_multi.pks.iter() {
+        let DescriptorPublicKey::XPub(xpub) = public_key else {
+            anyhow::bail!("Coldcard export requires extended public keys");
+        };
+        let (fingerprint, derivation_path) = match &xpub.origin {
+            Some((fingerprint, derivation_path)) => (*fingerprint, derivation_path.to_string()),
+            None => (xpub.xkey.fingerprint(), "m".to_string()),
+        };
+        lines.push(String::new());
+        lines.push(format!("Derivation: {}", derivation_path));
+        lines.push(format!(
+            "{}: {}",
+            fingerprint.to_string().to_uppercase(),
+            xpub.xkey
+        ));
+    }
+    return Ok(lines.join("\n") + "\n");
+}
+
+pub fn multisig_status(psbt: &PartiallySignedTransaction, threshold: usize) -> MultisigStatus {
+    let finalized = psbt
+        .inputs
+        .iter()
+        .all(|input| input.final_script_witness.is_some());
+    let signatures = if finalized {
+        threshold
+    } else {
+        psbt.inputs
+            .iter()
+            .map(|input| input.partial_sigs.len())
+            .min()
+            .unwrap_or(0)
+    };
+    return MultisigStatus {
+        signatures,
+        threshold,
+        finalized,
+    };
+}
+
+pub fn extract_finalized_tx(
+    psbt: &PartiallySignedTransaction,
+) -> Result<Transaction, anyhow::Error> {
+    let unfinalized = psbt
+        .inputs
+        .iter()
+        .filter(|input| input.final_script_witness.is_none() && input.final_script_sig.is_none())
+        .count();
+    if unfinalized > 0 {
+        anyhow::bail!(
+            "{} of {} inputs are not finalized yet",
+            unfinalized,
+            psbt.inputs.len()
+        );
+    }
+    return Ok(psbt.clone().extract_tx());
+}
+
+pub fn make_multisig_psbt(
+    wallet: &Wallet<MemoryDatabase>,
+    recipient_str: &str,
+    amount: u64,
+) -> Result<PartiallySignedTransaction, anyhow::Error> {
+    let recipient_address = Address::from_str(recipient_str)?.require_network(Network::Testnet)?;
+    let mut tx_builder = wallet.build_tx();
+    tx_builder
+        .add_recipient(recipient_address.script_pubkey(), amount)
+        .enable_rbf();
+    let (mut psbt, _tx_details) = tx_builder.finish()?;
+    wallet.sign(&mut psbt, SignOptions::default())?;
+    return Ok(psbt);
+}
+
+pub fn sign_multisig_psbt(
+    wallet: &Wallet<MemoryDatabase>,
+    psbt: &str,
+) -> Result<PartiallySignedTransaction, anyhow::Error> {
+    let mut psbt = PartiallySignedTransaction::from_str(psbt.trim())?;
+    wallet.sign(&mut psbt, SignOptions::default())?;
+    return Ok(psbt);
+}
+
+pub fn combine_multisig_psbts(
+    wallet: &Wallet<MemoryDatabase>,
+    psbt: &str,
+    other_psbt: &str,
+) -> Result<PartiallySignedTransaction, anyhow::Error> {
+    let mut psbt = PartiallySignedTransaction::from_str(psbt.trim())?;
+    let other_psbt = PartiallySignedTransaction::from_str(other_psbt.trim())?;
+    psbt.combine(other_psbt)?;
+    wallet.finalize_psbt(&mut psbt, SignOptions::default())?;
+    return Ok(psbt);
+}
+
+#[cfg(test)]
+mod tests {
+    use bdk::bitcoin::absolute::LockTime;
+    use bdk::bitcoin::psbt::PartiallySignedTransaction;
+    use bdk::bitcoin::{Transaction, TxIn};
+
+    use crate::bitcoin_wallet::{generate_xpriv, public_descriptor_string};
+    use crate::multisig::{
+        export_bsms, export_coldcard, extract_finalized_tx, generate_multisig_descriptors,
+        local_cosigner_key, multisig_policy, public_cosigner_key,
+    };
+
+    const MNEMONICS: [&str; 3] = [
+        "limb capital decade way negative task moral empty virus fragile copper elegant",
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+        "legal winner thank year wave sausage worth useful legal winner thank yellow",
+    ];
+
+    #[test]
+    fn test_multisig_descriptors_and_exports() {
+        let local_key =
+            local_cosigner_key(&generate_xpriv(MNEMONICS[0]).unwrap().to_string()).unwrap();
+        let mut cosigner_keys = vec![local_key];
+        for mnemonic in MNEMONICS[1..].iter() {
+            let priv_key = generate_xpriv(mnemonic).unwrap().to_string();
+            let cosigner_key = local_cosigner_key(&priv_key).unwrap();
+            cosigner_keys.push(public_cosigner_key(&cosigner_key).unwrap());
+        }
+        assert!(cosigner_keys[1].starts_with('[') && cosigner_keys[1].contains("tpub"));
+
+        let (external, internal) = generate_multisig_descriptors(2, &cosigner_keys).unwrap();
+        assert!(external.starts_with("wsh(sortedmulti(2,") && external.contains("tprv"));
+        assert_eq!(multisig_policy(&external), Some((2, 3)));
+        assert_eq!(
+            multisig_policy(&public_descriptor_string(&internal).unwrap()),
+            Some((2, 3))
+        );
+
+        let bsms = export_bsms(&external, &internal).unwrap();
+        let bsms_lines: Vec<&str> = bsms.lines().collect();
+        assert_eq!(bsms_lines[0], "BSMS 1.0");
+        assert!(bsms_lines[1].contains("/**") && !bsms_lines[1].contains("tprv"));
+        assert_eq!(bsms_lines[2], "/0/*,/1/*");
+        assert!(bsms_lines[3].starts_with("tb1q"));
+
+        let coldcard = export_coldcard("Treasury", &external).unwrap();
+        assert!(coldcard.contains("Policy: 2 of 3"));
+        assert_eq!(coldcard.matches("Derivation: m/48'/1'/0'/2'").count(), 3);
+
+        assert!(generate_multisig_descriptors(4, &cosigner_keys).is_err());
+        assert!(generate_multisig_descriptors(1, &cosigner_keys[..1]).is_err());
+
+        let unsigned = Transaction {
+            version: 2,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn::default()],
+            output: vec![],
+        };
+        let psbt = PartiallySignedTransaction::from_unsigned_tx(unsigned).unwrap();
+        assert!(extract_finalized_tx(&psbt).is_err());
+    }
+}
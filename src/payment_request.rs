@@ -0,0 +1,180 @@
+use bdk::bitcoin::{Address, Network};
+use bdk::TransactionDetails;
+use serde::{Deserialize, Serialize};
+
+use std::str::FromStr;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PaymentRequest {
+    pub address: String,
+    pub amount: u64,
+    pub memo: String,
+    pub created_at: i64,
+    pub expires_at: i64,
+    #[serde(default)]
+    pub received: u64,
+    // Unconfirmed payments can still be replaced, so only this part counts as paid
+    #[serde(default)]
+    pub received_confirmed: u64,
+    #[serde(default)]
+    pub txids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PaymentRequestStatus {
+    Pending,
+    PartiallyPaid,
+    PaidUnconfirmed,
+    Paid,
+    Expired,
+}
+
+impl PaymentRequestStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PaymentRequestStatus::Pending => "Pending",
+            PaymentRequestStatus::PartiallyPaid => "Partially Paid",
+            PaymentRequestStatus::PaidUnconfirmed => "Paid (unconfirmed)",
+            PaymentRequestStatus::Paid => "Paid",
+            PaymentRequestStatus::Expired => "Expired",
+        }
+    }
+}
+
+pub fn expiry_timestamp(created_at: i64, expiry_hours: i64) -> Option<i64> {
+    if expiry_hours <= 0 {
+        return None;
+    }
+    return expiry_hours
+        .checked_mul(3600)
+        .and_then(|expiry_seconds| created_at.checked_add(expiry_seconds));
+}
+
+impl PaymentRequest {
+    pub fn status(&self, now: i64) -> PaymentRequestStatus {
+        if self.received_confirmed >= self.amount {
+            return PaymentRequestStatus::Paid;
+        }
+        if self.received >= self.amount {
+            return PaymentRequestStatus::PaidUnconfirmed;
+        }
+        if now >= self.expires_at {
+            return PaymentRequestStatus::Expired;
+        }
+        if self.received > 0 {
+            return PaymentRequestStatus::PartiallyPaid;
+        }
+        return PaymentRequestStatus::Pending;
+    }
+
+    pub fn update_received(&mut self, transactions: &[TransactionDetails]) {
+        let Some(address) = Address::from_str(&self.address)
+            .ok()
+            .and_then(|address| address.require_network(Network::Testnet).ok())
+        else {
+            return;
+        };
+        let script_pubkey = address.script_pubkey();
+        self.received = 0;
+        self.received_confirmed = 0;
+        self.txids.clear();
+        for transaction_details in transactions.iter() {
+            let Some(transaction) = &transaction_details.transaction else {
+                continue;
+            };
+            let received: u64 = transaction
+                .output
+                .iter()
+                .filter(|output| output.script_pubkey == script_pubkey)
+                .map(|output| output.value)
+                .sum();
+            if received > 0 {
+                self.received += received;
+                if transaction_details.confirmation_time.is_some() {
+                    self.received_confirmed += received;
+                }
+                self.txids.push(transaction_details.txid.to_string());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bdk::bitcoin::absolute::LockTime;
+    use bdk::bitcoin::{Address, Transaction, TxOut};
+    use bdk::{BlockTime, TransactionDetails};
+
+    use std::str::FromStr;
+
+    use crate::payment_request::{expiry_timestamp, PaymentRequest, PaymentRequestStatus};
+
+    const ADDRESS: &str = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+
+    fn incoming_transaction(value: u64, lock_time: u32) -> TransactionDetails {
+        let address = Address::from_str(ADDRESS).unwrap().assume_checked();
+        let transaction = Transaction {
+            version: 2,
+            lock_time: LockTime::from_consensus(lock_time),
+            input: Vec::new(),
+            output: vec![TxOut {
+                value,
+                script_pubkey: address.script_pubkey(),
+            }],
+        };
+        TransactionDetails {
+            txid: transaction.txid(),
+            transaction: Some(transaction),
+            received: value,
+            sent: 0,
+            fee: Some(0),
+            confirmation_time: None,
+        }
+    }
+
+    #[test]
+    fn test_payment_request_status() {
+        let mut payment_request = PaymentRequest {
+            address: ADDRESS.to_string(),
+            amount: 10_000,
+            memo: "Invoice 42".to_string(),
+            created_at: 0,
+            expires_at: 3600,
+            received: 0,
+            received_confirmed: 0,
+            txids: Vec::new(),
+        };
+        assert_eq!(payment_request.status(10), PaymentRequestStatus::Pending);
+        assert_eq!(payment_request.status(3600), PaymentRequestStatus::Expired);
+
+        let mut transactions = vec![incoming_transaction(4_000, 0)];
+        payment_request.update_received(&transactions);
+        assert_eq!(
+            payment_request.status(10),
+            PaymentRequestStatus::PartiallyPaid
+        );
+
+        transactions.push(incoming_transaction(6_000, 1));
+        payment_request.update_received(&transactions);
+        assert_eq!(payment_request.received, 10_000);
+        assert_eq!(payment_request.txids.len(), 2);
+        assert_eq!(
+            payment_request.status(7200),
+            PaymentRequestStatus::PaidUnconfirmed
+        );
+
+        for transaction_details in transactions.iter_mut() {
+            transaction_details.confirmation_time = Some(BlockTime {
+                height: 100,
+                timestamp: 0,
+            });
+        }
+        payment_request.update_received(&transactions);
+        assert_eq!(payment_request.received_confirmed, 10_000);
+        assert_eq!(payment_request.status(7200), PaymentRequestStatus::Paid);
+
+        assert_eq!(expiry_timestamp(100, 2), Some(7300));
+        assert_eq!(expiry_timestamp(100, 0), None);
+        assert_eq!(expiry_timestamp(100, i64::MAX / 1000), None);
+    }
+}
@@ -0,0 +1,282 @@
+use bdk::bitcoin::psbt::PartiallySignedTransaction;
+use bdk::bitcoin::{Address, Network};
+use bdk::database::MemoryDatabase;
+use bdk::descriptor::policy::{PkOrF, Policy, SatisfiableItem};
+use bdk::miniscript::descriptor::Descriptor;
+use bdk::miniscript::policy::Concrete;
+use bdk::miniscript::{Miniscript, Segwitv0, Translator};
+use bdk::wallet::Wallet;
+use bdk::{KeychainKind, SignOptions};
+
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+use crate::bitcoin_wallet::{checksummed_descriptor, generate_wallet_from_descriptors};
+
+struct PolicyKeyTranslator<'a> {
+    policy_keys: &'a HashMap<String, String>,
+    chain: u32,
+}
+
+impl<'a> Translator<String, String, anyhow::Error> for PolicyKeyTranslator<'a> {
+    fn pk(&mut self, key_name: &String) -> Result<String, anyhow::Error> {
+        let Some(key) = self.policy_keys.get(key_name) else {
+            anyhow::bail!("No key named {} has been added", key_name);
+        };
+        return Ok(format!("{}/{}/*", key.trim(), self.chain));
+    }
+
+    fn sha256(&mut self, sha256: &String) -> Result<String, anyhow::Error> {
+        return Ok(sha256.clone());
+    }
+
+    fn hash256(&mut self, hash256: &String) -> Result<String, anyhow::Error> {
+        return Ok(hash256.clone());
+    }
+
+    fn ripemd160(&mut self, ripemd160: &String) -> Result<String, anyhow::Error> {
+        return Ok(ripemd160.clone());
+    }
+
+    fn hash160(&mut self, hash160: &String) -> Result<String, anyhow::Error> {
+        return Ok(hash160.clone());
+    }
+}
+
+pub fn compile_policy(
+    policy: &str,
+    policy_keys: &[(String, String)],
+) -> Result<(String, String), anyhow::Error> {
+    let concrete_policy = Concrete::<String>::from_str(policy.trim())?;
+    let miniscript: Miniscript<String, Segwitv0> = concrete_policy.compile()?;
+    let descriptor = Descriptor::new_wsh(miniscript)?;
+    let policy_keys: HashMap<String, String> = policy_keys.iter().cloned().collect();
+
+    let descriptor_for_chain = |chain: u32| -> Result<String, anyhow::Error> {
+        let mut translator = PolicyKeyTranslator {
+            policy_keys: &policy_keys,
+            chain,
+        };
+        let translated_descriptor = descriptor
+            .translate_pk(&mut translator)
+            .map_err(|error| anyhow::anyhow!("{:?}", error))?;
+        checksummed_descriptor(&translated_descriptor.to_string())
+    };
+    let external = descriptor_for_chain(0)?;
+    let internal = descriptor_for_chain(1)?;
+    generate_wallet_from_descriptors(&external, &internal)?;
+    return Ok((external, internal));
+}
+
+fn describe_key(key: &PkOrF) -> String {
+    match key {
+        PkOrF::Fingerprint(fingerprint) => format!("key {}", fingerprint),
+        PkOrF::Pubkey(public_key) => format!("key {}", public_key),
+        PkOrF::XOnlyPubkey(public_key) => format!("key {}", public_key),
+    }
+}
+
+fn describe_policy(item: &SatisfiableItem) -> String {
+    match item {
+        SatisfiableItem::EcdsaSignature(key) | SatisfiableItem::SchnorrSignature(key) => {
+            format!("signature from {}", describe_key(key))
+        }
+        SatisfiableItem::Multisig { keys, threshold } => {
+            let keys: Vec<String> = keys.iter().map(describe_key).collect();
+            format!(
+                "{} of {} signatures from {}",
+                threshold,
+                keys.len(),
+                keys.join(", ")
+            )
+        }
+        SatisfiableItem::RelativeTimelock { value } if value.is_time_locked() => {
+            format!("relative timelock of {} seconds", (value.0 & 0xffff) * 512)
+        }
+        SatisfiableItem::RelativeTimelock { value } => {
+            format!("relative timelock of {} blocks", value.0 & 0xffff)
+        }
+        SatisfiableItem::AbsoluteTimelock { value } => format!("absolute timelock {}", value),
+        SatisfiableItem::Thresh { items, threshold } => {
+            let items: Vec<String> = items
+                .iter()
+                .map(|item| describe_policy(&item.item))
+                .collect();
+            if *threshold == items.len() {
+                format!("({})", items.join(" and "))
+            } else if *threshold == 1 {
+                format!("({})", items.join(" or "))
+            } else {
+                format!("{} of ({})", threshold, items.join(", "))
+            }
+        }
+        _ => "hash preimage".to_string(),
+    }
+}
+
+fn combinations(count: usize, threshold: usize) -> Vec<Vec<usize>> {
+    if threshold == 0 {
+        return vec![Vec::new()];
+    }
+    if threshold > count {
+        return Vec::new();
+    }
+    let mut chosen = combinations(count - 1, threshold);
+    for mut combination in combinations(count - 1, threshold - 1) {
+        combination.push(count - 1);
+        chosen.push(combination);
+    }
+    // Sorted so that for an or the path index is the item index
+    chosen.sort();
+    return chosen;
+}
+
+// Every way of satisfying the policy, with the items chosen at each threshold along the way.
+// bdk needs a choice for nested thresholds too, not just the top level one
+fn spending_paths(policy: &Policy) -> Vec<(Vec<String>, BTreeMap<String, Vec<usize>>)> {
+    let SatisfiableItem::Thresh { items, threshold } = &policy.item else {
+        return vec![(vec![describe_policy(&policy.item)], BTreeMap::new())];
+    };
+    let mut paths = Vec::new();
+    for chosen in combinations(items.len(), *threshold) {
+        let mut partial_paths = vec![(
+            Vec::new(),
+            BTreeMap::from([(policy.id.clone(), chosen.clone())]),
+        )];
+        for index in chosen.iter() {
+            let item_paths = spending_paths(&items[*index]);
+            partial_paths = partial_paths
+                .iter()
+                .flat_map(|(descriptions, path)| {
+                    item_paths.iter().map(|(item_descriptions, item_path)| {
+                        let mut descriptions = descriptions.clone();
+                        descriptions.extend(item_descriptions.iter().cloned());
+                        let mut path = path.clone();
+                        path.extend(item_path.clone());
+                        (descriptions, path)
+                    })
+                })
+                .collect();
+        }
+        paths.extend(partial_paths);
+    }
+    return paths;
+}
+
+pub fn spending_path_descriptions(
+    wallet: &Wallet<MemoryDatabase>,
+) -> Result<Vec<String>, anyhow::Error> {
+    let Some(policy) = wallet.policies(KeychainKind::External)? else {
+        return Ok(Vec::new());
+    };
+    let paths = spending_paths(&policy);
+    // A single way to spend leaves nothing to choose
+    if paths.len() < 2 {
+        return Ok(Vec::new());
+    }
+    return Ok(paths
+        .into_iter()
+        .map(|(descriptions, _)| descriptions.join(" and "))
+        .collect());
+}
+
+fn policy_path(
+    wallet: &Wallet<MemoryDatabase>,
+    keychain: KeychainKind,
+    spending_path: usize,
+) -> Result<Option<BTreeMap<String, Vec<usize>>>, anyhow::Error> {
+    let Some(policy) = wallet.policies(keychain)? else {
+        return Ok(None);
+    };
+    let path = spending_paths(&policy)
+        .into_iter()
+        .nth(spending_path)
+        .map(|(_, path)| path)
+        .filter(|path| !path.is_empty());
+    return Ok(path);
+}
+
+pub fn make_policy_psbt(
+    wallet: &Wallet<MemoryDatabase>,
+    recipient_str: &str,
+    amount: u64,
+    spending_path: usize,
+) -> Result<PartiallySignedTransaction, anyhow::Error> {
+    let recipient_address = Address::from_str(recipient_str)?.require_network(Network::Testnet)?;
+    let mut tx_builder = wallet.build_tx();
+    // RBF is left off so that bdk can set nSequence to the relative timelock of the chosen path
+    tx_builder.add_recipient(recipient_address.script_pubkey(), amount);
+    for keychain in [KeychainKind::External, KeychainKind::Internal] {
+        if let Some(path) = policy_path(wallet, keychain, spending_path)? {
+            tx_builder.policy_path(path, keychain);
+        }
+    }
+    let (mut psbt, _tx_details) = tx_builder.finish()?;
+    wallet.sign(&mut psbt, SignOptions::default())?;
+    return Ok(psbt);
+}
+
+#[cfg(test)]
+mod tests {
+    use bdk::KeychainKind;
+
+    use crate::bitcoin_wallet::{generate_wallet_from_descriptors, generate_xpriv};
+    use crate::multisig::{local_cosigner_key, public_cosigner_key};
+    use crate::policy::{compile_policy, policy_path, spending_path_descriptions};
+
+    #[test]
+    fn test_timelocked_recovery_policy() {
+        let my_key = local_cosigner_key(
+            &generate_xpriv(
+                "limb capital decade way negative task moral empty virus fragile copper elegant",
+            )
+            .unwrap()
+            .to_string(),
+        )
+        .unwrap();
+        let spouse_key = public_cosigner_key(
+            &local_cosigner_key(
+                &generate_xpriv(
+                    "legal winner thank year wave sausage worth useful legal winner thank yellow",
+                )
+                .unwrap()
+                .to_string(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let policy_keys = vec![
+            ("me".to_string(), my_key),
+            ("spouse".to_string(), spouse_key),
+        ];
+
+        let policy = "or(99@pk(me),1@and(pk(spouse),older(52560)))";
+        let (external, internal) = compile_policy(policy, &policy_keys).unwrap();
+        assert!(external.starts_with("wsh(") && external.contains("older(52560)"));
+
+        let wallet = generate_wallet_from_descriptors(&external, &internal).unwrap();
+        let spending_paths = spending_path_descriptions(&wallet).unwrap();
+        assert_eq!(spending_paths.len(), 2);
+        assert!(spending_paths[1].contains("52560 blocks"));
+
+        assert!(compile_policy("or(pk(me),pk(stranger))", &policy_keys).is_err());
+
+        // The choice sits below the top level and still needs a path for bdk to spend
+        let nested = "and(pk(me),or(pk(spouse),after(500000)))";
+        let (external, internal) = compile_policy(nested, &policy_keys).unwrap();
+        let wallet = generate_wallet_from_descriptors(&external, &internal).unwrap();
+        let spending_paths = spending_path_descriptions(&wallet).unwrap();
+        assert_eq!(spending_paths.len(), 2);
+        assert!(spending_paths
+            .iter()
+            .any(|description| description.contains("absolute timelock")));
+        let paths: Vec<_> = (0..2)
+            .map(|spending_path| {
+                policy_path(&wallet, KeychainKind::External, spending_path)
+                    .unwrap()
+                    .unwrap()
+            })
+            .collect();
+        assert_ne!(paths[0], paths[1]);
+    }
+}
@@ -0,0 +1,155 @@
+use bdk::bitcoin::{Address, Network, PrivateKey, Transaction};
+use bdk::database::MemoryDatabase;
+use bdk::wallet::Wallet;
+use bdk::{FeeRate, KeychainKind, SignOptions};
+
+use crate::backend::WalletBackend;
+
+#[derive(PartialEq, Clone)]
+pub struct SweepTransaction {
+    pub transaction: Transaction,
+    pub amount: u64,
+    pub fee: u64,
+    pub address: String,
+}
+
+pub fn sweep_descriptors(wif: &str) -> Result<Vec<String>, anyhow::Error> {
+    let private_key = PrivateKey::from_wif(wif.trim())?;
+    if private_key.network != Network::Testnet {
+        anyhow::bail!("Private key {} is not a testnet key", wif.trim());
+    }
+    let wif = private_key.to_wif();
+    let mut descriptors = vec![format!("pkh({})", wif)];
+    // Uncompressed keys can't be used in segwit scripts
+    if private_key.compressed {
+        descriptors.push(format!("sh(wpkh({}))", wif));
+        descriptors.push(format!("wpkh({})", wif));
+    }
+    return Ok(descriptors);
+}
+
+pub fn parse_wif_keys(wif_keys: &str) -> Vec<String> {
+    return wif_keys
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|wif| !wif.is_empty())
+        .map(|wif| wif.to_string())
+        .collect();
+}
+
+pub fn decode_qr_image(path: &str) -> Result<String, anyhow::Error> {
+    let image = image::open(path)?.to_luma8();
+    let mut prepared_image = rqrr::PreparedImage::prepare(image);
+    let Some(grid) = prepared_image.detect_grids().into_iter().next() else {
+        anyhow::bail!("No QR code found in {}", path);
+    };
+    let (_, content) = grid.decode()?;
+    return Ok(content);
+}
+
+fn find_funded_wallets(
+    backend: &dyn WalletBackend,
+    wif_keys: &[String],
+) -> Result<Vec<Wallet<MemoryDatabase>>, anyhow::Error> {
+    let mut funded_wallets = Vec::new();
+    for wif in wif_keys.iter() {
+        for descriptor in sweep_descriptors(wif)? {
+            let wallet = Wallet::new(
+                descriptor.as_str(),
+                None,
+                Network::Testnet,
+                MemoryDatabase::new(),
+            )?;
+            backend.sync(&wallet, None)?;
+            if !wallet.list_unspent()?.is_empty() {
+                funded_wallets.push(wallet);
+            }
+        }
+    }
+    return Ok(funded_wallets);
+}
+
+pub fn make_sweep_transaction(
+    backend: &dyn WalletBackend,
+    wif_keys: &[String],
+    destination: &Address,
+    fee_rate: f32,
+) -> Result<SweepTransaction, anyhow::Error> {
+    let funded_wallets = find_funded_wallets(backend, wif_keys)?;
+    let Some((base_wallet, foreign_wallets)) = funded_wallets.split_first() else {
+        anyhow::bail!("No funds found for the given private keys");
+    };
+
+    let mut tx_builder = base_wallet.build_tx();
+    tx_builder
+        .drain_wallet()
+        .drain_to(destination.script_pubkey())
+        .fee_rate(FeeRate::from_sat_per_vb(fee_rate))
+        .enable_rbf();
+    for wallet in foreign_wallets.iter() {
+        let satisfaction_weight = wallet
+            .get_descriptor_for_keychain(KeychainKind::External)
+            .max_weight_to_satisfy()?;
+        for utxo in wallet.list_unspent()? {
+            let outpoint = utxo.outpoint;
+            let psbt_input = wallet.get_psbt_input(utxo, None, false)?;
+            tx_builder.add_foreign_utxo(outpoint, psbt_input, satisfaction_weight)?;
+        }
+    }
+    let (mut psbt, tx_details) = tx_builder.finish()?;
+
+    for wallet in funded_wallets.iter() {
+        wallet.sign(&mut psbt, SignOptions::default())?;
+        // Every sweep wallet holds the same key, so signatures made for another wallet's
+        // script type must be dropped before that wallet signs its own inputs
+        for input in psbt.inputs.iter_mut() {
+            if input.final_script_sig.is_none() && input.final_script_witness.is_none() {
+                input.partial_sigs.clear();
+            }
+        }
+    }
+    let finalized = psbt
+        .inputs
+        .iter()
+        .all(|input| input.final_script_sig.is_some() || input.final_script_witness.is_some());
+    if !finalized {
+        anyhow::bail!("Unable to sign every input of the sweep transaction");
+    }
+
+    // tx_details.sent only covers the base wallet's inputs, not the foreign ones
+    let destination_script = destination.script_pubkey();
+    let Some(amount) = psbt
+        .unsigned_tx
+        .output
+        .iter()
+        .find(|output| output.script_pubkey == destination_script)
+        .map(|output| output.value)
+    else {
+        anyhow::bail!("The sweep transaction has no output to the destination");
+    };
+    let fee = tx_details.fee.unwrap_or(0);
+    return Ok(SweepTransaction {
+        transaction: psbt.extract_tx(),
+        amount: amount,
+        fee: fee,
+        address: destination.to_string(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sweep::{parse_wif_keys, sweep_descriptors};
+
+    #[test]
+    fn test_sweep_descriptors() {
+        let wif = "cVt4o7BGAig1UXywgGSmARhxMdzP5qvQsxKkSsc1XEkw3tDTQFpy";
+        let descriptors = sweep_descriptors(wif).unwrap();
+        assert_eq!(descriptors.len(), 3);
+        assert!(descriptors[1].starts_with("sh(wpkh("));
+
+        let mainnet_wif = "KwdMAjGmerYanjeui5SHS7JkmpZvVipYvB2LJGU1ZxJwYvP98617";
+        assert!(sweep_descriptors(mainnet_wif).is_err());
+
+        let wif_keys = parse_wif_keys(&format!("{}\n {} ,", wif, mainnet_wif));
+        assert_eq!(wif_keys, vec![wif.to_string(), mainnet_wif.to_string()]);
+    }
+}
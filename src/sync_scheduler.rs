@@ -0,0 +1,229 @@
+use chrono::{DateTime, Local};
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_SYNC_INTERVAL_S: u64 = 60;
+const MAX_SYNC_BACKOFF_S: u64 = 1800;
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum SyncStatus {
+    NotSynced,
+    Syncing,
+    Synced,
+    Failed(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct WalletSyncState {
+    pub status: SyncStatus,
+    pub last_synced: Option<DateTime<Local>>,
+    last_attempt: Option<Instant>,
+    failures: u32,
+    offline: bool,
+}
+
+impl Default for WalletSyncState {
+    fn default() -> Self {
+        WalletSyncState {
+            status: SyncStatus::NotSynced,
+            last_synced: None,
+            last_attempt: None,
+            failures: 0,
+            offline: false,
+        }
+    }
+}
+
+impl SyncStatus {
+    pub fn name(&self) -> String {
+        match self {
+            SyncStatus::NotSynced => "Not Synced".to_string(),
+            SyncStatus::Syncing => "Syncing".to_string(),
+            SyncStatus::Synced => "Synced".to_string(),
+            SyncStatus::Failed(error) => format!("Failed: {}", error),
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum ConnectionStatus {
+    NotSynced,
+    Connected,
+    Syncing,
+    Offline,
+    Error(String),
+}
+
+impl ConnectionStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConnectionStatus::NotSynced => "● Not Synced",
+            ConnectionStatus::Connected => "● Connected",
+            ConnectionStatus::Syncing => "● Syncing",
+            ConnectionStatus::Offline => "● Offline",
+            ConnectionStatus::Error(_) => "● Error",
+        }
+    }
+}
+
+pub struct SyncScheduler {
+    pub interval: Duration,
+    states: HashMap<String, WalletSyncState>,
+}
+
+impl SyncScheduler {
+    pub fn new() -> Self {
+        SyncScheduler {
+            interval: Duration::from_secs(DEFAULT_SYNC_INTERVAL_S),
+            states: HashMap::new(),
+        }
+    }
+
+    pub fn state(&self, pub_key: &str) -> WalletSyncState {
+        return self.states.get(pub_key).cloned().unwrap_or_default();
+    }
+
+    // Each consecutive failure doubles the wait, up to half an hour
+    fn retry_delay(&self, failures: u32) -> Duration {
+        let delay = self.interval.saturating_mul(2u32.saturating_pow(failures));
+        return delay.min(Duration::from_secs(MAX_SYNC_BACKOFF_S).max(self.interval));
+    }
+
+    pub fn due_wallets(&self, pub_keys: &[String], now: Instant) -> Vec<String> {
+        return pub_keys
+            .iter()
+            .filter(|pub_key| {
+                let Some(state) = self.states.get(*pub_key) else {
+                    return true;
+                };
+                if state.status == SyncStatus::Syncing {
+                    return false;
+                }
+                return state.last_attempt.map_or(true, |last_attempt| {
+                    now.duration_since(last_attempt) >= self.retry_delay(state.failures)
+                });
+            })
+            .cloned()
+            .collect();
+    }
+
+    pub fn request_sync_all(&mut self) {
+        for state in self.states.values_mut() {
+            state.last_attempt = None;
+            state.failures = 0;
+        }
+    }
+
+    pub fn started(&mut self, pub_key: &str, now: Instant) {
+        let state = self.states.entry(pub_key.to_string()).or_default();
+        state.status = SyncStatus::Syncing;
+        state.last_attempt = Some(now);
+    }
+
+    pub fn succeeded(&mut self, pub_key: &str) {
+        let state = self.states.entry(pub_key.to_string()).or_default();
+        state.status = SyncStatus::Synced;
+        state.last_synced = Some(Local::now());
+        state.failures = 0;
+        state.offline = false;
+    }
+
+    pub fn failed(&mut self, pub_key: &str, error: &str, offline: bool) {
+        let state = self.states.entry(pub_key.to_string()).or_default();
+        state.status = SyncStatus::Failed(error.to_string());
+        state.failures += 1;
+        state.offline = offline;
+    }
+
+    // Worked out across every wallet, so one wallet finishing last can't hide another's failure
+    pub fn connection_status(&self, pub_keys: &[String]) -> ConnectionStatus {
+        if pub_keys.is_empty() {
+            return ConnectionStatus::NotSynced;
+        }
+        let states: Vec<WalletSyncState> =
+            pub_keys.iter().map(|pub_key| self.state(pub_key)).collect();
+        if states.iter().any(|state| {
+            state.status == SyncStatus::NotSynced || state.status == SyncStatus::Syncing
+        }) {
+            return ConnectionStatus::Syncing;
+        }
+        let error = states.iter().find_map(|state| match &state.status {
+            SyncStatus::Failed(error) if !state.offline => Some(error.clone()),
+            _ => None,
+        });
+        if let Some(error) = error {
+            return ConnectionStatus::Error(error);
+        }
+        if states.iter().any(|state| state.offline) {
+            return ConnectionStatus::Offline;
+        }
+        return ConnectionStatus::Connected;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::sync_scheduler::{ConnectionStatus, SyncScheduler, SyncStatus};
+
+    #[test]
+    fn test_sync_scheduler() {
+        let mut scheduler = SyncScheduler::new();
+        scheduler.interval = Duration::from_secs(10);
+        let pub_keys = vec!["a".to_string(), "b".to_string()];
+        let start = Instant::now();
+        assert_eq!(scheduler.due_wallets(&pub_keys, start), pub_keys);
+
+        assert_eq!(
+            scheduler.connection_status(&pub_keys),
+            ConnectionStatus::Syncing
+        );
+        assert_eq!(
+            scheduler.connection_status(&[]),
+            ConnectionStatus::NotSynced
+        );
+
+        scheduler.started("a", start);
+        scheduler.started("b", start);
+        assert!(scheduler.due_wallets(&pub_keys, start).is_empty());
+
+        scheduler.succeeded("a");
+        assert_eq!(
+            scheduler.connection_status(&pub_keys),
+            ConnectionStatus::Syncing
+        );
+        scheduler.failed("b", "Connection refused", false);
+        assert_eq!(
+            scheduler.connection_status(&pub_keys),
+            ConnectionStatus::Error("Connection refused".to_string())
+        );
+        assert_eq!(
+            scheduler.state("b").status,
+            SyncStatus::Failed("Connection refused".to_string())
+        );
+        assert!(scheduler.state("a").last_synced.is_some());
+        assert_eq!(
+            scheduler.due_wallets(&pub_keys, start + Duration::from_secs(10)),
+            vec!["a".to_string()]
+        );
+        assert_eq!(
+            scheduler.due_wallets(&pub_keys, start + Duration::from_secs(20)),
+            pub_keys
+        );
+
+        scheduler.started("b", start);
+        scheduler.failed("b", "Connection refused", true);
+        assert_eq!(
+            scheduler.connection_status(&pub_keys),
+            ConnectionStatus::Offline
+        );
+        assert!(!scheduler
+            .due_wallets(&pub_keys, start + Duration::from_secs(39))
+            .contains(&"b".to_string()));
+        assert!(scheduler
+            .due_wallets(&pub_keys, start + Duration::from_secs(40))
+            .contains(&"b".to_string()));
+    }
+}
@@ -0,0 +1,89 @@
+pub mod mock_electrum;
+pub mod regtest;
+
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+use crate::backend::BackendConfig;
+use crate::wallet_file_manager::{SyncData, WalletModel};
+
+pub const TEST_PASSWORD: &str = "correct horse battery staple";
+
+// Removes the wallet file when the test finishes, whether it passed or not
+pub struct TempWalletPath(PathBuf);
+
+impl Deref for TempWalletPath {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        return &self.0;
+    }
+}
+
+impl Drop for TempWalletPath {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+// Unique per test and process so parallel test runs never share a wallet file
+pub fn temp_wallet_path(name: &str) -> TempWalletPath {
+    let path = std::env::temp_dir().join(format!(
+        "bitcoin_rust_wallet_{}_{}.txt",
+        name,
+        std::process::id()
+    ));
+    let _ = fs::remove_file(&path);
+    return TempWalletPath(path);
+}
+
+pub fn temp_wallet_model(path: &Path, backend_config: BackendConfig) -> WalletModel {
+    let mut wallet_model = WalletModel::with_filepath(path.to_path_buf());
+    wallet_model
+        .create_passworded_file(TEST_PASSWORD.to_string())
+        .unwrap();
+    wallet_model.set_backend(backend_config).unwrap();
+    return wallet_model;
+}
+
+pub fn reload_wallet_model(path: &Path) -> WalletModel {
+    let mut wallet_model = WalletModel::with_filepath(path.to_path_buf());
+    assert!(wallet_model.validate_password(TEST_PASSWORD));
+    wallet_model.initialise_from_wallet_file().unwrap();
+    return wallet_model;
+}
+
+// Runs the same background worker the app uses and applies its result to the model
+pub fn sync_wallet_model(wallet_model: &mut WalletModel, pub_key: &str) -> Result<(), String> {
+    let (sync_sender, sync_receiver) = mpsc::channel();
+    let handle = wallet_model
+        .sync_wallet_in_background(pub_key, sync_sender)
+        .expect("Wallet not loaded");
+    handle.join().unwrap();
+    match sync_receiver.recv().unwrap() {
+        SyncData::Synced {
+            pub_key,
+            balance,
+            transactions,
+            utxos,
+            tip_height,
+        } => {
+            wallet_model
+                .sync_wallet(
+                    &pub_key,
+                    Some(balance),
+                    Some(transactions),
+                    Some(utxos),
+                    tip_height,
+                )
+                .unwrap();
+            return Ok(());
+        }
+        SyncData::Failed { error, .. } => return Err(error),
+        SyncData::SweepFound { .. } | SyncData::FeeEstimated { .. } => {
+            unreachable!("Sync workers never send sweep results")
+        }
+    }
+}
@@ -0,0 +1,409 @@
+use bdk::bitcoin::absolute::LockTime;
+use bdk::bitcoin::block::{Header, Version};
+use bdk::bitcoin::blockdata::constants::genesis_block;
+use bdk::bitcoin::consensus::encode::{deserialize, serialize_hex};
+use bdk::bitcoin::hashes::Hash;
+use bdk::bitcoin::{CompactTarget, Network, OutPoint, Script, ScriptBuf, Sequence};
+use bdk::bitcoin::{Transaction, TxIn, TxMerkleNode, TxOut, Txid, Witness};
+use bdk::electrum_client::ToElectrumScriptHash;
+use serde_json::{json, Value};
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use crate::backend::{BackendConfig, BackendKind};
+
+const BLOCK_INTERVAL_S: u32 = 600;
+const RELAY_FEE_BTC_PER_KVB: f64 = 0.00001;
+
+// Scripted chain state served by the mock, plus knobs for misbehaving
+pub struct MockChain {
+    headers: Vec<Header>,
+    transactions: HashMap<Txid, Transaction>,
+    // Confirmation height of each known transaction, None while in the mempool
+    heights: HashMap<Txid, Option<u32>>,
+    // Bumped on every reorg so replacement blocks get new hashes
+    branch: u32,
+    funding_count: u32,
+    pub fee_rate_btc_per_kvb: f64,
+    pub error: Option<String>,
+    pub delay: Duration,
+}
+
+impl MockChain {
+    pub fn new() -> Self {
+        MockChain {
+            headers: vec![genesis_block(Network::Regtest).header],
+            transactions: HashMap::new(),
+            heights: HashMap::new(),
+            branch: 0,
+            funding_count: 0,
+            fee_rate_btc_per_kvb: 0.0001,
+            error: None,
+            delay: Duration::ZERO,
+        }
+    }
+
+    pub fn tip_height(&self) -> u32 {
+        return self.headers.len() as u32 - 1;
+    }
+
+    pub fn height(&self, txid: &Txid) -> Option<Option<u32>> {
+        return self.heights.get(txid).copied();
+    }
+
+    pub fn add_transaction(&mut self, transaction: Transaction) -> Txid {
+        let txid = transaction.txid();
+        self.transactions.insert(txid, transaction);
+        self.heights.entry(txid).or_insert(None);
+        return txid;
+    }
+
+    // Pays the script from a fresh parent so previous outputs can be served too
+    pub fn fund(&mut self, script: &Script, amount: u64) -> Txid {
+        self.funding_count += 1;
+        let parent = Transaction {
+            version: 2,
+            lock_time: LockTime::from_consensus(self.funding_count),
+            input: vec![TxIn {
+                previous_output: OutPoint::null(),
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::MAX,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value: amount + 1_000,
+                script_pubkey: ScriptBuf::new(),
+            }],
+        };
+        let parent_txid = self.add_transaction(parent);
+        let transaction = Transaction {
+            version: 2,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::new(parent_txid, 0),
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value: amount,
+                script_pubkey: script.to_owned(),
+            }],
+        };
+        return self.add_transaction(transaction);
+    }
+
+    // The first new block confirms everything in the mempool
+    pub fn mine(&mut self, blocks: u32) {
+        for _ in 0..blocks {
+            let previous = self.headers.last().unwrap();
+            let header = Header {
+                version: Version::ONE,
+                prev_blockhash: previous.block_hash(),
+                merkle_root: TxMerkleNode::all_zeros(),
+                time: previous.time + BLOCK_INTERVAL_S,
+                bits: CompactTarget::from_consensus(0x207fffff),
+                nonce: self.branch,
+            };
+            self.headers.push(header);
+            let height = self.tip_height();
+            for confirmation_height in self.heights.values_mut() {
+                if confirmation_height.is_none() {
+                    *confirmation_height = Some(height);
+                }
+            }
+        }
+    }
+
+    // Disconnects the top blocks, sending their transactions back to the mempool
+    pub fn reorg(&mut self, depth: u32) {
+        let new_height = self.tip_height().saturating_sub(depth);
+        self.headers.truncate(new_height as usize + 1);
+        self.branch += 1;
+        for confirmation_height in self.heights.values_mut() {
+            if confirmation_height.map_or(false, |height| height > new_height) {
+                *confirmation_height = None;
+            }
+        }
+    }
+
+    // Forgets a transaction, as if it was double spent or evicted
+    pub fn remove_transaction(&mut self, txid: &Txid) {
+        self.transactions.remove(txid);
+        self.heights.remove(txid);
+    }
+
+    fn spends_script(&self, transaction: &Transaction, script_hash: &str) -> bool {
+        return transaction.input.iter().any(|input| {
+            self.transactions
+                .get(&input.previous_output.txid)
+                .and_then(|previous| previous.output.get(input.previous_output.vout as usize))
+                .map_or(false, |output| {
+                    output.script_pubkey.to_electrum_scripthash().to_string() == script_hash
+                })
+        });
+    }
+
+    fn history(&self, script_hash: &str) -> Value {
+        let mut history: Vec<(u32, Txid)> = self
+            .transactions
+            .iter()
+            .filter(|(_, transaction)| {
+                transaction.output.iter().any(|output| {
+                    output.script_pubkey.to_electrum_scripthash().to_string() == script_hash
+                }) || self.spends_script(transaction, script_hash)
+            })
+            .map(|(txid, _)| (self.heights[txid].unwrap_or(0), *txid))
+            .collect();
+        // Mempool entries come after confirmed ones, as electrum servers order them
+        history.sort_by_key(|(height, txid)| (*height == 0, *height, *txid));
+        return Value::Array(
+            history
+                .into_iter()
+                .map(|(height, txid)| json!({"tx_hash": txid.to_string(), "height": height}))
+                .collect(),
+        );
+    }
+
+    fn header(&self, height: &Value) -> Result<&Header, String> {
+        let Some(height) = height.as_u64() else {
+            return Err("Invalid height".to_string());
+        };
+        return self
+            .headers
+            .get(height as usize)
+            .ok_or_else(|| format!("Height {} out of range", height));
+    }
+
+    fn handle(&mut self, method: &str, params: &[Value]) -> Result<Value, String> {
+        if let Some(error) = &self.error {
+            return Err(error.clone());
+        }
+        let param = params.first().cloned().unwrap_or(Value::Null);
+        match method {
+            "server.version" => return Ok(json!(["MockElectrum 1.0", "1.4"])),
+            "server.ping" => return Ok(Value::Null),
+            "blockchain.headers.subscribe" => {
+                let tip = self.headers.last().unwrap();
+                return Ok(json!({"height": self.tip_height(), "hex": serialize_hex(tip)}));
+            }
+            "blockchain.block.header" => return Ok(json!(serialize_hex(self.header(&param)?))),
+            "blockchain.block.headers" => {
+                let start = param.as_u64().unwrap_or(0) as usize;
+                let count = params.get(1).and_then(Value::as_u64).unwrap_or(0) as usize;
+                let headers: Vec<String> = self
+                    .headers
+                    .iter()
+                    .skip(start)
+                    .take(count)
+                    .map(serialize_hex)
+                    .collect();
+                return Ok(json!({"count": headers.len(), "hex": headers.concat(), "max": 2016}));
+            }
+            "blockchain.scripthash.get_history" => {
+                return Ok(self.history(param.as_str().unwrap_or("")))
+            }
+            "blockchain.transaction.get" => {
+                let txid = Txid::from_str(param.as_str().unwrap_or(""))
+                    .map_err(|error| error.to_string())?;
+                let Some(transaction) = self.transactions.get(&txid) else {
+                    return Err(format!("No such transaction {}", txid));
+                };
+                return Ok(json!(serialize_hex(transaction)));
+            }
+            "blockchain.transaction.broadcast" => {
+                let bytes =
+                    hex::decode(param.as_str().unwrap_or("")).map_err(|error| error.to_string())?;
+                let transaction: Transaction =
+                    deserialize(&bytes).map_err(|error| error.to_string())?;
+                return Ok(json!(self.add_transaction(transaction).to_string()));
+            }
+            "blockchain.estimatefee" => return Ok(json!(self.fee_rate_btc_per_kvb)),
+            "blockchain.relayfee" => return Ok(json!(RELAY_FEE_BTC_PER_KVB)),
+            _ => return Err(format!("Unknown method {}", method)),
+        }
+    }
+}
+
+fn respond(chain: &Mutex<MockChain>, request: &Value) -> Value {
+    let delay = chain.lock().unwrap().delay;
+    thread::sleep(delay);
+    let method = request["method"].as_str().unwrap_or("");
+    let params = request["params"].as_array().cloned().unwrap_or_default();
+    return match chain.lock().unwrap().handle(method, &params) {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": request["id"], "result": result}),
+        Err(message) => json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "error": {"code": -32000, "message": message}
+        }),
+    };
+}
+
+fn serve_connection(stream: TcpStream, chain: Arc<Mutex<MockChain>>) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        let Ok(request) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+        let response = match request {
+            Value::Array(requests) => Value::Array(
+                requests
+                    .iter()
+                    .map(|request| respond(&chain, request))
+                    .collect(),
+            ),
+            request => respond(&chain, &request),
+        };
+        if writeln!(writer, "{}", response).is_err() {
+            return;
+        }
+    }
+}
+
+// Speaks Electrum JSON-RPC over a local socket; it lives until the test process exits
+pub struct MockElectrumServer {
+    pub address: SocketAddr,
+    chain: Arc<Mutex<MockChain>>,
+}
+
+impl MockElectrumServer {
+    pub fn start(chain: MockChain) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let chain = Arc::new(Mutex::new(chain));
+        let server_chain = Arc::clone(&chain);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let chain = Arc::clone(&server_chain);
+                thread::spawn(move || serve_connection(stream, chain));
+            }
+        });
+        return MockElectrumServer { address, chain };
+    }
+
+    pub fn chain(&self) -> MutexGuard<'_, MockChain> {
+        return self.chain.lock().unwrap();
+    }
+
+    pub fn backend_config(&self) -> BackendConfig {
+        return BackendConfig {
+            kind: BackendKind::Electrum,
+            url: format!("tcp://{}", self.address),
+            network: Network::Regtest,
+            ..BackendConfig::default()
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bdk::wallet::AddressIndex;
+
+    use std::time::{Duration, Instant};
+
+    use crate::bitcoin_wallet::{generate_mnemonic_string, generate_xpriv};
+    use crate::history_diff::TransactionState;
+    use crate::test_support::mock_electrum::{MockChain, MockElectrumServer};
+    use crate::test_support::{
+        sync_wallet_model, temp_wallet_model, temp_wallet_path, TempWalletPath,
+    };
+    use crate::wallet_file_manager::WalletModel;
+
+    // The path is returned so the wallet file lives until the end of the test
+    fn funded_wallet_model(
+        server: &MockElectrumServer,
+        name: &str,
+    ) -> (TempWalletPath, WalletModel, String) {
+        let wallet_path = temp_wallet_path(name);
+        let mut wallet_model = temp_wallet_model(&wallet_path, server.backend_config());
+        let mnemonic = generate_mnemonic_string().unwrap();
+        let priv_key = generate_xpriv(&mnemonic).unwrap().to_string();
+        wallet_model
+            .add_wallet(&priv_key, &mnemonic, "Mock", true)
+            .unwrap();
+        let pub_key = wallet_model.get_active_wallet_pub_key();
+        let script = wallet_model
+            .get_active_wallet()
+            .lock()
+            .unwrap()
+            .get_address(AddressIndex::New)
+            .unwrap()
+            .script_pubkey();
+        server.chain().fund(&script, 50_000);
+        server.chain().mine(1);
+        return (wallet_path, wallet_model, pub_key);
+    }
+
+    #[test]
+    fn test_mock_electrum_reorg() {
+        let server = MockElectrumServer::start(MockChain::new());
+        let (_wallet_path, mut wallet_model, pub_key) = funded_wallet_model(&server, "mock_reorg");
+        sync_wallet_model(&mut wallet_model, &pub_key).unwrap();
+        let wallet_data = wallet_model.get_active_wallet_data();
+        assert_eq!(wallet_data.balance.unwrap().confirmed, 50_000);
+        let transactions = wallet_data.sorted_transactions.unwrap();
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(
+            transactions[0].confirmation_time.as_ref().unwrap().height,
+            1
+        );
+        assert_eq!(wallet_data.tip_height, Some(1));
+
+        let txid = transactions[0].txid;
+        server.chain().reorg(1);
+        sync_wallet_model(&mut wallet_model, &pub_key).unwrap();
+        let wallet_data = wallet_model.get_active_wallet_data();
+        assert_eq!(wallet_data.balance.unwrap().confirmed, 0);
+        assert!(wallet_data.sorted_transactions.unwrap()[0]
+            .confirmation_time
+            .is_none());
+        assert_eq!(
+            wallet_data.transaction_states[&txid.to_string()],
+            TransactionState::ReorgedOut
+        );
+
+        server.chain().remove_transaction(&txid);
+        sync_wallet_model(&mut wallet_model, &pub_key).unwrap();
+        let wallet_data = wallet_model.get_active_wallet_data();
+        assert!(wallet_data.sorted_transactions.unwrap().is_empty());
+        assert_eq!(wallet_data.get_total(), 0);
+        assert_eq!(wallet_data.removed_transactions[0].txid, txid);
+        assert_eq!(
+            wallet_data.transaction_states[&txid.to_string()],
+            TransactionState::ReorgedOut
+        );
+    }
+
+    #[test]
+    fn test_mock_electrum_errors_and_delays() {
+        let server = MockElectrumServer::start(MockChain::new());
+        let (_wallet_path, mut wallet_model, pub_key) = funded_wallet_model(&server, "mock_errors");
+        server.chain().error = Some("Server busy".to_string());
+        let error = sync_wallet_model(&mut wallet_model, &pub_key).unwrap_err();
+        assert!(error.contains("Server busy"));
+        assert!(wallet_model.get_active_wallet_data().balance.is_none());
+
+        server.chain().error = None;
+        server.chain().delay = Duration::from_millis(20);
+        let start = Instant::now();
+        sync_wallet_model(&mut wallet_model, &pub_key).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        assert_eq!(wallet_model.get_active_wallet_data().get_total(), 50_000);
+
+        let recipient = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+        let txid = wallet_model.send_transaction(recipient, 10_000).unwrap();
+        assert_eq!(server.chain().height(&txid), Some(None));
+    }
+}
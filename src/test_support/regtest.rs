@@ -0,0 +1,210 @@
+use bdk::bitcoin::{Address, Amount, Network, Script, Txid};
+use bdk::electrum_client::ElectrumApi;
+use electrsd::bitcoind::bitcoincore_rpc::RpcApi;
+use electrsd::bitcoind::BitcoinD;
+use electrsd::ElectrsD;
+
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+
+use crate::backend::{BackendConfig, BackendKind};
+
+const WAIT_ATTEMPTS: usize = 100;
+
+// A throwaway bitcoind and electrs pair, using the binaries electrsd downloaded unless
+// BITCOIND_EXE and ELECTRS_EXE point elsewhere
+pub struct RegtestNode {
+    pub bitcoind: BitcoinD,
+    pub electrsd: ElectrsD,
+}
+
+// Wallets encode addresses for testnet, which share scripts with regtest
+pub fn regtest_address(testnet_address: &str) -> Address {
+    let address = Address::from_str(testnet_address)
+        .unwrap()
+        .require_network(Network::Testnet)
+        .unwrap();
+    return Address::from_script(&address.script_pubkey(), Network::Regtest).unwrap();
+}
+
+impl RegtestNode {
+    pub fn new() -> Self {
+        let bitcoind = BitcoinD::new(electrsd::bitcoind::exe_path().unwrap()).unwrap();
+        let mut electrs_conf = electrsd::Conf::default();
+        electrs_conf.http_enabled = false;
+        let electrsd =
+            ElectrsD::with_conf(electrsd::exe_path().unwrap(), &bitcoind, &electrs_conf).unwrap();
+        let node = RegtestNode { bitcoind, electrsd };
+        // Coinbase outputs need 100 confirmations before the node wallet can spend them
+        node.mine(101);
+        return node;
+    }
+
+    pub fn backend_config(&self) -> BackendConfig {
+        return BackendConfig {
+            kind: BackendKind::Electrum,
+            url: format!("tcp://{}", self.electrsd.electrum_url),
+            network: Network::Regtest,
+            ..BackendConfig::default()
+        };
+    }
+
+    pub fn new_testnet_address(&self) -> String {
+        let address = self
+            .bitcoind
+            .client
+            .get_new_address(None, None)
+            .unwrap()
+            .assume_checked();
+        return Address::from_script(&address.script_pubkey(), Network::Testnet)
+            .unwrap()
+            .to_string();
+    }
+
+    pub fn mine(&self, blocks: u64) {
+        let address = self
+            .bitcoind
+            .client
+            .get_new_address(None, None)
+            .unwrap()
+            .assume_checked();
+        self.bitcoind
+            .client
+            .generate_to_address(blocks, &address)
+            .unwrap();
+        self.wait_for_height();
+    }
+
+    pub fn fund(&self, testnet_address: &str, amount: u64) -> Txid {
+        let address = regtest_address(testnet_address);
+        let txid = self
+            .bitcoind
+            .client
+            .send_to_address(
+                &address,
+                Amount::from_sat(amount),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        self.wait_for_tx(&address.script_pubkey(), txid);
+        return txid;
+    }
+
+    fn wait_for_height(&self) {
+        let height = self.bitcoind.client.get_block_count().unwrap() as usize;
+        for _ in 0..WAIT_ATTEMPTS {
+            self.electrsd.trigger().unwrap();
+            if self
+                .electrsd
+                .client
+                .block_headers_subscribe()
+                .unwrap()
+                .height
+                >= height
+            {
+                return;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        panic!("electrs never reached height {}", height);
+    }
+
+    // electrs indexes the mempool on its own schedule, so wait before syncing
+    pub fn wait_for_tx(&self, script: &Script, txid: Txid) {
+        for _ in 0..WAIT_ATTEMPTS {
+            self.electrsd.trigger().unwrap();
+            let history = self.electrsd.client.script_get_history(script).unwrap();
+            if history.iter().any(|entry| entry.tx_hash == txid) {
+                return;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        panic!("electrs never saw transaction {}", txid);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bdk::wallet::AddressIndex;
+
+    use crate::bitcoin_wallet::{generate_mnemonic_string, generate_xpriv};
+    use crate::test_support::regtest::{regtest_address, RegtestNode};
+    use crate::test_support::{
+        reload_wallet_model, sync_wallet_model, temp_wallet_model, temp_wallet_path,
+    };
+
+    #[test]
+    fn test_wallet_model_regtest() {
+        let node = RegtestNode::new();
+        let wallet_path = temp_wallet_path("regtest");
+        let mut wallet_model = temp_wallet_model(&wallet_path, node.backend_config());
+        let mnemonic = generate_mnemonic_string().unwrap();
+        let priv_key = generate_xpriv(&mnemonic).unwrap().to_string();
+        wallet_model
+            .add_wallet(&priv_key, &mnemonic, "Regtest", true)
+            .unwrap();
+        let pub_key = wallet_model.get_active_wallet_pub_key();
+
+        let receive_address = wallet_model
+            .get_active_wallet()
+            .lock()
+            .unwrap()
+            .get_address(AddressIndex::New)
+            .unwrap()
+            .to_string();
+        node.fund(&receive_address, 1_000_000);
+        node.mine(1);
+        sync_wallet_model(&mut wallet_model, &pub_key).unwrap();
+        let balance = wallet_model.get_active_wallet_data().balance.unwrap();
+        assert_eq!(balance.confirmed, 1_000_000);
+
+        // Replace the unconfirmed send with a higher fee version before it confirms
+        let recipient = node.new_testnet_address();
+        let recipient_script = regtest_address(&recipient).script_pubkey();
+        let original_txid = wallet_model.send_transaction(&recipient, 200_000).unwrap();
+        node.wait_for_tx(&recipient_script, original_txid);
+        sync_wallet_model(&mut wallet_model, &pub_key).unwrap();
+        let replacement_txid = wallet_model
+            .bump_fee(&original_txid.to_string(), 5.0)
+            .unwrap();
+        node.wait_for_tx(&recipient_script, replacement_txid);
+        node.mine(1);
+        sync_wallet_model(&mut wallet_model, &pub_key).unwrap();
+
+        let wallet_data = wallet_model.get_active_wallet_data();
+        let transactions = wallet_data.sorted_transactions.clone().unwrap();
+        assert!(!transactions
+            .iter()
+            .any(|transaction| transaction.txid == original_txid));
+        let replacement_details = transactions
+            .iter()
+            .find(|transaction| transaction.txid == replacement_txid)
+            .unwrap();
+        assert!(replacement_details.confirmation_time.is_some());
+        let balance = wallet_data.balance.unwrap();
+        assert_eq!(
+            balance.confirmed,
+            800_000 - replacement_details.fee.unwrap()
+        );
+        assert!(wallet_model.contacts_contain_wallet(&recipient));
+
+        let reloaded = reload_wallet_model(&wallet_path);
+        assert_eq!(reloaded.get_active_wallet_data().balance, Some(balance));
+        assert!(reloaded.contacts_contain_wallet(&recipient));
+        assert_eq!(reloaded.json_wallet_data.backend, node.backend_config());
+
+        let restore_path = temp_wallet_path("regtest_restore");
+        let mut restored = temp_wallet_model(&restore_path, node.backend_config());
+        restored
+            .add_wallet(&priv_key, &mnemonic, "Restored", true)
+            .unwrap();
+        sync_wallet_model(&mut restored, &pub_key).unwrap();
+        assert_eq!(restored.get_active_wallet_data().balance, Some(balance));
+    }
+}
@@ -0,0 +1,203 @@
+use bdk::bitcoin::consensus::encode::serialize_hex;
+use bdk::bitcoin::{Address, Network, Script};
+use bdk::database::{Database, MemoryDatabase};
+use bdk::wallet::Wallet;
+use bdk::{KeychainKind, TransactionDetails};
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct DetailInput {
+    pub previous_output: String,
+    // Only known when the previous transaction is one of ours
+    pub value: Option<u64>,
+    pub is_mine: bool,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct DetailOutput {
+    pub address: Option<String>,
+    pub script: String,
+    pub value: u64,
+    pub is_mine: bool,
+    pub is_change: bool,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct TransactionDetail {
+    pub txid: String,
+    pub inputs: Vec<DetailInput>,
+    pub outputs: Vec<DetailOutput>,
+    pub fee: Option<u64>,
+    pub fee_rate: Option<f32>,
+    pub vsize: usize,
+    pub weight: u64,
+    pub rbf: bool,
+    pub locktime: u32,
+    pub height: Option<u32>,
+    pub confirmations: Option<u32>,
+    pub raw_hex: String,
+}
+
+fn script_keychain(
+    wallet: &Wallet<MemoryDatabase>,
+    script: &Script,
+) -> Result<Option<KeychainKind>, anyhow::Error> {
+    let path = wallet.database().get_path_from_script_pubkey(script)?;
+    return Ok(path.map(|(keychain, _)| keychain));
+}
+
+// None when the tip isn't known, so a confirmed transaction can't be counted
+pub fn confirmations(height: Option<u32>, tip_height: Option<u32>) -> Option<u32> {
+    match (height, tip_height) {
+        (Some(height), Some(tip_height)) => Some(tip_height.saturating_sub(height) + 1),
+        (None, _) => Some(0),
+        _ => None,
+    }
+}
+
+pub fn transaction_detail(
+    wallet: &Wallet<MemoryDatabase>,
+    transaction_details: &TransactionDetails,
+    tip_height: Option<u32>,
+) -> Result<TransactionDetail, anyhow::Error> {
+    let Some(transaction) = &transaction_details.transaction else {
+        anyhow::bail!(
+            "Raw transaction {} is not available",
+            transaction_details.txid
+        );
+    };
+
+    let mut inputs = Vec::new();
+    for input in transaction.input.iter() {
+        let previous_output = wallet
+            .get_tx(&input.previous_output.txid, true)?
+            .and_then(|previous| previous.transaction)
+            .and_then(|previous| {
+                previous
+                    .output
+                    .get(input.previous_output.vout as usize)
+                    .cloned()
+            });
+        let is_mine = match &previous_output {
+            Some(previous_output) => wallet.is_mine(&previous_output.script_pubkey)?,
+            None => false,
+        };
+        inputs.push(DetailInput {
+            previous_output: input.previous_output.to_string(),
+            value: previous_output.map(|previous_output| previous_output.value),
+            is_mine,
+        });
+    }
+
+    let mut outputs = Vec::new();
+    for output in transaction.output.iter() {
+        let keychain = script_keychain(wallet, &output.script_pubkey)?;
+        outputs.push(DetailOutput {
+            address: Address::from_script(&output.script_pubkey, Network::Testnet)
+                .ok()
+                .map(|address| address.to_string()),
+            script: output.script_pubkey.to_asm_string(),
+            value: output.value,
+            is_mine: keychain.is_some(),
+            is_change: keychain == Some(KeychainKind::Internal),
+        });
+    }
+
+    let vsize = transaction.vsize();
+    let height = transaction_details
+        .confirmation_time
+        .as_ref()
+        .map(|confirmation_time| confirmation_time.height);
+    return Ok(TransactionDetail {
+        txid: transaction_details.txid.to_string(),
+        inputs,
+        outputs,
+        fee: transaction_details.fee,
+        fee_rate: transaction_details.fee.map(|fee| fee as f32 / vsize as f32),
+        vsize,
+        weight: transaction.weight().to_wu(),
+        rbf: transaction.is_explicitly_rbf(),
+        locktime: transaction.lock_time.to_consensus_u32(),
+        height,
+        confirmations: confirmations(height, tip_height),
+        raw_hex: serialize_hex(transaction),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use bdk::bitcoin::absolute::LockTime;
+    use bdk::bitcoin::script::{Builder, PushBytesBuf};
+    use bdk::bitcoin::{Network, ScriptBuf};
+    use bdk::bitcoin::{OutPoint, Sequence, Transaction, TxIn, TxOut, Witness};
+    use bdk::database::MemoryDatabase;
+    use bdk::wallet::{AddressIndex, Wallet};
+    use bdk::{BlockTime, TransactionDetails};
+
+    use crate::transaction_detail::{confirmations, transaction_detail};
+
+    #[test]
+    fn test_transaction_detail() {
+        let wallet = Wallet::new(
+            "wpkh(tpubD6NzVbkrYhZ4XgiXtGrdW5XDAPFCL9h7we1vwNCpn8tGbBcgfVYjXyhWo4E1xkh56hjod1RhGjxbaTLV3X4FyWuejifB9jusQ46QzG87VKp/0/*)",
+            Some("wpkh(tpubD6NzVbkrYhZ4XgiXtGrdW5XDAPFCL9h7we1vwNCpn8tGbBcgfVYjXyhWo4E1xkh56hjod1RhGjxbaTLV3X4FyWuejifB9jusQ46QzG87VKp/1/*)"),
+            Network::Testnet,
+            MemoryDatabase::new(),
+        )
+        .unwrap();
+        let receive = wallet.get_address(AddressIndex::New).unwrap();
+        let change = wallet.get_internal_address(AddressIndex::New).unwrap();
+        let op_return = Builder::new()
+            .push_opcode(bdk::bitcoin::opcodes::all::OP_RETURN)
+            .push_slice(PushBytesBuf::try_from(b"hello".to_vec()).unwrap())
+            .into_script();
+        let transaction = Transaction {
+            version: 2,
+            lock_time: LockTime::from_consensus(100),
+            input: vec![TxIn {
+                previous_output: OutPoint::null(),
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                witness: Witness::new(),
+            }],
+            output: vec![
+                TxOut {
+                    value: 10_000,
+                    script_pubkey: receive.script_pubkey(),
+                },
+                TxOut {
+                    value: 5_000,
+                    script_pubkey: change.script_pubkey(),
+                },
+                TxOut {
+                    value: 0,
+                    script_pubkey: op_return,
+                },
+            ],
+        };
+        let transaction_details = TransactionDetails {
+            txid: transaction.txid(),
+            transaction: Some(transaction),
+            received: 15_000,
+            sent: 0,
+            fee: Some(200),
+            confirmation_time: Some(BlockTime {
+                height: 95,
+                timestamp: 0,
+            }),
+        };
+
+        let detail = transaction_detail(&wallet, &transaction_details, Some(100)).unwrap();
+        assert_eq!(detail.inputs[0].value, None);
+        assert!(detail.outputs[0].is_mine && !detail.outputs[0].is_change);
+        assert!(detail.outputs[1].is_mine && detail.outputs[1].is_change);
+        assert_eq!(detail.outputs[2].address, None);
+        assert!(!detail.outputs[2].is_mine);
+        assert!(detail.rbf);
+        assert_eq!(detail.locktime, 100);
+        assert_eq!(detail.confirmations, Some(6));
+        assert_eq!(detail.fee_rate, Some(200.0 / detail.vsize as f32));
+        assert!(detail.raw_hex.starts_with("02000000"));
+        assert_eq!(confirmations(Some(95), None), None);
+        assert_eq!(confirmations(None, None), Some(0));
+    }
+}
@@ -0,0 +1,259 @@
+use serde::{Deserialize, Serialize};
+
+use std::env;
+
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum BitcoinUnit {
+    Btc,
+    MilliBtc,
+    Bits,
+    Sats,
+}
+
+pub const BITCOIN_UNITS: [BitcoinUnit; 4] = [
+    BitcoinUnit::Btc,
+    BitcoinUnit::MilliBtc,
+    BitcoinUnit::Bits,
+    BitcoinUnit::Sats,
+];
+
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct NumberFormat {
+    pub decimal_separator: char,
+    pub group_separator: char,
+}
+
+// Locales which write 1.234,56 rather than 1,234.56
+const COMMA_DECIMAL_LANGUAGES: [&str; 14] = [
+    "de", "fr", "es", "it", "nl", "pt", "ru", "pl", "sv", "da", "nb", "fi", "cs", "tr",
+];
+
+impl BitcoinUnit {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BitcoinUnit::Btc => "BTC",
+            BitcoinUnit::MilliBtc => "mBTC",
+            BitcoinUnit::Bits => "bits",
+            BitcoinUnit::Sats => "sats",
+        }
+    }
+
+    pub fn decimals(&self) -> usize {
+        match self {
+            BitcoinUnit::Btc => 8,
+            BitcoinUnit::MilliBtc => 5,
+            BitcoinUnit::Bits => 2,
+            BitcoinUnit::Sats => 0,
+        }
+    }
+}
+
+impl Default for BitcoinUnit {
+    fn default() -> Self {
+        BitcoinUnit::Sats
+    }
+}
+
+impl NumberFormat {
+    pub fn from_locale(locale: &str) -> NumberFormat {
+        let language = locale
+            .split(|c| c == '_' || c == '-' || c == '.')
+            .next()
+            .unwrap_or("")
+            .to_lowercase();
+        if COMMA_DECIMAL_LANGUAGES.contains(&language.as_str()) {
+            return NumberFormat {
+                decimal_separator: ',',
+                group_separator: '.',
+            };
+        }
+        return NumberFormat::default();
+    }
+
+    pub fn from_environment() -> NumberFormat {
+        for variable in ["LC_ALL", "LC_NUMERIC", "LANG"] {
+            if let Ok(locale) = env::var(variable) {
+                if !locale.is_empty() {
+                    return NumberFormat::from_locale(&locale);
+                }
+            }
+        }
+        return NumberFormat::default();
+    }
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        NumberFormat {
+            decimal_separator: '.',
+            group_separator: ',',
+        }
+    }
+}
+
+fn group_digits(digits: &str, group_separator: char) -> String {
+    let mut grouped = String::new();
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index) % 3 == 0 {
+            grouped.push(group_separator);
+        }
+        grouped.push(digit);
+    }
+    return grouped;
+}
+
+pub fn format_decimal_amount(sats: u64, decimals: usize, decimal_separator: char) -> String {
+    let divisor = 10u64.pow(decimals as u32);
+    if decimals == 0 {
+        return sats.to_string();
+    }
+    let fraction = format!("{:0width$}", sats % divisor, width = decimals);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        return (sats / divisor).to_string();
+    }
+    return format!("{}{}{}", sats / divisor, decimal_separator, fraction);
+}
+
+pub fn parse_decimal_amount(
+    amount: &str,
+    decimals: usize,
+    decimal_separator: char,
+) -> Result<u64, anyhow::Error> {
+    let amount = amount.trim();
+    let (whole, fraction) = amount.split_once(decimal_separator).unwrap_or((amount, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        anyhow::bail!("Amount is empty");
+    }
+    if !whole
+        .chars()
+        .chain(fraction.chars())
+        .all(|c| c.is_ascii_digit())
+    {
+        anyhow::bail!("Amount {} is not a number", amount);
+    }
+    if fraction.len() > decimals {
+        anyhow::bail!("Amount {} is more precise than one satoshi", amount);
+    }
+    let whole: u64 = if whole.is_empty() { 0 } else { whole.parse()? };
+    let fraction: u64 = if decimals == 0 {
+        0
+    } else {
+        format!("{:0<width$}", fraction, width = decimals).parse()?
+    };
+    let Some(sats) = whole
+        .checked_mul(10u64.pow(decimals as u32))
+        .and_then(|whole| whole.checked_add(fraction))
+    else {
+        anyhow::bail!("Amount is too large");
+    };
+    return Ok(sats);
+}
+
+pub fn format_amount(sats: u64, unit: BitcoinUnit, number_format: NumberFormat) -> String {
+    let amount = format_decimal_amount(sats, unit.decimals(), number_format.decimal_separator);
+    let (whole, fraction) = amount
+        .split_once(number_format.decimal_separator)
+        .unwrap_or((&amount, ""));
+    let whole = group_digits(whole, number_format.group_separator);
+    if fraction.is_empty() {
+        return format!("{} {}", whole, unit.name());
+    }
+    return format!(
+        "{}{}{} {}",
+        whole,
+        number_format.decimal_separator,
+        fraction,
+        unit.name()
+    );
+}
+
+pub fn format_input_amount(sats: u64, unit: BitcoinUnit, number_format: NumberFormat) -> String {
+    return format_decimal_amount(sats, unit.decimals(), number_format.decimal_separator);
+}
+
+// Group separators are only accepted between groups of three digits before the decimal
+// separator, so "0,5" typed with the wrong locale is an error rather than 5
+pub fn strip_group_separators(
+    amount: &str,
+    number_format: NumberFormat,
+) -> Result<String, anyhow::Error> {
+    let (whole, fraction) = match amount.split_once(number_format.decimal_separator) {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (amount, None),
+    };
+    if whole.contains(number_format.group_separator) {
+        let mut groups = whole.split(number_format.group_separator);
+        let first = groups.next().unwrap_or_default();
+        if first.is_empty() || first.len() > 3 || groups.any(|group| group.len() != 3) {
+            anyhow::bail!("Amount {} has a misplaced digit separator", amount);
+        }
+    }
+    if fraction.is_some_and(|fraction| fraction.contains(number_format.group_separator)) {
+        anyhow::bail!("Amount {} has a misplaced digit separator", amount);
+    }
+    let whole: String = whole
+        .chars()
+        .filter(|c| *c != number_format.group_separator)
+        .collect();
+    return Ok(match fraction {
+        Some(fraction) => format!("{}{}{}", whole, number_format.decimal_separator, fraction),
+        None => whole,
+    });
+}
+
+pub fn parse_amount(
+    amount: &str,
+    unit: BitcoinUnit,
+    number_format: NumberFormat,
+) -> Result<u64, anyhow::Error> {
+    let amount = amount.trim().trim_end_matches(unit.name()).trim();
+    let amount = strip_group_separators(amount, number_format)?;
+    return parse_decimal_amount(&amount, unit.decimals(), number_format.decimal_separator);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::units::{format_amount, parse_amount, BitcoinUnit, NumberFormat};
+
+    #[test]
+    fn test_amount_formatting_and_parsing() {
+        let english = NumberFormat::from_locale("en_GB.UTF-8");
+        let german = NumberFormat::from_locale("de_DE.UTF-8");
+
+        assert_eq!(
+            format_amount(123_456_789, BitcoinUnit::Btc, english),
+            "1.23456789 BTC"
+        );
+        assert_eq!(
+            format_amount(1_234_500_000, BitcoinUnit::MilliBtc, german),
+            "12.345 mBTC"
+        );
+        assert_eq!(format_amount(150, BitcoinUnit::Bits, english), "1.5 bits");
+        assert_eq!(
+            format_amount(1_000_000, BitcoinUnit::Sats, english),
+            "1,000,000 sats"
+        );
+
+        assert_eq!(
+            parse_amount("0.001", BitcoinUnit::Btc, english).unwrap(),
+            100_000
+        );
+        assert_eq!(
+            parse_amount("1.000,5", BitcoinUnit::MilliBtc, german).unwrap(),
+            100_050_000
+        );
+        assert_eq!(
+            parse_amount("2,500 sats", BitcoinUnit::Sats, english).unwrap(),
+            2_500
+        );
+        assert!(parse_amount("0.000000001", BitcoinUnit::Btc, english).is_err());
+        assert!(parse_amount("1.5", BitcoinUnit::Sats, english).is_err());
+        assert!(parse_amount("abc", BitcoinUnit::Bits, english).is_err());
+        // A decimal typed with the other locale's separator must not parse as ten times more
+        assert!(parse_amount("0,5", BitcoinUnit::Btc, english).is_err());
+        assert!(parse_amount("0.5", BitcoinUnit::Btc, german).is_err());
+        assert!(parse_amount("1,0000", BitcoinUnit::Sats, english).is_err());
+        assert!(parse_amount("1.5,000", BitcoinUnit::Sats, english).is_err());
+    }
+}
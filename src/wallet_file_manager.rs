@@ -1,12 +1,11 @@
-use bdk::blockchain::Blockchain;
-use bdk::blockchain::ElectrumBlockchain;
+use bdk::bitcoin::psbt::PartiallySignedTransaction;
+use bdk::bitcoin::Txid;
 use bdk::database::MemoryDatabase;
-use bdk::electrum_client::Client;
 
 use bdk::wallet::AddressIndex;
 use bdk::wallet::Wallet;
 use bdk::Balance;
-use bdk::SyncOptions;
+use bdk::LocalUtxo;
 use bdk::TransactionDetails;
 use magic_crypt::MagicCrypt256;
 
@@ -15,18 +14,69 @@ use std::fs;
 use std::fs::File;
 use std::fs::OpenOptions;
 
-use crate::bitcoin_wallet::extract_address_from_transaction;
+use crate::backend::connect_backend;
+use crate::backend::is_offline_error;
+use crate::backend::BackendConfig;
+use crate::backend::ProgressReporter;
+use crate::backend::SyncProgress;
+use crate::backend::WalletBackend;
+use crate::bitcoin_wallet::classify_transaction;
+use crate::bitcoin_wallet::generate_bip84_descriptors;
 use crate::bitcoin_wallet::generate_wallet;
+use crate::bitcoin_wallet::generate_wallet_from_descriptors;
 use crate::bitcoin_wallet::generate_xpriv;
 use crate::bitcoin_wallet::get_transaction_details;
+use crate::bitcoin_wallet::make_fee_bump;
 use crate::bitcoin_wallet::make_transaction;
+use crate::bitcoin_wallet::private_descriptor_string;
+use crate::bitcoin_wallet::validate_single_key_descriptor_pair;
+use crate::bitcoin_wallet::TransactionClassification;
 use crate::bitcoin_wallet::TransactionDirection;
+use crate::bitcoin_wallet::TransactionKind;
+use crate::cost_basis::WalletTransaction;
+use crate::fiat::FiatCurrency;
+use crate::fiat::PriceSource;
+use crate::history_diff::diff_history;
+use crate::history_diff::TransactionState;
+use crate::history_export::in_date_range;
+use crate::history_export::ExportRow;
+use crate::labels::export_bip329;
+use crate::labels::get_label;
+use crate::labels::import_bip329;
+use crate::labels::merge_labels;
+use crate::labels::set_label;
+use crate::labels::Label;
+use crate::labels::LabelType;
+use crate::multisig::combine_multisig_psbts;
+use crate::multisig::extract_finalized_tx;
+use crate::multisig::generate_multisig_descriptors;
+use crate::multisig::make_multisig_psbt;
+use crate::multisig::multisig_policy;
+use crate::multisig::sign_multisig_psbt;
+use crate::payment_request::expiry_timestamp;
+use crate::payment_request::PaymentRequest;
+use crate::policy::compile_policy;
+use crate::policy::make_policy_psbt;
+use crate::policy::spending_path_descriptions;
+use crate::sweep::make_sweep_transaction;
+use crate::sweep::parse_wif_keys;
+use crate::sweep::SweepTransaction;
+use crate::sync_scheduler::DEFAULT_SYNC_INTERVAL_S;
+use crate::transaction_detail::confirmations;
+use crate::transaction_detail::transaction_detail;
+use crate::transaction_detail::TransactionDetail;
+use crate::units::BitcoinUnit;
+use crate::units::NumberFormat;
+use chrono::NaiveDate;
 use directories_next::UserDirs;
 use std::io::Read;
 use std::path::PathBuf;
+use std::str::FromStr;
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::TryLockError;
 use std::thread;
 use std::thread::JoinHandle;
 
@@ -37,6 +87,28 @@ use serde::{Deserialize, Serialize};
 pub struct JsonWalletData {
     pub wallets: Vec<JsonWallet>,
     pub contacts: Vec<JsonWallet>,
+    #[serde(default)]
+    pub backend: BackendConfig,
+    #[serde(default)]
+    pub display_unit: BitcoinUnit,
+    #[serde(default = "NumberFormat::from_environment")]
+    pub number_format: NumberFormat,
+    #[serde(default)]
+    pub fiat_currency: FiatCurrency,
+    #[serde(default)]
+    pub price_source: PriceSource,
+    #[serde(default = "default_sync_interval_s")]
+    pub sync_interval_s: u64,
+    #[serde(default = "default_final_confirmations")]
+    pub final_confirmations: u32,
+}
+
+fn default_sync_interval_s() -> u64 {
+    return DEFAULT_SYNC_INTERVAL_S;
+}
+
+fn default_final_confirmations() -> u32 {
+    return DEFAULT_FINAL_CONFIRMATIONS;
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -47,6 +119,35 @@ pub struct JsonWallet {
     pub wallet_name: String,
     pub balance: Option<Balance>,
     pub sorted_transactions: Option<Vec<TransactionDetails>>,
+    #[serde(default)]
+    pub backup_verified: bool,
+    #[serde(default)]
+    pub external_descriptor: Option<String>,
+    #[serde(default)]
+    pub internal_descriptor: Option<String>,
+    #[serde(default)]
+    pub policy: Option<String>,
+    #[serde(default)]
+    pub payment_requests: Vec<PaymentRequest>,
+    #[serde(default)]
+    pub labels: Vec<Label>,
+    #[serde(default)]
+    pub classifications: HashMap<String, TransactionClassification>,
+    #[serde(default)]
+    pub transaction_states: HashMap<String, TransactionState>,
+    #[serde(default)]
+    pub removed_transactions: Vec<TransactionDetails>,
+    #[serde(default)]
+    pub tip_height: Option<u32>,
+    // Cached from the last sync so the UI never waits on the wallet lock
+    #[serde(default)]
+    pub utxos: Vec<LocalUtxo>,
+    // Unix time each transaction was first seen unconfirmed, keyed by txid
+    #[serde(default)]
+    pub first_seen: HashMap<String, i64>,
+    // Highest receive address index handed out to a payment request
+    #[serde(default)]
+    pub reserved_address_index: Option<u32>,
 }
 #[derive(Copy, Clone)]
 pub enum EntryType {
@@ -55,56 +156,142 @@ pub enum EntryType {
 }
 
 const FILENAME: &str = "wallet.txt";
+pub const DEFAULT_FINAL_CONFIRMATIONS: u32 = 6;
 
-pub struct SyncData {
-    pub pub_key: String,
-    pub balance: Balance,
-    pub transactions: Vec<TransactionDetails>,
+pub enum SyncData {
+    Synced {
+        pub_key: String,
+        balance: Balance,
+        transactions: Vec<TransactionDetails>,
+        utxos: Vec<LocalUtxo>,
+        tip_height: Option<u32>,
+    },
+    Failed {
+        pub_key: String,
+        error: String,
+        offline: bool,
+    },
+    SweepFound {
+        result: Result<SweepTransaction, String>,
+    },
+    FeeEstimated {
+        result: Result<f32, String>,
+    },
 }
 
+type BackendConnection = Arc<Mutex<Option<Arc<dyn WalletBackend>>>>;
+
 pub struct WalletModel {
     pub json_wallet_data: JsonWalletData,
     pub wallet_objs: HashMap<String, Arc<Mutex<Wallet<MemoryDatabase>>>>,
+    // Compiling policies is slow, so the paths are worked out once per loaded wallet
+    spending_paths: HashMap<String, Vec<String>>,
     filepath: PathBuf,
-    blockchain: Arc<ElectrumBlockchain>,
+    backend: BackendConnection,
+    pub sync_progress: SyncProgress,
     pub active_wallet: Option<String>,
     pub key: Option<MagicCrypt256>,
 }
 
 impl JsonWallet {
+    pub fn classification(
+        &self,
+        transaction_details: &TransactionDetails,
+    ) -> Option<&TransactionClassification> {
+        return self
+            .classifications
+            .get(&transaction_details.txid.to_string());
+    }
+
     pub fn get_total(&self) -> u64 {
         match &self.balance {
             None => 0,
             Some(balance) => balance.clone().get_total(),
         }
     }
+
+    pub fn descriptors(&self) -> Result<(String, String), anyhow::Error> {
+        match (
+            &self.external_descriptor,
+            &self.internal_descriptor,
+            &self.priv_key,
+        ) {
+            (Some(external), Some(internal), _) => Ok((external.clone(), internal.clone())),
+            (_, _, Some(priv_key)) => generate_bip84_descriptors(priv_key),
+            _ => anyhow::bail!("Wallet has no descriptors"),
+        }
+    }
+
+    pub fn generate_wallet(&self) -> Result<Wallet<MemoryDatabase>, anyhow::Error> {
+        match &self.priv_key {
+            Some(priv_key) => generate_wallet(priv_key),
+            None => {
+                let (external, internal) = self.descriptors()?;
+                generate_wallet_from_descriptors(&external, &internal)
+            }
+        }
+    }
+
+    pub fn multisig_policy(&self) -> Option<(usize, usize)> {
+        let external = self.external_descriptor.as_ref()?;
+        return multisig_policy(external);
+    }
+
+    pub fn requires_psbt(&self) -> bool {
+        return self.multisig_policy().is_some() || self.policy.is_some();
+    }
+
+    pub fn is_watch_only(&self) -> bool {
+        if self.priv_key.is_some() {
+            return false;
+        }
+        match &self.external_descriptor {
+            Some(descriptor) => !matches!(private_descriptor_string(descriptor), Ok(Some(_))),
+            None => true,
+        }
+    }
 }
 
 impl WalletModel {
     pub fn start_wallet_syncing_worker(
         &self,
         wallet: Arc<Mutex<Wallet<MemoryDatabase>>>,
+        pub_key: &str,
         sync_sender: Sender<SyncData>,
     ) -> JoinHandle<()> {
-        let blockchain = Arc::clone(&self.blockchain);
-        let pub_key = self.get_active_wallet_pub_key();
+        let backend = Arc::clone(&self.backend);
+        let backend_config = self.json_wallet_data.backend.clone();
+        let pub_key = pub_key.to_string();
+        let sync_progress = Arc::clone(&self.sync_progress);
         let handle = thread::spawn(move || {
-            let wallet_locked = wallet.lock().unwrap();
-            wallet_locked
-                .sync(&blockchain, SyncOptions::default())
-                .unwrap();
-
-            let balance = wallet_locked.get_balance().unwrap();
-            let transactions = wallet_locked.list_transactions(true).unwrap();
-            let sync_data = SyncData {
-                pub_key,
-                balance,
-                transactions,
+            let progress = ProgressReporter {
+                pub_key: pub_key.clone(),
+                sync_progress: Arc::clone(&sync_progress),
             };
-
-            sync_sender
-                .send(sync_data)
-                .expect("Failed to send sync data");
+            let result = sync_wallet_data(&wallet, &backend, &backend_config, progress);
+            sync_progress.lock().unwrap().remove(&pub_key);
+            let sync_data = match result {
+                Ok((balance, transactions, utxos, tip_height)) => SyncData::Synced {
+                    pub_key,
+                    balance,
+                    transactions,
+                    utxos,
+                    tip_height,
+                },
+                Err(error) => {
+                    let offline = is_offline_error(&error);
+                    if offline {
+                        // Reconnect on the next attempt rather than reuse a dead socket
+                        *backend.lock().unwrap() = None;
+                    }
+                    SyncData::Failed {
+                        pub_key,
+                        error: error.to_string(),
+                        offline,
+                    }
+                }
+            };
+            let _ = sync_sender.send(sync_data);
         });
         return handle;
     }
@@ -117,23 +304,58 @@ impl WalletModel {
         }
     }
 
-    pub fn sync_current_wallet(&mut self, sync_sender: Sender<SyncData>) -> JoinHandle<()> {
-        let wallet = self.get_active_wallet();
-        let handle = self.start_wallet_syncing_worker(wallet, sync_sender);
-        return handle;
+    pub fn sync_wallet_in_background(
+        &self,
+        pub_key: &str,
+        sync_sender: Sender<SyncData>,
+    ) -> Option<JoinHandle<()>> {
+        let wallet = Arc::clone(self.wallet_objs.get(pub_key)?);
+        let handle = self.start_wallet_syncing_worker(wallet, pub_key, sync_sender);
+        return Some(handle);
+    }
+
+    fn insert_wallet_obj(&mut self, pub_key: String, wallet: Wallet<MemoryDatabase>) {
+        let spending_paths = spending_path_descriptions(&wallet).unwrap_or_default();
+        self.spending_paths.insert(pub_key.clone(), spending_paths);
+        self.wallet_objs
+            .insert(pub_key, Arc::new(Mutex::new(wallet)));
+    }
+
+    pub fn get_active_spending_paths(&self) -> Vec<String> {
+        let Some(pub_key) = &self.active_wallet else {
+            return Vec::new();
+        };
+        return self
+            .spending_paths
+            .get(pub_key)
+            .cloned()
+            .unwrap_or_default();
     }
 
     pub fn new() -> Self {
-        let client = Client::new("ssl://electrum.blockstream.info:60002").unwrap();
-        let blockchain = ElectrumBlockchain::from(client);
+        return Self::with_filepath(
+            get_wallet_path(FILENAME).expect("Documents directory not found"),
+        );
+    }
+
+    pub fn with_filepath(filepath: PathBuf) -> Self {
         let wallet_data = Self {
             json_wallet_data: JsonWalletData {
                 wallets: Vec::new(),
                 contacts: Vec::new(),
+                backend: BackendConfig::default(),
+                display_unit: BitcoinUnit::default(),
+                number_format: NumberFormat::from_environment(),
+                fiat_currency: FiatCurrency::default(),
+                price_source: PriceSource::default(),
+                sync_interval_s: DEFAULT_SYNC_INTERVAL_S,
+                final_confirmations: DEFAULT_FINAL_CONFIRMATIONS,
             },
             wallet_objs: HashMap::new(),
-            filepath: get_wallet_path(FILENAME).expect("Documents directory not found"),
-            blockchain: Arc::new(blockchain),
+            spending_paths: HashMap::new(),
+            filepath,
+            backend: Arc::new(Mutex::new(None)),
+            sync_progress: Arc::new(Mutex::new(HashMap::new())),
             active_wallet: None,
             key: None,
         };
@@ -163,13 +385,14 @@ impl WalletModel {
             .unwrap();
         self.json_wallet_data = serde_json::from_str(&contents)?;
 
-        for wallet in self.json_wallet_data.wallets.iter() {
-            let priv_key = wallet.priv_key.clone().unwrap();
-            let pub_key = wallet.pub_key.clone();
-            self.wallet_objs.insert(
-                pub_key,
-                Arc::new(Mutex::new(generate_wallet(&priv_key).unwrap())),
-            );
+        let wallets: Vec<(String, Wallet<MemoryDatabase>)> = self
+            .json_wallet_data
+            .wallets
+            .iter()
+            .map(|wallet| (wallet.pub_key.clone(), wallet.generate_wallet().unwrap()))
+            .collect();
+        for (pub_key, wallet) in wallets {
+            self.insert_wallet_obj(pub_key, wallet);
         }
         if self.json_wallet_data.wallets.len() > 0 {
             self.active_wallet = Some(self.get_first_wallet_pub_key());
@@ -184,6 +407,19 @@ impl WalletModel {
         pub_key: &str,
         wallet_name: &str,
     ) -> Result<(), Box<dyn std::error::Error>> {
+        self.push_entry(priv_key, mnemonic, pub_key, wallet_name);
+        self.write_to_file()?;
+        Ok(())
+    }
+
+    // Adds the entry without writing, for callers that write once after several changes
+    fn push_entry(
+        &mut self,
+        priv_key: Option<String>,
+        mnemonic: Option<String>,
+        pub_key: &str,
+        wallet_name: &str,
+    ) {
         let json_wallet = JsonWallet {
             pub_key: pub_key.to_string(),
             priv_key: priv_key.clone(),
@@ -191,16 +427,25 @@ impl WalletModel {
             wallet_name: wallet_name.to_string(),
             balance: None,
             sorted_transactions: None,
+            backup_verified: false,
+            external_descriptor: None,
+            internal_descriptor: None,
+            policy: None,
+            payment_requests: Vec::new(),
+            labels: Vec::new(),
+            classifications: HashMap::new(),
+            transaction_states: HashMap::new(),
+            removed_transactions: Vec::new(),
+            tip_height: None,
+            utxos: Vec::new(),
+            first_seen: HashMap::new(),
+            reserved_address_index: None,
         };
 
         match priv_key {
             Some(_) => self.json_wallet_data.wallets.push(json_wallet),
             None => self.json_wallet_data.contacts.push(json_wallet),
         }
-
-        self.write_to_file()?;
-
-        Ok(())
     }
 
     pub fn add_wallet(
@@ -208,6 +453,7 @@ impl WalletModel {
         priv_key: &str,
         mnemonic: &str,
         wallet_name: &str,
+        backup_verified: bool,
     ) -> Result<(), Box<dyn std::error::Error>> {
         let wallet = generate_wallet(&priv_key).unwrap();
         let pub_key = wallet
@@ -231,14 +477,120 @@ impl WalletModel {
             &pub_key,
             saved_wallet_name,
         )?;
+        if backup_verified {
+            self.set_backup_verified(&pub_key)?;
+        }
 
-        self.wallet_objs
-            .insert(pub_key.to_string(), Arc::new(Mutex::new(wallet)));
+        self.insert_wallet_obj(pub_key.to_string(), wallet);
         self.active_wallet = Some(pub_key.to_string());
 
         return Ok(());
     }
 
+    pub fn add_bip85_wallet(
+        &mut self,
+        mnemonic: &str,
+        wallet_name: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let priv_key = generate_xpriv(mnemonic)?.to_string();
+        if self.wallets_contain_wallet(&priv_key) {
+            return Err("Wallet already exists".into());
+        }
+        self.add_wallet(&priv_key, mnemonic, wallet_name, false)
+    }
+
+    pub fn add_descriptor_wallet(
+        &mut self,
+        external_descriptor: &str,
+        internal_descriptor: &str,
+        wallet_name: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let external_descriptor = external_descriptor.trim();
+        let internal_descriptor = internal_descriptor.trim();
+        validate_single_key_descriptor_pair(external_descriptor, internal_descriptor)?;
+        self.insert_descriptor_wallet(external_descriptor, internal_descriptor, wallet_name, None)
+    }
+
+    pub fn add_multisig_wallet(
+        &mut self,
+        threshold: usize,
+        cosigner_keys: &[String],
+        wallet_name: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let (external_descriptor, internal_descriptor) =
+            generate_multisig_descriptors(threshold, cosigner_keys)?;
+        self.insert_descriptor_wallet(
+            &external_descriptor,
+            &internal_descriptor,
+            wallet_name,
+            None,
+        )
+    }
+
+    pub fn add_policy_wallet(
+        &mut self,
+        policy: &str,
+        policy_keys: &[(String, String)],
+        wallet_name: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let (external_descriptor, internal_descriptor) = compile_policy(policy, policy_keys)?;
+        self.insert_descriptor_wallet(
+            &external_descriptor,
+            &internal_descriptor,
+            wallet_name,
+            Some(policy.trim().to_string()),
+        )
+    }
+
+    fn insert_descriptor_wallet(
+        &mut self,
+        external_descriptor: &str,
+        internal_descriptor: &str,
+        wallet_name: &str,
+        policy: Option<String>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let wallet = generate_wallet_from_descriptors(external_descriptor, internal_descriptor)?;
+        let pub_key = wallet.get_address(AddressIndex::Peek(0))?.to_string();
+        if self.wallets_contain_wallet(&pub_key) {
+            return Err("Wallet already exists".into());
+        }
+        if self.contacts_contain_wallet(&pub_key) {
+            self.delete_contact(&pub_key)?;
+        }
+        let mut saved_wallet_name = wallet_name;
+        if wallet_name.len() == 0 {
+            saved_wallet_name = &pub_key;
+        }
+
+        self.json_wallet_data.wallets.push(JsonWallet {
+            pub_key: pub_key.clone(),
+            priv_key: None,
+            mnemonic: None,
+            wallet_name: saved_wallet_name.to_string(),
+            balance: None,
+            sorted_transactions: None,
+            backup_verified: true,
+            external_descriptor: Some(external_descriptor.to_string()),
+            internal_descriptor: Some(internal_descriptor.to_string()),
+            policy: policy,
+            payment_requests: Vec::new(),
+            labels: Vec::new(),
+            classifications: HashMap::new(),
+            transaction_states: HashMap::new(),
+            removed_transactions: Vec::new(),
+            tip_height: None,
+            utxos: Vec::new(),
+            first_seen: HashMap::new(),
+            reserved_address_index: None,
+        });
+        self.write_to_file()?;
+
+        self.insert_wallet_obj(pub_key.clone(), wallet);
+        self.active_wallet = Some(pub_key);
+
+        return Ok(());
+    }
+
     pub fn delete_from_wallet(&mut self, pub_key: &str) -> Result<(), Box<dyn std::error::Error>> {
         let index = self
             .json_wallet_data
@@ -266,6 +618,7 @@ impl WalletModel {
         self.delete_from_wallet(pub_key)?;
 
         self.wallet_objs.remove(pub_key);
+        self.spending_paths.remove(pub_key);
         self.active_wallet = Some(self.json_wallet_data.wallets[0].pub_key.clone());
         return Ok(());
     }
@@ -318,36 +671,304 @@ impl WalletModel {
             Some(wallet_name.to_string()),
             None,
             None,
+            None,
         )?;
         return Ok(());
     }
 
+    pub fn set_backup_verified(&mut self, pub_key: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.set_wallet_data(EntryType::Wallet, pub_key, None, None, None, Some(true))?;
+        return Ok(());
+    }
+
     pub fn sync_wallet(
         &mut self,
         pub_key: &str,
         balance: Option<Balance>,
         transactions: Option<Vec<TransactionDetails>>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        self.set_wallet_data(
-            EntryType::Wallet,
-            pub_key,
-            None,
-            balance,
-            transactions.clone(),
-        )?;
-        for transaction_details in transactions.clone().unwrap() {
-            let (transaction_direction, pub_key, _, _, _, _) =
-                get_transaction_details(transaction_details);
-            if self.contains_wallet(&pub_key) {
+        utxos: Option<Vec<LocalUtxo>>,
+        tip_height: Option<u32>,
+    ) -> Result<Vec<Txid>, Box<dyn std::error::Error>> {
+        let lost_confirmations = match &transactions {
+            Some(transactions) => {
+                let lost_confirmations = self.update_history_states(pub_key, transactions);
+                self.update_first_seen(pub_key, transactions, tip_height);
+                lost_confirmations
+            }
+            None => Vec::new(),
+        };
+        if let Some(wallet) = self
+            .json_wallet_data
+            .wallets
+            .iter_mut()
+            .find(|wallet| wallet.pub_key == pub_key)
+        {
+            if let Some(balance) = balance {
+                wallet.balance = Some(balance);
+            }
+            if let Some(utxos) = utxos {
+                wallet.utxos = utxos;
+            }
+            if let Some(transactions) = transactions.clone() {
+                wallet.sorted_transactions = Some(transactions);
+            }
+        }
+        if let Some(transactions) = &transactions {
+            self.update_payment_requests(pub_key, transactions);
+            self.update_classifications(pub_key, transactions);
+            self.add_counterparty_contacts(pub_key);
+        }
+        self.write_to_file()?;
+        return Ok(lost_confirmations);
+    }
+
+    fn add_counterparty_contacts(&mut self, pub_key: &str) {
+        let classifications = self
+            .json_wallet_data
+            .wallets
+            .iter()
+            .find(|wallet| wallet.pub_key == pub_key)
+            .map(|wallet| wallet.classifications.clone())
+            .unwrap_or_default();
+        for classification in classifications.values() {
+            if classification.kind != TransactionKind::Outgoing
+                && classification.kind != TransactionKind::MultiRecipient
+            {
                 continue;
             }
-            if transaction_direction == TransactionDirection::To {
-                let _ = self.add_contact(&pub_key, &pub_key);
+            for counterparty in classification.counterparties.iter() {
+                if !self.contains_wallet(counterparty) {
+                    self.push_entry(None, None, counterparty, counterparty);
+                }
+            }
+        }
+    }
+
+    fn update_first_seen(
+        &mut self,
+        pub_key: &str,
+        transactions: &[TransactionDetails],
+        tip_height: Option<u32>,
+    ) {
+        let Some(wallet) = self
+            .json_wallet_data
+            .wallets
+            .iter_mut()
+            .find(|wallet| wallet.pub_key == pub_key)
+        else {
+            return;
+        };
+        let now = chrono::Local::now().timestamp();
+        for transaction_details in transactions.iter() {
+            if transaction_details.confirmation_time.is_none() {
+                wallet
+                    .first_seen
+                    .entry(transaction_details.txid.to_string())
+                    .or_insert(now);
             }
         }
+        let known_txids: Vec<String> = transactions
+            .iter()
+            .chain(wallet.removed_transactions.iter())
+            .map(|transaction_details| transaction_details.txid.to_string())
+            .collect();
+        wallet
+            .first_seen
+            .retain(|txid, _| known_txids.contains(txid));
+        if tip_height.is_some() {
+            wallet.tip_height = tip_height;
+        }
+    }
+
+    // All the sync updates are written to file once by sync_wallet
+    fn update_history_states(
+        &mut self,
+        pub_key: &str,
+        transactions: &[TransactionDetails],
+    ) -> Vec<Txid> {
+        let Some(wallet) = self
+            .json_wallet_data
+            .wallets
+            .iter_mut()
+            .find(|wallet| wallet.pub_key == pub_key)
+        else {
+            return Vec::new();
+        };
+        let diff = diff_history(
+            wallet.sorted_transactions.as_deref().unwrap_or_default(),
+            &wallet.removed_transactions,
+            &wallet.transaction_states,
+            transactions,
+        );
+        wallet.transaction_states = diff.states;
+        wallet.removed_transactions = diff.removed;
+        return diff.lost_confirmations;
+    }
+
+    fn update_classifications(&mut self, pub_key: &str, transactions: &[TransactionDetails]) {
+        let Some(wallet) = self.wallet_objs.get(pub_key).cloned() else {
+            return;
+        };
+        let removed_transactions = self
+            .json_wallet_data
+            .wallets
+            .iter()
+            .find(|wallet| wallet.pub_key == pub_key)
+            .map(|wallet| wallet.removed_transactions.clone())
+            .unwrap_or_default();
+        // A sync of this wallet that started since keeps the lock, and classifies on its return
+        let Ok(wallet) = wallet.try_lock() else {
+            return;
+        };
+        let classifications = transactions
+            .iter()
+            .chain(removed_transactions.iter())
+            .map(|transaction_details| {
+                let classification = classify_transaction(
+                    transaction_details,
+                    |script| wallet.is_mine(script).unwrap_or(false),
+                    |outpoint| {
+                        wallet
+                            .get_tx(&outpoint.txid, true)
+                            .ok()
+                            .flatten()
+                            .and_then(|details| details.transaction)
+                            .and_then(|tx| tx.output.get(outpoint.vout as usize).cloned())
+                    },
+                );
+                (transaction_details.txid.to_string(), classification)
+            })
+            .collect();
+        drop(wallet);
+        let Some(wallet_data) = self
+            .json_wallet_data
+            .wallets
+            .iter_mut()
+            .find(|wallet| wallet.pub_key == pub_key)
+        else {
+            return;
+        };
+        wallet_data.classifications = classifications;
+    }
+
+    fn update_payment_requests(&mut self, pub_key: &str, transactions: &[TransactionDetails]) {
+        let Some(wallet) = self
+            .json_wallet_data
+            .wallets
+            .iter_mut()
+            .find(|wallet| wallet.pub_key == pub_key)
+        else {
+            return;
+        };
+        for payment_request in wallet.payment_requests.iter_mut() {
+            payment_request.update_received(transactions);
+        }
+    }
+
+    pub fn add_payment_request(
+        &mut self,
+        amount: u64,
+        memo: &str,
+        expiry_hours: i64,
+    ) -> Result<PaymentRequest, Box<dyn std::error::Error>> {
+        if amount == 0 {
+            return Err("Requested amount must be greater than zero".into());
+        }
+        let created_at = chrono::Local::now().timestamp();
+        let Some(expires_at) = expiry_timestamp(created_at, expiry_hours) else {
+            return Err("Expiry must be a positive number of hours".into());
+        };
+        let pub_key = self.get_active_wallet_pub_key();
+        let (address, index) = self.reserve_address(&pub_key)?;
+        let payment_request = PaymentRequest {
+            address,
+            amount,
+            memo: memo.trim().to_string(),
+            created_at,
+            expires_at,
+            received: 0,
+            received_confirmed: 0,
+            txids: Vec::new(),
+        };
+        let (_, wallet_data) = self.get_wallet_data(&pub_key);
+        wallet_data.payment_requests.push(payment_request.clone());
+        wallet_data.reserved_address_index = Some(index);
+        self.write_to_file()?;
+        return Ok(payment_request);
+    }
+
+    // Wallets are rebuilt in memory on start up, so bdk's index restarts at 0. Index 0 is the
+    // wallet's pub_key shown on the Receive panel, so requests start past it and past any
+    // index already reserved
+    fn reserve_address(
+        &mut self,
+        pub_key: &str,
+    ) -> Result<(String, u32), Box<dyn std::error::Error>> {
+        let (_, wallet_data) = self.get_wallet_data(pub_key);
+        let reserved_addresses: Vec<String> = wallet_data
+            .payment_requests
+            .iter()
+            .map(|payment_request| payment_request.address.clone())
+            .collect();
+        let first_free = wallet_data
+            .reserved_address_index
+            .map_or(1, |index| index + 1);
+        let Some(wallet) = self.wallet_objs.get(pub_key) else {
+            return Err("Wallet not loaded".into());
+        };
+        let wallet = lock_wallet(wallet)?;
+        let mut address_info = wallet.get_address(AddressIndex::New)?;
+        if address_info.index < first_free {
+            address_info = wallet.get_address(AddressIndex::Reset(first_free))?;
+        }
+        loop {
+            let address = address_info.address.to_string();
+            if address != pub_key && !reserved_addresses.contains(&address) {
+                return Ok((address, address_info.index));
+            }
+            address_info = wallet.get_address(AddressIndex::New)?;
+        }
+    }
+
+    pub fn delete_payment_request(
+        &mut self,
+        address: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let pub_key = self.get_active_wallet_pub_key();
+        let (_, wallet_data) = self.get_wallet_data(&pub_key);
+        wallet_data
+            .payment_requests
+            .retain(|payment_request| payment_request.address != address);
+        self.write_to_file()?;
+        return Ok(());
+    }
+
+    pub fn set_label(
+        &mut self,
+        label_type: LabelType,
+        reference: &str,
+        label: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let pub_key = self.get_active_wallet_pub_key();
+        let (_, wallet_data) = self.get_wallet_data(&pub_key);
+        set_label(&mut wallet_data.labels, label_type, reference, label);
+        self.write_to_file()?;
         return Ok(());
     }
 
+    pub fn import_labels(&mut self, contents: &str) -> Result<usize, Box<dyn std::error::Error>> {
+        let imported = import_bip329(contents)?;
+        let pub_key = self.get_active_wallet_pub_key();
+        let (_, wallet_data) = self.get_wallet_data(&pub_key);
+        let count = merge_labels(&mut wallet_data.labels, imported);
+        self.write_to_file()?;
+        return Ok(count);
+    }
+
+    pub fn export_labels(&self) -> Result<String, Box<dyn std::error::Error>> {
+        return Ok(export_bip329(&self.get_active_wallet_data().labels)?);
+    }
+
     pub fn get_first_wallet_pub_key(&mut self) -> String {
         let first_wallet = self.json_wallet_data.wallets[0].pub_key.clone().to_string();
         return first_wallet;
@@ -399,6 +1020,7 @@ impl WalletModel {
         wallet_name: Option<String>,
         balance: Option<Balance>,
         transactions: Option<Vec<TransactionDetails>>,
+        backup_verified: Option<bool>,
     ) -> Result<(), Box<dyn std::error::Error>> {
         let mut wallet = None;
         match entry_type {
@@ -440,6 +1062,10 @@ impl WalletModel {
             wallet.sorted_transactions = Some(transactions);
         }
 
+        if let Some(backup_verified) = backup_verified {
+            wallet.backup_verified = backup_verified;
+        }
+
         self.write_to_file()?;
 
         return Ok(());
@@ -461,10 +1087,292 @@ impl WalletModel {
             })
     }
 
-    pub fn send_transaction(&mut self, recipient_address: &str, amount: u64) {
+    pub fn send_transaction(
+        &mut self,
+        recipient_address: &str,
+        amount: u64,
+    ) -> Result<Txid, Box<dyn std::error::Error>> {
+        let wallet = self.get_active_wallet();
+        let transaction = make_transaction(&*lock_wallet(&wallet)?, recipient_address, amount)?;
+        self.backend()?.broadcast(&transaction)?;
+        return Ok(transaction.txid());
+    }
+
+    // Replaces an unconfirmed send with one paying a higher fee rate
+    pub fn bump_fee(
+        &mut self,
+        txid: &str,
+        fee_rate: f32,
+    ) -> Result<Txid, Box<dyn std::error::Error>> {
+        let txid = Txid::from_str(txid)?;
+        let wallet = self.get_active_wallet();
+        let transaction = make_fee_bump(&*lock_wallet(&wallet)?, txid, fee_rate)?;
+        self.backend()?.broadcast(&transaction)?;
+        return Ok(transaction.txid());
+    }
+
+    pub fn create_multisig_psbt(
+        &mut self,
+        recipient_address: &str,
+        amount: u64,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let wallet = self.get_active_wallet();
+        let psbt = make_multisig_psbt(&*lock_wallet(&wallet)?, recipient_address, amount)?;
+        return Ok(psbt.to_string());
+    }
+
+    pub fn create_policy_psbt(
+        &mut self,
+        recipient_address: &str,
+        amount: u64,
+        spending_path: usize,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let wallet = self.get_active_wallet();
+        let psbt = make_policy_psbt(
+            &*lock_wallet(&wallet)?,
+            recipient_address,
+            amount,
+            spending_path,
+        )?;
+        return Ok(psbt.to_string());
+    }
+
+    pub fn sign_multisig_psbt(&mut self, psbt: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let wallet = self.get_active_wallet();
+        let psbt = sign_multisig_psbt(&*lock_wallet(&wallet)?, psbt)?;
+        return Ok(psbt.to_string());
+    }
+
+    pub fn combine_multisig_psbts(
+        &mut self,
+        psbt: &str,
+        other_psbt: &str,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let wallet = self.get_active_wallet();
+        let psbt = combine_multisig_psbts(&*lock_wallet(&wallet)?, psbt, other_psbt)?;
+        return Ok(psbt.to_string());
+    }
+
+    pub fn broadcast_psbt(&mut self, psbt: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let psbt = PartiallySignedTransaction::from_str(psbt.trim())?;
+        self.backend()?.broadcast(&extract_finalized_tx(&psbt)?)?;
+        return Ok(());
+    }
+
+    fn backend(&self) -> Result<Arc<dyn WalletBackend>, bdk::Error> {
+        return connect_cached_backend(&self.backend, &self.json_wallet_data.backend);
+    }
+
+    pub fn set_display_format(
+        &mut self,
+        display_unit: BitcoinUnit,
+        number_format: NumberFormat,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.json_wallet_data.display_unit = display_unit;
+        self.json_wallet_data.number_format = number_format;
+        self.write_to_file()?;
+        return Ok(());
+    }
+
+    pub fn set_fiat_currency(
+        &mut self,
+        fiat_currency: FiatCurrency,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.json_wallet_data.fiat_currency = fiat_currency;
+        self.write_to_file()?;
+        return Ok(());
+    }
+
+    pub fn set_price_source(
+        &mut self,
+        price_source: PriceSource,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.json_wallet_data.price_source = price_source;
+        self.write_to_file()?;
+        return Ok(());
+    }
+
+    pub fn set_sync_interval(
+        &mut self,
+        sync_interval_s: u64,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.json_wallet_data.sync_interval_s = sync_interval_s;
+        self.write_to_file()?;
+        return Ok(());
+    }
+
+    pub fn set_final_confirmations(
+        &mut self,
+        final_confirmations: u32,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.json_wallet_data.final_confirmations = final_confirmations;
+        self.write_to_file()?;
+        return Ok(());
+    }
+
+    pub fn set_backend(
+        &mut self,
+        backend_config: BackendConfig,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.json_wallet_data.backend = backend_config;
+        *self.backend.lock().unwrap() = None;
+        self.write_to_file()?;
+        return Ok(());
+    }
+
+    pub fn estimate_fee_rate_in_background(
+        &self,
+        target: usize,
+        sync_sender: Sender<SyncData>,
+    ) -> JoinHandle<()> {
+        let backend = Arc::clone(&self.backend);
+        let backend_config = self.json_wallet_data.backend.clone();
+        return thread::spawn(move || {
+            let result = connect_cached_backend(&backend, &backend_config)
+                .and_then(|backend| backend.estimate_fee(target))
+                .map(|fee_rate| fee_rate.as_sat_per_vb())
+                .map_err(|error| error.to_string());
+            let _ = sync_sender.send(SyncData::FeeEstimated { result });
+        });
+    }
+
+    // Syncing a wallet per key and script type takes a while, so it runs on a worker
+    pub fn find_sweep_funds_in_background(
+        &self,
+        wif_keys: &str,
+        fee_rate: f32,
+        sync_sender: Sender<SyncData>,
+    ) -> Result<JoinHandle<()>, Box<dyn std::error::Error>> {
+        let wif_keys = parse_wif_keys(wif_keys);
+        if wif_keys.is_empty() {
+            return Err("No private keys entered".into());
+        }
         let wallet = self.get_active_wallet();
-        let transaction = make_transaction(&wallet.lock().unwrap(), recipient_address, amount);
-        self.blockchain.broadcast(&transaction).unwrap();
+        let backend = Arc::clone(&self.backend);
+        let backend_config = self.json_wallet_data.backend.clone();
+        let handle = thread::spawn(move || {
+            let result = find_sweep_funds(&wallet, &backend, &backend_config, &wif_keys, fee_rate)
+                .map_err(|error| error.to_string());
+            let _ = sync_sender.send(SyncData::SweepFound { result });
+        });
+        return Ok(handle);
+    }
+
+    pub fn broadcast_sweep_transaction(
+        &mut self,
+        sweep_transaction: &SweepTransaction,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.backend()?.broadcast(&sweep_transaction.transaction)?;
+        return Ok(());
+    }
+
+    pub fn transaction_detail(
+        &self,
+        txid: &str,
+    ) -> Result<TransactionDetail, Box<dyn std::error::Error>> {
+        let wallet_data = self.get_active_wallet_data();
+        let Some(transaction_details) = wallet_data
+            .sorted_transactions
+            .iter()
+            .flatten()
+            .chain(wallet_data.removed_transactions.iter())
+            .find(|transaction_details| transaction_details.txid.to_string() == txid)
+        else {
+            return Err(format!("Transaction {} not found", txid).into());
+        };
+        let wallet = self.get_active_wallet();
+        let detail = transaction_detail(
+            &*lock_wallet(&wallet)?,
+            transaction_details,
+            wallet_data.tip_height,
+        )?;
+        return Ok(detail);
+    }
+
+    pub fn history_export_rows(
+        &self,
+        all_wallets: bool,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+        height: Option<u32>,
+    ) -> Vec<ExportRow> {
+        let active_wallet_pub_key = self.get_active_wallet_pub_key();
+        let mut rows = Vec::new();
+        for wallet in self.json_wallet_data.wallets.iter() {
+            if !all_wallets && wallet.pub_key != active_wallet_pub_key {
+                continue;
+            }
+            for transaction_details in wallet.sorted_transactions.iter().flatten() {
+                let timestamp = transaction_details
+                    .confirmation_time
+                    .as_ref()
+                    .map(|confirmation_time| confirmation_time.timestamp);
+                if !in_date_range(timestamp, from, to) {
+                    continue;
+                }
+                let (direction, address, txid, transaction_total, fee, confirmation_time) =
+                    get_transaction_details(
+                        transaction_details.clone(),
+                        wallet.classification(transaction_details),
+                    );
+                let (direction, amount_sats) = match direction {
+                    TransactionDirection::To => {
+                        ("Sent", transaction_total.unsigned_abs().saturating_sub(fee))
+                    }
+                    TransactionDirection::From => ("Received", transaction_total.unsigned_abs()),
+                };
+                let confirmations = confirmations(
+                    confirmation_time
+                        .as_ref()
+                        .map(|confirmation_time| confirmation_time.height),
+                    height,
+                );
+                let label = get_label(&wallet.labels, LabelType::Tx, &txid)
+                    .map(|label| label.to_string())
+                    .or_else(|| {
+                        wallet
+                            .payment_requests
+                            .iter()
+                            .find(|payment_request| payment_request.txids.contains(&txid))
+                            .map(|payment_request| payment_request.memo.clone())
+                    })
+                    .unwrap_or_default();
+                rows.push(ExportRow {
+                    wallet: wallet.wallet_name.clone(),
+                    counterparty: self.get_wallet_name(&address).unwrap_or(address),
+                    txid,
+                    timestamp,
+                    direction: direction.to_string(),
+                    amount_sats,
+                    fee_sats: fee,
+                    label,
+                    confirmations,
+                });
+            }
+        }
+        rows.sort_by_key(|row| std::cmp::Reverse(row.timestamp.unwrap_or(u64::MAX)));
+        return rows;
+    }
+
+    pub fn cost_basis_transactions(&self) -> Vec<WalletTransaction> {
+        let mut transactions = Vec::new();
+        for wallet in self.json_wallet_data.wallets.iter() {
+            for transaction_details in wallet.sorted_transactions.iter().flatten() {
+                // Unconfirmed transactions have no date to price them at yet
+                let Some(confirmation_time) = &transaction_details.confirmation_time else {
+                    continue;
+                };
+                transactions.push(WalletTransaction {
+                    wallet_id: wallet.pub_key.clone(),
+                    wallet_name: wallet.wallet_name.clone(),
+                    txid: transaction_details.txid.to_string(),
+                    timestamp: confirmation_time.timestamp,
+                    net_sats: transaction_details.received as i64 - transaction_details.sent as i64,
+                    fee: transaction_details.fee.unwrap_or(0),
+                });
+            }
+        }
+        return transactions;
     }
 
     pub fn validate_password(&mut self, password: &str) -> bool {
@@ -509,8 +1417,10 @@ impl WalletModel {
         for wallet in self.json_wallet_data.wallets.iter() {
             if let Some(transactions) = &wallet.sorted_transactions {
                 for transaction in transactions.iter() {
-                    let (_, found_pub_key, _, _, _, _) =
-                        get_transaction_details(transaction.clone());
+                    let (_, found_pub_key, _, _, _, _) = get_transaction_details(
+                        transaction.clone(),
+                        wallet.classification(transaction),
+                    );
                     if found_pub_key == pub_key {
                         if last_transaction.is_none()
                             || last_transaction.unwrap().confirmation_time
@@ -526,6 +1436,82 @@ impl WalletModel {
     }
 }
 
+// Sync workers hold the wallet lock for the whole sync, which can take hours for a first
+// compact filter sync, so the UI never waits on it
+fn lock_wallet(
+    wallet: &Mutex<Wallet<MemoryDatabase>>,
+) -> Result<MutexGuard<'_, Wallet<MemoryDatabase>>, Box<dyn std::error::Error>> {
+    match wallet.try_lock() {
+        Ok(wallet) => return Ok(wallet),
+        Err(TryLockError::WouldBlock) => {
+            return Err("The wallet is syncing, try again once the sync finishes".into())
+        }
+        Err(TryLockError::Poisoned(error)) => return Err(error.to_string().into()),
+    }
+}
+
+fn connect_cached_backend(
+    backend: &Mutex<Option<Arc<dyn WalletBackend>>>,
+    backend_config: &BackendConfig,
+) -> Result<Arc<dyn WalletBackend>, bdk::Error> {
+    if let Some(backend) = backend.lock().unwrap().as_ref() {
+        return Ok(Arc::clone(backend));
+    }
+    // Connecting can take until the timeout, so other callers aren't kept waiting on the lock
+    let connected = connect_backend(backend_config)?;
+    let mut backend = backend.lock().unwrap();
+    // Another thread may have connected in the meantime
+    if let Some(backend) = backend.as_ref() {
+        return Ok(Arc::clone(backend));
+    }
+    *backend = Some(Arc::clone(&connected));
+    return Ok(connected);
+}
+
+fn find_sweep_funds(
+    wallet: &Mutex<Wallet<MemoryDatabase>>,
+    backend: &Mutex<Option<Arc<dyn WalletBackend>>>,
+    backend_config: &BackendConfig,
+    wif_keys: &[String],
+    fee_rate: f32,
+) -> Result<SweepTransaction, anyhow::Error> {
+    // A change address can never be one handed out to a payment request
+    let destination = wallet
+        .lock()
+        .unwrap()
+        .get_internal_address(AddressIndex::New)?
+        .address;
+    let backend = connect_cached_backend(backend, backend_config)?;
+    return make_sweep_transaction(&*backend, wif_keys, &destination, fee_rate);
+}
+
+fn sync_wallet_data(
+    wallet: &Mutex<Wallet<MemoryDatabase>>,
+    backend: &Mutex<Option<Arc<dyn WalletBackend>>>,
+    backend_config: &BackendConfig,
+    progress: ProgressReporter,
+) -> Result<
+    (
+        Balance,
+        Vec<TransactionDetails>,
+        Vec<LocalUtxo>,
+        Option<u32>,
+    ),
+    bdk::Error,
+> {
+    let backend = connect_cached_backend(backend, backend_config)?;
+    let wallet = wallet.lock().unwrap();
+    backend.sync(&wallet, Some(Box::new(progress)))?;
+    // Confirmation counts are optional, so a failed tip lookup doesn't fail the sync
+    let tip_height = backend.get_height().ok();
+    return Ok((
+        wallet.get_balance()?,
+        wallet.list_transactions(true)?,
+        wallet.list_unspent()?,
+        tip_height,
+    ));
+}
+
 pub fn encryption_test() {
     let mc = new_magic_crypt!("magickey", 256);
 
@@ -549,8 +1535,65 @@ pub fn get_wallet_path(wallet_file_name: &str) -> Option<PathBuf> {
 
 #[cfg(test)]
 mod tests {
+    use bdk::bitcoin::hashes::Hash;
+    use bdk::bitcoin::Txid;
+    use bdk::{BlockTime, TransactionDetails};
+
+    use std::path::PathBuf;
+
+    use crate::wallet_file_manager::WalletModel;
+
     #[test]
     fn working() {
         assert!(true);
     }
+
+    fn transaction(seed: u8, height: Option<u32>) -> TransactionDetails {
+        return TransactionDetails {
+            txid: Txid::hash(&[seed]),
+            transaction: None,
+            received: 1_000,
+            sent: 0,
+            fee: None,
+            confirmation_time: height.map(|height| BlockTime {
+                height,
+                timestamp: 0,
+            }),
+        };
+    }
+
+    #[test]
+    fn test_update_first_seen() {
+        let mut wallet_model = WalletModel::with_filepath(PathBuf::from("unused.txt"));
+        wallet_model.push_entry(Some("priv".to_string()), None, "pub", "Wallet");
+        let unconfirmed = transaction(1, None);
+        let confirmed = transaction(2, Some(100));
+        let removed = transaction(3, None);
+        let forgotten = transaction(4, None);
+        {
+            let (_, wallet) = wallet_model.get_wallet_data("pub");
+            wallet.removed_transactions = vec![removed.clone()];
+            for transaction_details in [&confirmed, &removed, &forgotten] {
+                wallet
+                    .first_seen
+                    .insert(transaction_details.txid.to_string(), 1);
+            }
+        }
+
+        wallet_model.update_first_seen("pub", &[unconfirmed.clone(), confirmed.clone()], Some(120));
+        let (_, wallet) = wallet_model.get_wallet_data("pub");
+        assert!(wallet.first_seen[&unconfirmed.txid.to_string()] > 1);
+        assert_eq!(wallet.first_seen[&confirmed.txid.to_string()], 1);
+        assert_eq!(wallet.first_seen[&removed.txid.to_string()], 1);
+        assert!(!wallet.first_seen.contains_key(&forgotten.txid.to_string()));
+        assert_eq!(wallet.tip_height, Some(120));
+
+        // A sync that couldn't fetch the tip keeps the last known height
+        wallet_model.update_first_seen("pub", &[confirmed], None);
+        let (_, wallet) = wallet_model.get_wallet_data("pub");
+        assert_eq!(wallet.tip_height, Some(120));
+        assert!(!wallet
+            .first_seen
+            .contains_key(&unconfirmed.txid.to_string()));
+    }
 }